@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
 use std::sync::RwLock;
 
-use crate::midi::{KeyMessage, MidiMessageTypes};
+use crate::midi::{InputMessage, KeyMessage, MidiMessageTypes, Pedal, PedalMessage, PedalState};
+use crate::music::{note_spans, NoteSpan};
 
 pub enum ControlMessage {
     Heartbeat,
@@ -8,8 +10,252 @@ pub enum ControlMessage {
     Print,
 }
 
+/// Keys are stored as a bitmask, bit n set means MIDI key n
+pub fn keys_in_mask(mask: u128) -> Vec<u8> {
+    (0..128u8).filter(|key| mask & (1 << key) != 0).collect()
+}
+
+/// The bit for `key` in a mask, none for anything above the MIDI range
+fn key_bit(key: u8) -> u128 {
+    1u128.checked_shl(u32::from(key)).unwrap_or(0)
+}
+
+/// Replays key and pedal messages to work out which notes are sounding.
+///
+/// A note is sounding if its key is held down, if it was released while the
+/// sustain pedal was down (half-pedal counts as down), or if it was held when
+/// the sostenuto pedal went down and that pedal is still down.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SoundingState {
+    held: u128,
+    sustained: u128,
+    sostenuto_captured: u128,
+    sustain: u8,
+    sostenuto: u8,
+    soft: u8,
+}
+
+impl SoundingState {
+    pub fn apply_key(&mut self, msg: &KeyMessage) {
+        let bit = key_bit(msg.key);
+        match msg.message_type {
+            MidiMessageTypes::NoteOn => self.held |= bit,
+            MidiMessageTypes::NoteOff => {
+                self.held &= !bit;
+                if self.pedal_state(Pedal::Sustain) != PedalState::Up {
+                    self.sustained |= bit;
+                }
+            }
+        }
+    }
+
+    pub fn apply_pedal(&mut self, msg: &PedalMessage) {
+        let was_up = self.pedal_state(msg.pedal) == PedalState::Up;
+        let is_up = msg.state() == PedalState::Up;
+        match msg.pedal {
+            Pedal::Sustain => {
+                self.sustain = msg.value;
+                if is_up {
+                    self.sustained = 0;
+                }
+            }
+            Pedal::Sostenuto => {
+                self.sostenuto = msg.value;
+                if is_up {
+                    self.sostenuto_captured = 0;
+                } else if was_up {
+                    self.sostenuto_captured = self.held;
+                }
+            }
+            Pedal::Soft => self.soft = msg.value,
+        }
+    }
+
+    /// Keys that are physically held down
+    pub fn held(&self) -> u128 {
+        self.held
+    }
+
+    /// Keys that are held down or kept ringing by a pedal
+    pub fn sounding(&self) -> u128 {
+        self.held | self.sustained | self.sostenuto_captured
+    }
+
+    pub fn pedal_value(&self, pedal: Pedal) -> u8 {
+        match pedal {
+            Pedal::Sustain => self.sustain,
+            Pedal::Sostenuto => self.sostenuto,
+            Pedal::Soft => self.soft,
+        }
+    }
+
+    pub fn pedal_state(&self, pedal: Pedal) -> PedalState {
+        PedalState::from_value(self.pedal_value(pedal))
+    }
+}
+
+/// Tuning for the fuzzy time queries on KeyDb. All times are in microseconds,
+/// same as KeyMessage timestamps.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyDbConfig {
+    /// Width of a time bucket in the held-key index
+    pub bucket_micros: u64,
+    /// Messages this close together are treated as simultaneous, e.g. with the
+    /// default something at t=1.004s counts as played at t=1s
+    pub simultaneity_tolerance_micros: u64,
+    /// The longest gap between key downs that still counts as the same run
+    pub run_gap_micros: u64,
+}
+
+impl Default for KeyDbConfig {
+    fn default() -> KeyDbConfig {
+        KeyDbConfig {
+            bucket_micros: 1_000,
+            simultaneity_tolerance_micros: 5_000,
+            run_gap_micros: 2_000_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Which keys were held (and sounding) at any point during a bucket, and
+/// which were still held when it ended. Buckets are only stored when a
+/// message lands in them; buckets in between inherit the previous `*_end`.
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    held_any: u128,
+    held_end: u128,
+    sounding_any: u128,
+    sounding_end: u128,
+}
+
+#[derive(Default)]
+struct KeyStore {
+    messages: Vec<KeyMessage>,
+    pedals: Vec<PedalMessage>,
+    // when each of `messages` and `pedals` arrived, counted across both, so
+    // a rebuild replays same-timestamp messages in the order they came in
+    message_arrivals: Vec<u64>,
+    pedal_arrivals: Vec<u64>,
+    arrivals: u64,
+    // the state after each key or pedal message, in timestamp order
+    states: Vec<(u64, SoundingState)>,
+    buckets: BTreeMap<u64, Bucket>,
+}
+
+impl KeyStore {
+    fn insert(&mut self, message: InputMessage, bucket_micros: u64) {
+        let timestamp = message.timestamp();
+        let out_of_order = self
+            .states
+            .last()
+            .is_some_and(|(last, _)| *last > timestamp);
+        let arrival = self.arrivals;
+        self.arrivals += 1;
+        match message {
+            InputMessage::Key(key) => {
+                let idx = self.messages.partition_point(|m| m.timestamp <= timestamp);
+                self.messages.insert(idx, key);
+                self.message_arrivals.insert(idx, arrival);
+            }
+            InputMessage::Pedal(pedal) => {
+                let idx = self.pedals.partition_point(|p| p.timestamp <= timestamp);
+                self.pedals.insert(idx, pedal);
+                self.pedal_arrivals.insert(idx, arrival);
+            }
+        }
+        if out_of_order {
+            // rare (only for hand-built messages), so just redo the whole index
+            self.rebuild(bucket_micros);
+        } else {
+            self.index(&message, bucket_micros);
+        }
+    }
+
+    fn index(&mut self, message: &InputMessage, bucket_micros: u64) {
+        let prior = self.states.last().map(|(_, s)| *s).unwrap_or_default();
+        let mut state = prior;
+        match message {
+            InputMessage::Key(key) => state.apply_key(key),
+            InputMessage::Pedal(pedal) => state.apply_pedal(pedal),
+        }
+
+        let timestamp = message.timestamp();
+        let bucket = self
+            .buckets
+            .entry(timestamp / bucket_micros)
+            .or_insert(Bucket {
+                held_any: prior.held(),
+                held_end: prior.held(),
+                sounding_any: prior.sounding(),
+                sounding_end: prior.sounding(),
+            });
+        bucket.held_any |= state.held();
+        bucket.held_end = state.held();
+        bucket.sounding_any |= state.sounding();
+        bucket.sounding_end = state.sounding();
+        self.states.push((timestamp, state));
+    }
+
+    /// Re-indexes every message. Messages with the same timestamp go in the
+    /// order they arrived.
+    fn rebuild(&mut self, bucket_micros: u64) {
+        self.states.clear();
+        self.buckets.clear();
+        let mut merged: Vec<(u64, InputMessage)> = self
+            .message_arrivals
+            .iter()
+            .zip(&self.messages)
+            .map(|(arrival, k)| (*arrival, InputMessage::Key(*k)))
+            .chain(
+                self.pedal_arrivals
+                    .iter()
+                    .zip(&self.pedals)
+                    .map(|(arrival, p)| (*arrival, InputMessage::Pedal(*p))),
+            )
+            .collect();
+        merged.sort_by_key(|(arrival, m)| (m.timestamp(), *arrival));
+        for (_, message) in merged {
+            self.index(&message, bucket_micros);
+        }
+    }
+
+    fn state_at(&self, timestamp: u64) -> SoundingState {
+        match self.states.partition_point(|(t, _)| *t <= timestamp) {
+            0 => SoundingState::default(),
+            idx => self.states[idx - 1].1,
+        }
+    }
+
+    /// (held, sounding) masks for everything touching buckets `first..=last`
+    fn masks_between(&self, first: u64, last: u64) -> (u128, u128) {
+        let (mut held, mut sounding) = self
+            .buckets
+            .range(..first)
+            .next_back()
+            .map_or((0, 0), |(_, b)| (b.held_end, b.sounding_end));
+        for (_, bucket) in self.buckets.range(first..=last) {
+            held |= bucket.held_any;
+            sounding |= bucket.sounding_any;
+        }
+        (held, sounding)
+    }
+}
+
+/// KeyDb stores key and pedal messages along with a time-bucketed index of
+/// which keys are held, so that "what was held between t1 and t2" and "is
+/// key K free in this range" don't have to scan the whole log.
+///
+/// Messages are expected to arrive roughly in timestamp order; anything
+/// older than the latest message triggers a rebuild of the index.
 pub struct KeyDb {
-    buf: RwLock<Vec<KeyMessage>>,
+    config: KeyDbConfig,
+    store: RwLock<KeyStore>,
 }
 
 fn always_true(_k: &&KeyMessage) -> bool {
@@ -18,23 +264,205 @@ fn always_true(_k: &&KeyMessage) -> bool {
 
 type FilterMethod = fn(&&KeyMessage) -> bool;
 
+impl Default for KeyDb {
+    fn default() -> KeyDb {
+        KeyDb::new()
+    }
+}
+
 impl KeyDb {
     pub fn new() -> KeyDb {
+        KeyDb::with_config(KeyDbConfig::default())
+    }
+
+    pub fn with_config(config: KeyDbConfig) -> KeyDb {
+        assert!(config.bucket_micros > 0, "bucket_micros must be positive");
         KeyDb {
-            buf: RwLock::from(Vec::new()),
+            config,
+            store: RwLock::from(KeyStore::default()),
         }
     }
 
+    pub fn config(&self) -> KeyDbConfig {
+        self.config
+    }
+
     pub fn flat_message_log(&self) -> Vec<KeyMessage> {
-        self.buf.read().unwrap().to_vec()
+        self.store.read().unwrap().messages.to_vec()
     }
 
     pub fn push_msg(&self, key: KeyMessage) {
-        self.buf.write().unwrap().push(key)
+        self.store
+            .write()
+            .unwrap()
+            .insert(InputMessage::Key(key), self.config.bucket_micros)
+    }
+
+    pub fn pedal_log(&self) -> Vec<PedalMessage> {
+        self.store.read().unwrap().pedals.to_vec()
+    }
+
+    pub fn push_pedal(&self, pedal: PedalMessage) {
+        self.store
+            .write()
+            .unwrap()
+            .insert(InputMessage::Pedal(pedal), self.config.bucket_micros)
     }
 
     pub fn clear(&self) {
-        self.buf.write().unwrap().clear()
+        *self.store.write().unwrap() = KeyStore::default();
+    }
+
+    /// State after every key and pedal message up to and including `timestamp`
+    pub fn sounding_state_at(&self, timestamp: u64) -> SoundingState {
+        self.store.read().unwrap().state_at(timestamp)
+    }
+
+    /// Keys physically held down at `timestamp`
+    pub fn held_keys_at(&self, timestamp: u64) -> Vec<u8> {
+        keys_in_mask(self.sounding_state_at(timestamp).held())
+    }
+
+    /// Keys held down or sustained by the sustain/sostenuto pedals at `timestamp`
+    pub fn sounding_keys_at(&self, timestamp: u64) -> Vec<u8> {
+        keys_in_mask(self.sounding_state_at(timestamp).sounding())
+    }
+
+    pub fn is_sounding(&self, key: u8, timestamp: u64) -> bool {
+        self.sounding_state_at(timestamp).sounding() & key_bit(key) != 0
+    }
+
+    pub fn pedal_state_at(&self, pedal: Pedal, timestamp: u64) -> PedalState {
+        self.sounding_state_at(timestamp).pedal_state(pedal)
+    }
+
+    fn bucket_range(&self, start: u64, end: u64) -> (u64, u64) {
+        let tolerance = self.config.simultaneity_tolerance_micros;
+        (
+            start.saturating_sub(tolerance) / self.config.bucket_micros,
+            end.saturating_add(tolerance) / self.config.bucket_micros,
+        )
+    }
+
+    /// Keys held down at any point between roughly `start` and `end`
+    pub fn keys_held_between(&self, start: u64, end: u64) -> Vec<u8> {
+        let (first, last) = self.bucket_range(start, end);
+        keys_in_mask(self.store.read().unwrap().masks_between(first, last).0)
+    }
+
+    /// Like keys_held_between, but includes notes kept ringing by a pedal
+    pub fn keys_sounding_between(&self, start: u64, end: u64) -> Vec<u8> {
+        let (first, last) = self.bucket_range(start, end);
+        keys_in_mask(self.store.read().unwrap().masks_between(first, last).1)
+    }
+
+    /// True if `key` isn't held down at any point between roughly `start` and `end`
+    pub fn is_key_free(&self, key: u8, start: u64, end: u64) -> bool {
+        let (first, last) = self.bucket_range(start, end);
+        self.store.read().unwrap().masks_between(first, last).0 & key_bit(key) == 0
+    }
+
+    /// Messages of `message_type` between roughly `start` and `end`, in chronological order
+    pub fn messages_between(
+        &self,
+        message_type: MidiMessageTypes,
+        start: u64,
+        end: u64,
+    ) -> Vec<KeyMessage> {
+        let tolerance = self.config.simultaneity_tolerance_micros;
+        let (start, end) = (
+            start.saturating_sub(tolerance),
+            end.saturating_add(tolerance),
+        );
+        let store = self.store.read().unwrap();
+        let first = store.messages.partition_point(|m| m.timestamp < start);
+        let last = store.messages.partition_point(|m| m.timestamp <= end);
+        store.messages[first..last]
+            .iter()
+            .filter(|m| m.message_type == message_type)
+            .copied()
+            .collect()
+    }
+
+    pub fn key_ups_between(&self, start: u64, end: u64) -> Vec<KeyMessage> {
+        self.messages_between(MidiMessageTypes::NoteOff, start, end)
+    }
+
+    pub fn key_downs_between(&self, start: u64, end: u64) -> Vec<KeyMessage> {
+        self.messages_between(MidiMessageTypes::NoteOn, start, end)
+    }
+
+    /// Key downs close enough to `timestamp` to count as played together
+    pub fn simultaneous_key_downs(&self, timestamp: u64) -> Vec<KeyMessage> {
+        self.key_downs_between(timestamp, timestamp)
+    }
+
+    /// Finds the most recent run in which `first_key` was pressed and later
+    /// `second_key`. A run is a stretch of key downs with no gap longer than
+    /// `run_gap_micros`. The range starts at the run's first key down and ends
+    /// at the last message before the next run starts.
+    pub fn run_containing(&self, first_key: u8, second_key: u8) -> Option<TimeRange> {
+        let store = self.store.read().unwrap();
+        let downs: Vec<&KeyMessage> = store
+            .messages
+            .iter()
+            .filter(|m| m.message_type == MidiMessageTypes::NoteOn)
+            .collect();
+        let same_run =
+            |i: usize| downs[i + 1].timestamp - downs[i].timestamp <= self.config.run_gap_micros;
+
+        let (first_idx, second_idx) = (0..downs.len())
+            .rev()
+            .filter(|j| downs[*j].key == second_key)
+            .find_map(|j| {
+                let mut i = j;
+                while i > 0 && same_run(i - 1) {
+                    i -= 1;
+                    if downs[i].key == first_key {
+                        return Some((i, j));
+                    }
+                }
+                None
+            })?;
+
+        let mut run_start = first_idx;
+        while run_start > 0 && same_run(run_start - 1) {
+            run_start -= 1;
+        }
+        let mut run_end = second_idx;
+        while run_end + 1 < downs.len() && same_run(run_end) {
+            run_end += 1;
+        }
+        let end = match downs.get(run_end + 1) {
+            Some(next_run) => store
+                .messages
+                .iter()
+                .take_while(|m| m.timestamp < next_run.timestamp)
+                .last()
+                .map_or(downs[run_end].timestamp, |m| m.timestamp),
+            None => store.messages.last().unwrap().timestamp,
+        };
+        Some(TimeRange {
+            start: downs[run_start].timestamp,
+            end,
+        })
+    }
+
+    pub fn note_spans(&self) -> Vec<NoteSpan> {
+        note_spans(&self.store.read().unwrap().messages)
+    }
+
+    /// Notes sounding (without counting pedals) at any point between roughly `start` and `end`
+    pub fn note_spans_between(&self, start: u64, end: u64) -> Vec<NoteSpan> {
+        let tolerance = self.config.simultaneity_tolerance_micros;
+        let (start, end) = (
+            start.saturating_sub(tolerance),
+            end.saturating_add(tolerance),
+        );
+        self.note_spans()
+            .into_iter()
+            .filter(|span| span.start <= end && span.end.is_none_or(|e| e >= start))
+            .collect()
     }
 
     pub fn last_n_key_ups_reversed(&self, n: usize) -> Vec<KeyMessage> {
@@ -57,14 +485,270 @@ impl KeyDb {
         n: usize,
     ) -> Vec<KeyMessage> {
         return self
-            .buf
+            .store
             .read()
             .unwrap()
+            .messages
             .iter()
             .rev()
             .filter(custom_filter.unwrap_or(always_true))
             .take(n)
-            .map(|k| *k)
+            .copied()
             .collect::<Vec<KeyMessage>>();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::Articulation;
+
+    fn key_down(timestamp: u64, key: u8) -> KeyMessage {
+        KeyMessage {
+            timestamp,
+            message_type: MidiMessageTypes::NoteOn,
+            key,
+            channel: 0,
+            velocity: 64,
+        }
+    }
+
+    fn key_up(timestamp: u64, key: u8) -> KeyMessage {
+        KeyMessage {
+            message_type: MidiMessageTypes::NoteOff,
+            velocity: 0,
+            ..key_down(timestamp, key)
+        }
+    }
+
+    fn pedal(timestamp: u64, pedal: Pedal, value: u8) -> PedalMessage {
+        PedalMessage {
+            timestamp,
+            pedal,
+            value,
+            channel: 0,
+        }
+    }
+
+    #[test]
+    fn keys_above_the_midi_range_are_never_held() {
+        let key_db = KeyDb::new();
+        key_db.push_msg(key_down(100, 127));
+        key_db.push_msg(key_down(100, 200));
+        assert!(key_db.is_sounding(127, 100));
+        assert!(!key_db.is_sounding(128, 100));
+        assert!(!key_db.is_sounding(255, 100));
+        assert!(key_db.is_key_free(200, 0, 200));
+        assert_eq!(key_db.held_keys_at(100), [127]);
+    }
+
+    #[test]
+    fn rebuilds_same_timestamp_messages_in_arrival_order() {
+        let key_db = KeyDb::new();
+        key_db.push_msg(key_down(50, 60));
+        key_db.push_pedal(pedal(100, Pedal::Sustain, 127));
+        key_db.push_msg(key_up(100, 60));
+        assert!(key_db.is_sounding(60, 100));
+
+        // an older message redoes the index
+        key_db.push_msg(key_down(10, 40));
+        assert!(key_db.is_sounding(60, 100));
+        assert_eq!(key_db.sounding_keys_at(100), [40, 60]);
+    }
+
+    #[test]
+    fn sostenuto_only_keeps_keys_held_when_it_went_down() {
+        let key_db = KeyDb::new();
+        key_db.push_msg(key_down(100, 60));
+        key_db.push_pedal(pedal(200, Pedal::Sostenuto, 127));
+        key_db.push_msg(key_down(300, 64));
+        key_db.push_msg(key_up(400, 60));
+        key_db.push_msg(key_up(500, 64));
+        // moving the pedal while it's down doesn't capture anything new
+        key_db.push_msg(key_down(550, 67));
+        key_db.push_pedal(pedal(600, Pedal::Sostenuto, 100));
+        key_db.push_msg(key_up(650, 67));
+
+        assert_eq!(key_db.sounding_keys_at(500), [60]);
+        assert_eq!(key_db.sounding_keys_at(700), [60]);
+        assert!(key_db.held_keys_at(700).is_empty());
+        assert_eq!(
+            key_db.pedal_state_at(Pedal::Sostenuto, 700),
+            PedalState::Down
+        );
+
+        key_db.push_pedal(pedal(800, Pedal::Sostenuto, 0));
+        assert!(key_db.sounding_keys_at(800).is_empty());
+    }
+
+    #[test]
+    fn half_sustain_keeps_released_keys_sounding() {
+        let key_db = KeyDb::new();
+        key_db.push_pedal(pedal(100, Pedal::Sustain, 64));
+        key_db.push_msg(key_down(200, 60));
+        key_db.push_msg(key_up(300, 60));
+        assert_eq!(key_db.pedal_state_at(Pedal::Sustain, 300), PedalState::Half);
+        assert_eq!(key_db.sounding_keys_at(300), [60]);
+
+        key_db.push_pedal(pedal(400, Pedal::Sustain, 10));
+        assert!(key_db.sounding_keys_at(400).is_empty());
+    }
+
+    fn exact_config() -> KeyDbConfig {
+        KeyDbConfig {
+            bucket_micros: 1_000,
+            simultaneity_tolerance_micros: 0,
+            ..KeyDbConfig::default()
+        }
+    }
+
+    #[test]
+    fn range_queries_respect_bucket_boundaries() {
+        let key_db = KeyDb::with_config(exact_config());
+        key_db.push_msg(key_down(500, 60));
+        key_db.push_msg(key_up(999, 60));
+        key_db.push_msg(key_down(2_000, 62));
+        key_db.push_msg(key_up(5_000, 62));
+
+        assert_eq!(key_db.keys_held_between(999, 999), [60]);
+        assert!(key_db.keys_held_between(1_000, 1_999).is_empty());
+        assert_eq!(key_db.keys_held_between(1_999, 2_000), [62]);
+        // no messages land in bucket 3, it carries on from bucket 2
+        assert_eq!(key_db.keys_held_between(3_000, 3_999), [62]);
+        assert!(key_db.is_key_free(62, 1_000, 1_999));
+        assert!(!key_db.is_key_free(62, 3_000, 3_999));
+        assert!(key_db.is_key_free(60, 3_000, 3_999));
+        assert_eq!(
+            key_db
+                .key_ups_between(999, 5_000)
+                .iter()
+                .map(|m| m.key)
+                .collect::<Vec<u8>>(),
+            [60, 62]
+        );
+
+        let tolerant = KeyDb::with_config(KeyDbConfig {
+            simultaneity_tolerance_micros: 1_000,
+            ..exact_config()
+        });
+        for message in key_db.flat_message_log() {
+            tolerant.push_msg(message);
+        }
+        assert_eq!(tolerant.keys_held_between(1_000, 1_000), [60, 62]);
+    }
+
+    #[test]
+    fn rebuilding_gives_the_same_masks_as_indexing_in_order() {
+        let messages = [
+            InputMessage::Key(key_down(100, 60)),
+            InputMessage::Pedal(pedal(1_500, Pedal::Sustain, 127)),
+            InputMessage::Key(key_down(2_200, 64)),
+            InputMessage::Key(key_up(2_900, 60)),
+            InputMessage::Pedal(pedal(4_100, Pedal::Sostenuto, 127)),
+            InputMessage::Key(key_up(6_000, 64)),
+            InputMessage::Pedal(pedal(7_300, Pedal::Sustain, 0)),
+            InputMessage::Key(key_down(8_000, 67)),
+            InputMessage::Key(key_up(9_999, 67)),
+            InputMessage::Pedal(pedal(11_000, Pedal::Sostenuto, 0)),
+        ];
+        let push = |key_db: &KeyDb, message: &InputMessage| match message {
+            InputMessage::Key(key) => key_db.push_msg(*key),
+            InputMessage::Pedal(pedal) => key_db.push_pedal(*pedal),
+        };
+        for tolerance in [0, 700, 5_000] {
+            let config = KeyDbConfig {
+                simultaneity_tolerance_micros: tolerance,
+                ..exact_config()
+            };
+            let in_order = KeyDb::with_config(config);
+            let rebuilt = KeyDb::with_config(config);
+            for message in &messages {
+                push(&in_order, message);
+            }
+            for message in messages.iter().rev() {
+                push(&rebuilt, message);
+            }
+            for t in (0..12_000).step_by(250) {
+                assert_eq!(in_order.sounding_state_at(t), rebuilt.sounding_state_at(t));
+                assert_eq!(
+                    in_order.keys_held_between(t, t + 500),
+                    rebuilt.keys_held_between(t, t + 500)
+                );
+                assert_eq!(
+                    in_order.keys_sounding_between(t, t + 500),
+                    rebuilt.keys_sounding_between(t, t + 500)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_latest_run_containing_two_keys() {
+        let key_db = KeyDb::new();
+        for (i, key) in [60, 62, 64].into_iter().enumerate() {
+            let t = i as u64 * 100_000;
+            key_db.push_msg(key_down(t, key));
+            key_db.push_msg(key_up(t + 90_000, key));
+        }
+        key_db.push_msg(key_down(3_000_000, 60));
+        key_db.push_msg(key_down(3_100_000, 67));
+        key_db.push_msg(key_up(3_500_000, 60));
+
+        assert_eq!(
+            key_db.run_containing(60, 64),
+            Some(TimeRange {
+                start: 0,
+                end: 290_000
+            })
+        );
+        assert_eq!(
+            key_db.run_containing(60, 67),
+            Some(TimeRange {
+                start: 3_000_000,
+                end: 3_500_000
+            })
+        );
+        assert_eq!(key_db.run_containing(64, 60), None);
+    }
+
+    #[test]
+    fn note_spans_classify_legato_and_staccato() {
+        let key_db = KeyDb::new();
+        // legato C into D, staccato D into E, non-legato E into F
+        key_db.push_msg(key_down(0, 60));
+        key_db.push_msg(key_down(100_000, 62));
+        key_db.push_msg(key_up(110_000, 60));
+        key_db.push_msg(key_up(130_000, 62));
+        key_db.push_msg(key_down(200_000, 64));
+        key_db.push_msg(key_up(270_000, 64));
+        key_db.push_msg(key_down(300_000, 65));
+        // a repeated key down ends the earlier note
+        key_db.push_msg(key_down(400_000, 65));
+
+        let spans = key_db.note_spans();
+        assert_eq!(
+            spans.iter().map(|span| span.key).collect::<Vec<u8>>(),
+            [60, 62, 64, 65, 65]
+        );
+        let articulations: Vec<Option<Articulation>> = spans
+            .windows(2)
+            .map(|pair| pair[0].articulation(&pair[1]))
+            .collect();
+        assert_eq!(
+            articulations,
+            [
+                Some(Articulation::Legato),
+                Some(Articulation::Staccato),
+                Some(Articulation::NonLegato),
+                Some(Articulation::Legato),
+            ]
+        );
+        assert_eq!(spans[0].overlap_with(&spans[1]), Some(10_000));
+        assert_eq!(spans[3].end, Some(400_000));
+        assert_eq!(spans[4].end, None);
+
+        let between = key_db.note_spans_between(150_000, 250_000);
+        assert_eq!(between.len(), 1);
+        assert_eq!(between[0].key, 64);
+    }
+}
@@ -1,5 +1,9 @@
+// the code keeps its explicit `return` style
+#![allow(clippy::needless_return)]
+
 pub mod practice_program;
 pub mod midi;
 pub mod music;
 pub mod key_handler;
-pub mod speech;
\ No newline at end of file
+pub mod session;
+pub mod speech;
@@ -1,4 +1,8 @@
+// the code keeps its explicit `return` style
+#![allow(clippy::needless_return)]
+
 use std::io::stdin;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use std::time::Duration;
@@ -13,11 +17,18 @@ use clap::Parser;
 use log::{debug, info, trace};
 
 use midi_hack::key_handler::{ControlMessage, KeyDb};
-use midi_hack::midi::{KeyMessage, KNOWN_MESSAGE_TYPES};
+use midi_hack::midi::{InputMessage, KeyMessage, MidiMessage, MidiParseError, KEEP_ALIVE};
+use midi_hack::music::{ChordQuality, Direction, Key, Pitch};
+use midi_hack::practice_program::script::MAX_OCTAVES;
 use midi_hack::practice_program::{
-    CircleOfFourthsPracticeProgram, EarTrainingPracticeProgram, FreePlayPracticeProgram,
-    PracticeProgram,
+    harmonic_minor_scales_random_order, ChordEarTrainingPracticeProgram,
+    ChordProgressionPracticeProgram, CircleOfFourthsPracticeProgram, EarTrainingPracticeProgram,
+    EarTrainingStats, Earcon, Earcons, FreePlayPracticeProgram, MajorScalesCircleOfFourths,
+    MelodicDictationPracticeProgram, MelodySettings, NotePlayer, PracticeProgram,
+    ProgressionAnswer, Scheduler, Script, ScriptedPracticeProgram, VoicingStrictness,
 };
+use midi_hack::session::{SessionRecorder, SessionSpeech};
+use midi_hack::speech::{backend_by_name, Locale, Speaker, SpeechBackend};
 use midir::{Ignore, MidiInput, MidiOutput};
 
 const HEARTBEATS_PER_AUTO_NEW_RUN: usize = 100;
@@ -42,7 +53,17 @@ impl KeyLogAndDispatch {
         };
     }
 
-    fn accept(&mut self, message: KeyMessage) {
+    fn accept(&mut self, message: InputMessage) {
+        match message {
+            InputMessage::Key(key_message) => self.accept_key(key_message),
+            InputMessage::Pedal(pedal_message) => {
+                self.key_db.push_pedal(pedal_message);
+                self.most_recent_insert = max(pedal_message.timestamp, self.most_recent_insert);
+            }
+        }
+    }
+
+    fn accept_key(&mut self, message: KeyMessage) {
         self.key_db.push_msg(message);
         self.most_recent_insert = max(message.timestamp, self.most_recent_insert);
         self.call_listeners(message);
@@ -106,7 +127,7 @@ impl KeyLogAndDispatch {
 
     pub(crate) fn start_recv_loop(
         mut self,
-        playback_receiver: Receiver<KeyMessage>,
+        playback_receiver: Receiver<InputMessage>,
         control_receiver: Receiver<ControlMessage>,
     ) -> JoinHandle<()> {
         return std::thread::spawn(move || {
@@ -131,6 +152,27 @@ trait RunEndListener {
 }
 
 fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    // load any script and check settings first so mistakes in them are
+    // reported before connecting to MIDI
+    let script = match cli.practice_program_file.as_ref() {
+        Some(path) => Some(Script::load(path)?),
+        None => None,
+    };
+    let melody_settings = cli.melody_settings()?;
+    let octaves = cli.octaves()?;
+    let scheduler = cli.scheduler()?;
+    let stats = cli.stats()?;
+    let session = cli.session_file.as_ref().map(|_| SessionRecorder::new());
+    let speech_backend: Arc<dyn SpeechBackend> = match session.as_ref() {
+        Some(session) => Arc::new(SessionSpeech::new(
+            &cli.speech_backend,
+            cli.voice.clone(),
+            session.clone(),
+        )?),
+        None => Arc::from(backend_by_name(&cli.speech_backend, cli.voice.clone())?),
+    };
+    let speaker = Speaker::with_locale(speech_backend, cli.locale);
+
     // Midi read setup
     let mut input = String::new();
     let mut midi_in = MidiInput::new("midir reading input")?;
@@ -186,25 +228,116 @@ fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
     let key_reader_ro_copy = Arc::clone(&key_db);
     let key_reader = KeyLogAndDispatch::new(program_sender, key_db);
     match cli.practice_program.as_ref() {
+        _ if script.is_some() => {
+            let program = ScriptedPracticeProgram::new(
+                control_sender_practice_program,
+                program_receiver,
+                speaker,
+                script.unwrap(),
+            );
+            program.run();
+        }
         "circle-of-fourths" => {
             let program = CircleOfFourthsPracticeProgram::new(
                 control_sender_practice_program,
                 program_receiver,
                 key_reader_ro_copy,
+                speaker,
+                octaves,
+                cli.direction,
+                cli.start_octave,
+                cli.contrary_motion,
+                scheduler,
+            );
+            program.run();
+        }
+        "major-scales-circle-of-fourths" => {
+            let program = MajorScalesCircleOfFourths::new(
+                control_sender_practice_program,
+                program_receiver,
+                speaker,
+            );
+            program.run();
+        }
+        "harmonic-minor-scales-random-order" => {
+            let program = ScriptedPracticeProgram::new(
+                control_sender_practice_program,
+                program_receiver,
+                speaker,
+                harmonic_minor_scales_random_order(),
             );
             program.run();
         }
         "ear-training" => {
             assert!(
-                midi_out_connection.is_some(),
-                "functional MIDI out required for ear training"
+                midi_out_connection.is_some() || session.is_some(),
+                "functional MIDI out (or --session-file) required for ear training"
             );
             let program = EarTrainingPracticeProgram::new(
                 control_sender_practice_program,
-                midi_out_sender,
+                NotePlayer::new(midi_out_sender),
                 program_receiver,
                 key_reader_ro_copy,
+                speaker,
+                cli.earcons(),
                 true,
+                cli.descending_intervals,
+                stats.unwrap_or_default(),
+                scheduler,
+            );
+            program.run();
+        }
+        "chord-ear-training" => {
+            assert!(
+                midi_out_connection.is_some() || session.is_some(),
+                "functional MIDI out (or --session-file) required for ear training"
+            );
+            let program = ChordEarTrainingPracticeProgram::new(
+                control_sender_practice_program,
+                NotePlayer::new(midi_out_sender),
+                program_receiver,
+                key_reader_ro_copy,
+                speaker,
+                cli.earcons(),
+                cli.chord_qualities.clone(),
+                cli.inversions.clone(),
+                cli.voicing,
+                scheduler,
+            );
+            program.run();
+        }
+        "melodic-dictation" => {
+            assert!(
+                midi_out_connection.is_some() || session.is_some(),
+                "functional MIDI out (or --session-file) required for ear training"
+            );
+            let program = MelodicDictationPracticeProgram::new(
+                control_sender_practice_program,
+                NotePlayer::new(midi_out_sender),
+                program_receiver,
+                key_reader_ro_copy,
+                speaker,
+                cli.earcons(),
+                melody_settings,
+            );
+            program.run();
+        }
+        "chord-progressions" => {
+            assert!(
+                midi_out_connection.is_some() || session.is_some(),
+                "functional MIDI out (or --session-file) required for ear training"
+            );
+            let program = ChordProgressionPracticeProgram::new(
+                control_sender_practice_program,
+                NotePlayer::new(midi_out_sender),
+                program_receiver,
+                key_reader_ro_copy,
+                speaker,
+                cli.earcons(),
+                cli.progression_level,
+                cli.play_back,
+                cli.key,
+                scheduler,
             );
             program.run();
         }
@@ -213,6 +346,7 @@ fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
                 control_sender_practice_program,
                 program_receiver,
                 key_reader_ro_copy,
+                speaker,
             );
             program.run();
         }
@@ -222,22 +356,19 @@ fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
     let _conn_in = midi_in.connect(
         in_port,
         "midir-read-input",
-        move |stamp, message, _| {
-            if !KNOWN_MESSAGE_TYPES.contains(&message[0]) {
-                println!(
-                    "unknown message {}: {:?} (len = {})",
-                    stamp,
-                    message,
-                    message.len()
-                );
-            }
-            if message.len() == 3 {
-                if message[0] == midi_hack::midi::KEY_UP || message[0] == midi_hack::midi::KEY_DOWN
-                {
-                    let parsed_message = KeyMessage::from_midi(stamp, message);
-                    playback_sender.send(parsed_message).unwrap();
-                }
-            }
+        move |stamp, message, _| match MidiMessage::parse(message) {
+            Ok(parsed) => match InputMessage::from_midi_message(stamp, &parsed) {
+                Some(input_message) => playback_sender.send(input_message).unwrap(),
+                None => trace!("ignoring {:?}", parsed),
+            },
+            Err(MidiParseError::UnsupportedStatus(KEEP_ALIVE)) => (),
+            Err(err) => println!(
+                "unknown message {}: {:?} (len = {}): {}",
+                stamp,
+                message,
+                message.len(),
+                err
+            ),
         },
         (),
     )?;
@@ -253,7 +384,15 @@ fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
 
     key_reader.start_recv_loop(playback_receiver, control_receiver);
 
-    if midi_out_connection.is_some() {
+    if let Some(session) = session.clone() {
+        // notes go into the session instead of to the device
+        std::thread::spawn(move || {
+            while let Ok(message) = midi_out_receiver.recv() {
+                trace!("rendering {:?}", message);
+                session.record_key(message);
+            }
+        });
+    } else if midi_out_connection.is_some() {
         std::thread::spawn(move || {
             const WAIT_DELAY: Duration = std::time::Duration::from_secs(1);
             let mut midi_out = midi_out_connection.unwrap();
@@ -288,18 +427,227 @@ fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
         }
     }
 
+    if let (Some(session), Some(path)) = (session, cli.session_file.as_ref()) {
+        session.write_wav(path)?;
+        println!("Wrote session to {}", path.display());
+    }
+
     println!("Closing connection");
     Ok(())
 }
 
+/// Programs that can pick what to ask with a Scheduler
+const SCHEDULED_PROGRAMS: [&str; 4] = [
+    "circle-of-fourths",
+    "ear-training",
+    "chord-ear-training",
+    "chord-progressions",
+];
+
 #[derive(Parser)]
 struct Cli {
     /// Name of the practice program to play
+    #[arg(default_value = "free-play")]
     practice_program: String,
 
+    /// Script to run instead of a built-in practice program, see drills/
+    #[arg(long)]
+    practice_program_file: Option<PathBuf>,
+
+    /// Text-to-speech engine: auto, say, espeak-ng, spd-say, festival, piper or none
+    #[arg(long, env = "MIDI_HACK_SPEECH_BACKEND", default_value = "auto")]
+    speech_backend: String,
+
+    /// Voice for the speech engine; for piper, the path to a .onnx voice model
+    #[arg(long, env = "MIDI_HACK_VOICE")]
+    voice: Option<String>,
+
+    /// Language for everything spoken: en, de, fr, it, es or ja. Pick a
+    /// voice that speaks it too.
+    #[arg(long, env = "MIDI_HACK_LOCALE", default_value = "en")]
+    locale: Locale,
+
+    /// Render speech and the notes played to you into this WAV file instead
+    /// of playing them live, to review the lesson later
+    #[arg(long)]
+    session_file: Option<PathBuf>,
+
+    /// Cue right and wrong answers and new questions with short phrases on
+    /// the instrument instead of speech (ear-training)
+    #[arg(long)]
+    earcons: bool,
+
+    /// Earcon for a new question: pitches like "A5", "C5+E5" to play them
+    /// together, and ":ms" for a length, e.g. "A5:80" (implies --earcons)
+    #[arg(long)]
+    earcon_prompt: Option<Earcon>,
+
+    /// Earcon for a right answer, e.g. "C5 E5:250" (implies --earcons)
+    #[arg(long)]
+    earcon_correct: Option<Earcon>,
+
+    /// Earcon for a wrong answer, e.g. "C2+Db2:400" (implies --earcons)
+    #[arg(long)]
+    earcon_incorrect: Option<Earcon>,
+
+    /// Ask for descending intervals as well as ascending ones (ear-training)
+    #[arg(long)]
+    descending_intervals: bool,
+
+    /// Keep score of right and wrong answers in this file across sessions,
+    /// so weak intervals keep coming up and harder ones unlock (ear-training)
+    #[arg(long, env = "MIDI_HACK_STATS_FILE")]
+    stats_file: Option<PathBuf>,
+
+    /// Pick what to ask next like flashcards, bringing back what was missed
+    /// or slow sooner, and keep the schedule in this file across sessions
+    /// (circle-of-fourths, ear-training, chord-ear-training, chord-progressions)
+    #[arg(long, env = "MIDI_HACK_SCHEDULE_FILE")]
+    schedule_file: Option<PathBuf>,
+
+    /// Chords to ask for as symbols or names, e.g. "maj,m,7,m7b5"; triads and
+    /// seventh chords if left out (chord-ear-training)
+    #[arg(long, value_delimiter = ',')]
+    chord_qualities: Vec<ChordQuality>,
+
+    /// Inversions to ask for, e.g. "0,1,2" (chord-ear-training)
+    #[arg(long, value_delimiter = ',', default_value = "0")]
+    inversions: Vec<u8>,
+
+    /// How closely chords have to be played back: any-voicing, same-inversion
+    /// or exact (chord-ear-training)
+    #[arg(long, default_value = "any-voicing")]
+    voicing: VoicingStrictness,
+
+    /// Key to ask in, e.g. "Eb" or "F# minor"; a random major key for each
+    /// question if left out (melodic-dictation, chord-progressions)
+    #[arg(long)]
+    key: Option<Key>,
+
+    /// Lowest note melodies may use (melodic-dictation)
+    #[arg(long, default_value = "G3")]
+    melody_lowest: Pitch,
+
+    /// Highest note melodies may use, at least an octave above
+    /// --melody-lowest (melodic-dictation)
+    #[arg(long, default_value = "G5")]
+    melody_highest: Pitch,
+
+    /// Fewest notes in a melody, 3 to 8 (melodic-dictation)
+    #[arg(long, default_value_t = 3)]
+    melody_min_notes: usize,
+
+    /// Most notes in a melody, 3 to 8 (melodic-dictation)
+    #[arg(long, default_value_t = 8)]
+    melody_max_notes: usize,
+
+    /// Play melodies with varied note lengths instead of evenly (melodic-dictation)
+    #[arg(long)]
+    rhythm: bool,
+
+    /// Progressions to ask for: 1 for cadences like I-IV-V-I, 2 adds ii-V-I
+    /// and I-vi-IV-V, 3 adds secondary dominants (chord-progressions)
+    #[arg(long, default_value_t = 1)]
+    progression_level: u8,
+
+    /// What to play back: bass-line or chords (chord-progressions)
+    #[arg(long, default_value = "chords")]
+    play_back: ProgressionAnswer,
+
     /// Midi device port (indexed by 0)
     #[arg(short, long, default_value_t = 0)]
     midi_device_port: usize,
+
+    /// Octaves to play scales over (circle-of-fourths)
+    #[arg(long, default_value_t = 1)]
+    octaves: u8,
+
+    /// Scale direction: ascending, descending, up-and-down or down-and-up (circle-of-fourths)
+    #[arg(long, default_value = "up-and-down")]
+    direction: Direction,
+
+    /// Octave of the first note of each scale, e.g. 3 to start on the C below
+    /// middle C; any register is accepted if left out (circle-of-fourths)
+    #[arg(long)]
+    start_octave: Option<i8>,
+
+    /// Play scales hands together in contrary motion, starting on the same key (circle-of-fourths)
+    #[arg(long)]
+    contrary_motion: bool,
+}
+
+impl Cli {
+    fn earcons(&self) -> Option<Earcons> {
+        if !(self.earcons
+            || self.earcon_prompt.is_some()
+            || self.earcon_correct.is_some()
+            || self.earcon_incorrect.is_some())
+        {
+            return None;
+        }
+        let defaults = Earcons::default();
+        Some(Earcons::new(
+            self.earcon_prompt.clone().unwrap_or(defaults.prompt),
+            self.earcon_correct.clone().unwrap_or(defaults.correct),
+            self.earcon_incorrect.clone().unwrap_or(defaults.incorrect),
+        ))
+    }
+
+    fn melody_settings(&self) -> Result<MelodySettings, String> {
+        let midi = |flag: &str, pitch: Pitch| {
+            pitch
+                .midi()
+                .ok_or_else(|| format!("{} {} isn't on a MIDI keyboard", flag, pitch))
+        };
+        let settings = MelodySettings {
+            key: self.key,
+            lowest: midi("--melody-lowest", self.melody_lowest)?,
+            highest: midi("--melody-highest", self.melody_highest)?,
+            min_notes: self.melody_min_notes,
+            max_notes: self.melody_max_notes,
+            rhythm: self.rhythm,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn octaves(&self) -> Result<u8, String> {
+        if !(1..=MAX_OCTAVES).contains(&self.octaves) {
+            return Err(format!(
+                "--octaves must be between 1 and {}, not {}",
+                MAX_OCTAVES, self.octaves
+            ));
+        }
+        Ok(self.octaves)
+    }
+
+    /// The schedule in --schedule-file, if given for a program that uses one
+    fn scheduler(&self) -> Result<Option<Scheduler>, Box<dyn Error>> {
+        let Some(path) = self.schedule_file.as_ref() else {
+            return Ok(None);
+        };
+        if self.practice_program_file.is_some()
+            || !SCHEDULED_PROGRAMS.contains(&self.practice_program.as_str())
+        {
+            return Err(format!(
+                "--schedule-file only works with {}",
+                SCHEDULED_PROGRAMS.join(", ")
+            )
+            .into());
+        }
+        Ok(Some(Scheduler::load(path)?))
+    }
+
+    /// The scores in --stats-file, if given for ear-training
+    fn stats(&self) -> Result<Option<EarTrainingStats>, Box<dyn Error>> {
+        let Some(path) = self.stats_file.as_ref() else {
+            return Ok(None);
+        };
+        if self.practice_program_file.is_some() || self.practice_program != "ear-training" {
+            return Err("--stats-file only works with ear-training".into());
+        }
+        Ok(Some(EarTrainingStats::load(path)?))
+    }
 }
 
 fn main() {
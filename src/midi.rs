@@ -1,8 +1,15 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::music::{Pitch, PitchClass};
+
 #[derive(Clone, Copy, Debug)]
 pub struct KeyMessage {
     pub timestamp: u64, // TODO make this an option for user-generated messages
     pub message_type: MidiMessageTypes,
     pub key: u8,
+    pub channel: u8, // 0-indexed, so MIDI channel 1 is 0
+    pub velocity: u8,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -14,49 +21,488 @@ pub enum MidiMessageTypes {
 
 pub const KEY_DOWN: u8 = 144;
 pub const KEY_UP: u8 = 128;
+pub const POLYPHONIC_AFTERTOUCH: u8 = 0xA0;
+pub const CONTROL_CHANGE: u8 = 0xB0;
+pub const PROGRAM_CHANGE: u8 = 0xC0;
+pub const CHANNEL_PRESSURE: u8 = 0xD0;
+pub const PITCH_BEND: u8 = 0xE0;
 pub const KEEP_ALIVE: u8 = 254;
-const TIME_KEEPING: u8 = 208;
-pub static KNOWN_MESSAGE_TYPES: &'static [u8] = &[KEY_DOWN, KEY_UP, KEEP_ALIVE, TIME_KEEPING];
-const VELOCITY: u8 = 0x64;
+pub const DEFAULT_VELOCITY: u8 = 0x64;
+
+/// A MIDI channel-voice message. Channels are 0-indexed, so the status byte
+/// 0x91 (note on, MIDI channel 2) parses to `NoteOn { channel: 1, .. }`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiMessage {
+    NoteOff {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        key: u8,
+        velocity: u8,
+    },
+    PolyphonicAftertouch {
+        channel: u8,
+        key: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    /// 14-bit value, 0x2000 is the centre (no bend)
+    PitchBend {
+        channel: u8,
+        value: u16,
+    },
+}
 
-// real pianos start with a low A, the midi standard starts at C
-const NOTE_SEQ_OFFSET: usize = 3;
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiParseError {
+    Empty,
+    /// The first byte is a data byte. Running status isn't supported because
+    /// midir hands us complete messages.
+    MissingStatus(u8),
+    /// System common/real-time messages (0xF0 and up), e.g. active sensing
+    UnsupportedStatus(u8),
+    WrongLength {
+        status: u8,
+        expected: usize,
+        actual: usize,
+    },
+    InvalidDataByte {
+        status: u8,
+        byte: u8,
+    },
+    /// A valid message that doesn't describe a key going up or down
+    NotAKeyMessage(u8),
+}
+
+impl fmt::Display for MidiParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiParseError::Empty => write!(f, "empty MIDI message"),
+            MidiParseError::MissingStatus(byte) => {
+                write!(f, "expected a status byte, found data byte {:#04x}", byte)
+            }
+            MidiParseError::UnsupportedStatus(status) => {
+                write!(f, "unsupported status byte {:#04x}", status)
+            }
+            MidiParseError::WrongLength {
+                status,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "status {:#04x} expects {} bytes, got {}",
+                status, expected, actual
+            ),
+            MidiParseError::InvalidDataByte { status, byte } => write!(
+                f,
+                "invalid data byte {:#04x} for status {:#04x}",
+                byte, status
+            ),
+            MidiParseError::NotAKeyMessage(status) => {
+                write!(f, "status {:#04x} is not a note on/off message", status)
+            }
+        }
+    }
+}
+
+impl Error for MidiParseError {}
+
+impl MidiMessage {
+    pub fn parse(bytes: &[u8]) -> Result<MidiMessage, MidiParseError> {
+        let status = *bytes.first().ok_or(MidiParseError::Empty)?;
+        if status < 0x80 {
+            return Err(MidiParseError::MissingStatus(status));
+        }
+        if status >= 0xF0 {
+            return Err(MidiParseError::UnsupportedStatus(status));
+        }
+
+        let kind = status & 0xF0;
+        let channel = status & 0x0F;
+        let expected = match kind {
+            PROGRAM_CHANGE | CHANNEL_PRESSURE => 2,
+            _ => 3,
+        };
+        if bytes.len() != expected {
+            return Err(MidiParseError::WrongLength {
+                status,
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        if let Some(byte) = bytes[1..].iter().find(|b| **b > 0x7F) {
+            return Err(MidiParseError::InvalidDataByte {
+                status,
+                byte: *byte,
+            });
+        }
+
+        Ok(match kind {
+            KEY_UP => MidiMessage::NoteOff {
+                channel,
+                key: bytes[1],
+                velocity: bytes[2],
+            },
+            KEY_DOWN => MidiMessage::NoteOn {
+                channel,
+                key: bytes[1],
+                velocity: bytes[2],
+            },
+            POLYPHONIC_AFTERTOUCH => MidiMessage::PolyphonicAftertouch {
+                channel,
+                key: bytes[1],
+                pressure: bytes[2],
+            },
+            CONTROL_CHANGE => MidiMessage::ControlChange {
+                channel,
+                controller: bytes[1],
+                value: bytes[2],
+            },
+            PROGRAM_CHANGE => MidiMessage::ProgramChange {
+                channel,
+                program: bytes[1],
+            },
+            CHANNEL_PRESSURE => MidiMessage::ChannelPressure {
+                channel,
+                pressure: bytes[1],
+            },
+            // the only remaining kind is PITCH_BEND, LSB first
+            _ => MidiMessage::PitchBend {
+                channel,
+                value: u16::from(bytes[1]) | (u16::from(bytes[2]) << 7),
+            },
+        })
+    }
+
+    pub fn status(&self) -> u8 {
+        let kind = match self {
+            MidiMessage::NoteOff { .. } => KEY_UP,
+            MidiMessage::NoteOn { .. } => KEY_DOWN,
+            MidiMessage::PolyphonicAftertouch { .. } => POLYPHONIC_AFTERTOUCH,
+            MidiMessage::ControlChange { .. } => CONTROL_CHANGE,
+            MidiMessage::ProgramChange { .. } => PROGRAM_CHANGE,
+            MidiMessage::ChannelPressure { .. } => CHANNEL_PRESSURE,
+            MidiMessage::PitchBend { .. } => PITCH_BEND,
+        };
+        kind | self.channel()
+    }
+
+    pub fn channel(&self) -> u8 {
+        match *self {
+            MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::PolyphonicAftertouch { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBend { channel, .. } => channel,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let status = self.status();
+        match *self {
+            MidiMessage::NoteOff { key, velocity, .. }
+            | MidiMessage::NoteOn { key, velocity, .. } => {
+                vec![status, key, velocity]
+            }
+            MidiMessage::PolyphonicAftertouch { key, pressure, .. } => vec![status, key, pressure],
+            MidiMessage::ControlChange {
+                controller, value, ..
+            } => vec![status, controller, value],
+            MidiMessage::ProgramChange { program, .. } => vec![status, program],
+            MidiMessage::ChannelPressure { pressure, .. } => vec![status, pressure],
+            MidiMessage::PitchBend { value, .. } => {
+                vec![status, (value & 0x7F) as u8, ((value >> 7) & 0x7F) as u8]
+            }
+        }
+    }
+}
+
+impl fmt::Display for KeyMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}{} ", self.message_type, self.readable_note())
+    }
+}
 
 impl KeyMessage {
+    /// Scientific pitch notation, e.g. "C4" for middle C
     pub fn readable_note(&self) -> String {
-        return format!("{}{}", self.note_name(), self.key / 12);
+        return Pitch::from_midi(self.key).to_string();
     }
 
-    pub fn note_name(&self) -> &str {
-        let keys = [
-            "A", "Bb", "B", "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab",
-        ];
-        keys.get((usize::from(self.key) + NOTE_SEQ_OFFSET) % keys.len())
-            .unwrap()
+    pub fn note_name(&self) -> String {
+        PitchClass::from_semitone(self.key % 12).to_string()
     }
 
     pub fn print(&self) {
         print!("{:?}{} ", self.message_type, self.readable_note());
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{:?}{} ", self.message_type, self.readable_note())
+    pub fn encode(&self) -> [u8; 3] {
+        return [
+            self.message_type as u8 | self.channel,
+            self.key,
+            self.velocity,
+        ];
     }
 
-    pub fn encode(&self) -> [u8; 3] {
-        return [self.message_type as u8, self.key, VELOCITY];
+    pub fn from_midi(
+        timestamp: u64,
+        unstructured_message: &[u8],
+    ) -> Result<KeyMessage, MidiParseError> {
+        let message = MidiMessage::parse(unstructured_message)?;
+        KeyMessage::from_midi_message(timestamp, &message)
+            .ok_or(MidiParseError::NotAKeyMessage(message.status()))
     }
 
-    pub fn from_midi(timestamp: u64, unstructured_message: &[u8]) -> KeyMessage {
-        let m_type = match unstructured_message[0] {
-            KEY_DOWN => MidiMessageTypes::NoteOn,
-            KEY_UP => MidiMessageTypes::NoteOff,
-            _ => panic!("unknown message type"),
-        };
-        return KeyMessage {
-            timestamp: timestamp,
-            message_type: m_type,
-            key: unstructured_message[1],
+    /// Returns None for channel-voice messages that aren't note on/off.
+    ///
+    /// Many keyboards never send note off and instead send note on with
+    /// velocity 0, so those are normalized to NoteOff here.
+    pub fn from_midi_message(timestamp: u64, message: &MidiMessage) -> Option<KeyMessage> {
+        let (message_type, channel, key, velocity) = match *message {
+            MidiMessage::NoteOn {
+                channel,
+                key,
+                velocity: 0,
+            } => (MidiMessageTypes::NoteOff, channel, key, 0),
+            MidiMessage::NoteOn {
+                channel,
+                key,
+                velocity,
+            } => (MidiMessageTypes::NoteOn, channel, key, velocity),
+            MidiMessage::NoteOff {
+                channel,
+                key,
+                velocity,
+            } => (MidiMessageTypes::NoteOff, channel, key, velocity),
+            _ => return None,
         };
+        Some(KeyMessage {
+            timestamp,
+            message_type,
+            key,
+            channel,
+            velocity,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pedal {
+    Sustain = 64,
+    Sostenuto = 66,
+    Soft = 67,
+}
+
+impl Pedal {
+    pub fn from_controller(controller: u8) -> Option<Pedal> {
+        match controller {
+            64 => Some(Pedal::Sustain),
+            66 => Some(Pedal::Sostenuto),
+            67 => Some(Pedal::Soft),
+            _ => None,
+        }
+    }
+}
+
+/// Continuous pedals send the whole 0-127 range, switch pedals only 0 and 127
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PedalState {
+    Up,
+    Half,
+    Down,
+}
+
+impl PedalState {
+    pub fn from_value(value: u8) -> PedalState {
+        match value {
+            0..=31 => PedalState::Up,
+            32..=95 => PedalState::Half,
+            _ => PedalState::Down,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PedalMessage {
+    pub timestamp: u64,
+    pub pedal: Pedal,
+    pub value: u8,
+    pub channel: u8,
+}
+
+impl PedalMessage {
+    pub fn state(&self) -> PedalState {
+        PedalState::from_value(self.value)
+    }
+
+    /// Returns None for anything but a sustain, sostenuto or soft pedal control change
+    pub fn from_midi_message(timestamp: u64, message: &MidiMessage) -> Option<PedalMessage> {
+        match *message {
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => Pedal::from_controller(controller).map(|pedal| PedalMessage {
+                timestamp,
+                pedal,
+                value,
+                channel,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Everything from the MIDI input that gets recorded in the KeyDb
+#[derive(Clone, Copy, Debug)]
+pub enum InputMessage {
+    Key(KeyMessage),
+    Pedal(PedalMessage),
+}
+
+impl InputMessage {
+    pub fn from_midi_message(timestamp: u64, message: &MidiMessage) -> Option<InputMessage> {
+        KeyMessage::from_midi_message(timestamp, message)
+            .map(InputMessage::Key)
+            .or_else(|| {
+                PedalMessage::from_midi_message(timestamp, message).map(InputMessage::Pedal)
+            })
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        match self {
+            InputMessage::Key(key) => key.timestamp,
+            InputMessage::Pedal(pedal) => pedal.timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_channel_voice_message() {
+        assert_eq!(
+            MidiMessage::parse(&[0x91, 60, 100]),
+            Ok(MidiMessage::NoteOn {
+                channel: 1,
+                key: 60,
+                velocity: 100
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0xC0, 5]),
+            Ok(MidiMessage::ProgramChange {
+                channel: 0,
+                program: 5
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0xEF, 0x00, 0x40]),
+            Ok(MidiMessage::PitchBend {
+                channel: 15,
+                value: 0x2000
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_status_bytes() {
+        assert_eq!(MidiMessage::parse(&[]), Err(MidiParseError::Empty));
+        assert_eq!(
+            MidiMessage::parse(&[KEEP_ALIVE]),
+            Err(MidiParseError::UnsupportedStatus(KEEP_ALIVE))
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0xF0, 0x7E, 0xF7]),
+            Err(MidiParseError::UnsupportedStatus(0xF0))
+        );
+        // running status, i.e. the data bytes of another note on
+        assert_eq!(
+            MidiMessage::parse(&[62, 100]),
+            Err(MidiParseError::MissingStatus(62))
+        );
+    }
+
+    #[test]
+    fn rejects_short_messages_and_bad_data_bytes() {
+        assert_eq!(
+            MidiMessage::parse(&[0x90, 60]),
+            Err(MidiParseError::WrongLength {
+                status: 0x90,
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0xD0, 10, 20]),
+            Err(MidiParseError::WrongLength {
+                status: 0xD0,
+                expected: 2,
+                actual: 3
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0x80, 60, 0x80]),
+            Err(MidiParseError::InvalidDataByte {
+                status: 0x80,
+                byte: 0x80
+            })
+        );
+    }
+
+    #[test]
+    fn encodes_what_it_parses() {
+        for bytes in [
+            vec![0x80, 60, 64],
+            vec![0x9F, 127, 1],
+            vec![0xA3, 61, 90],
+            vec![0xB0, 64, 127],
+            vec![0xC2, 0],
+            vec![0xD4, 77],
+            vec![0xE0, 0x7F, 0x7F],
+        ] {
+            let message = MidiMessage::parse(&bytes).unwrap();
+            assert_eq!(message.encode(), bytes);
+            assert_eq!(message.status(), bytes[0]);
+        }
+    }
+
+    #[test]
+    fn note_on_without_velocity_is_a_key_up() {
+        let key_up = KeyMessage::from_midi(7, &[0x92, 60, 0]).unwrap();
+        assert_eq!(key_up.message_type, MidiMessageTypes::NoteOff);
+        assert_eq!((key_up.timestamp, key_up.channel), (7, 2));
+        assert_eq!(key_up.encode(), [0x82, 60, 0]);
+
+        let key_down = KeyMessage::from_midi(7, &[0x90, 60, 1]).unwrap();
+        assert_eq!(key_down.message_type, MidiMessageTypes::NoteOn);
+        assert_eq!(key_down.velocity, 1);
+
+        let sustain = MidiMessage::parse(&[0xB0, 64, 127]).unwrap();
+        assert!(KeyMessage::from_midi_message(7, &sustain).is_none());
+        assert_eq!(
+            KeyMessage::from_midi(7, &[0xB0, 64, 127]).unwrap_err(),
+            MidiParseError::NotAKeyMessage(0xB0)
+        );
     }
 }
@@ -1,58 +1,102 @@
+use std::collections::HashMap;
+
 use log::trace;
 
 use crate::midi::{KeyMessage, MidiMessageTypes};
 
-pub fn is_minor_maj_7_chord(buf: &Vec<KeyMessage>) -> bool {
-    let mut major_minor_chord_c = vec!["C", "Eb", "G", "B"];
-    major_minor_chord_c.sort();
+pub mod chord;
+pub mod diff;
+pub mod interval;
+pub mod pitch;
+pub mod progression;
+pub mod scale;
 
-    if buf.len() < 8 {
-        return false;
-    }
+pub use chord::{identify_chord, identify_chord_in_key, ChordMatch, ChordQuality};
+pub use diff::{diff_notes, Mistake, NoteDiff, NoteError};
+pub use interval::{Interval, IntervalQuality};
+pub use pitch::{Key, Letter, Mode, Pitch, PitchClass, PitchParseError};
+pub use progression::{Progression, RomanNumeral};
+pub use scale::{Direction, Scale, ScaleMatch, ScaleRequest, ScaleVerification};
 
-    let key_downs: Vec<&KeyMessage> = buf
-        .iter()
-        .filter(|m| m.message_type == MidiMessageTypes::NoteOn)
-        .collect();
+/// A note from key down to key up. Timestamps are in microseconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoteSpan {
+    pub key: u8,
+    pub velocity: u8,
+    pub start: u64,
+    /// None while the key is still held down
+    pub end: Option<u64>,
+    pub channel: u8,
+}
 
-    let timestamp_threshold = 30000;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Articulation {
+    /// the note is held at least until the next one starts
+    Legato,
+    NonLegato,
+    /// the note is held for less than half the time until the next one starts
+    Staccato,
+}
 
-    if key_downs.len() >= 4 {
-        let mut start_run_index = 0;
-        while start_run_index < key_downs.len() {
-            let mut end_run_idx = start_run_index + 1;
+impl NoteSpan {
+    pub fn duration(&self) -> Option<u64> {
+        self.end.map(|end| end - self.start)
+    }
 
-            while end_run_idx < key_downs.len()
-                && key_downs[end_run_idx].timestamp - key_downs[start_run_index].timestamp
-                    < timestamp_threshold
-            {
-                end_run_idx += 1;
-            }
+    /// Duration, treating an unterminated note as ending at `now`
+    pub fn duration_until(&self, now: u64) -> u64 {
+        self.end.unwrap_or(now).saturating_sub(self.start)
+    }
 
-            let mut key_down_notes: Vec<&str> = key_downs
-                .get(start_run_index..end_run_idx)
-                .unwrap()
-                .iter()
-                .map(|m| m.note_name())
-                .collect();
-            key_down_notes.sort();
+    /// How many microseconds this note overlaps `next`, negative if there was
+    /// a gap between them. None if this note hasn't ended.
+    pub fn overlap_with(&self, next: &NoteSpan) -> Option<i64> {
+        self.end.map(|end| end as i64 - next.start as i64)
+    }
 
-            if key_down_notes == major_minor_chord_c {
-                return true;
-            } else {
-                trace!(
-                    "run indices = ({},{}), sorted_notes = {:?}, reference = {:?}",
-                    start_run_index,
-                    end_run_idx,
-                    key_down_notes,
-                    major_minor_chord_c
-                );
-            }
-            start_run_index += 1
+    /// How this note connects to `next`. None if this note hasn't ended or
+    /// `next` doesn't start after it (e.g. both are in the same chord).
+    pub fn articulation(&self, next: &NoteSpan) -> Option<Articulation> {
+        if next.start <= self.start {
+            return None;
         }
+        let inter_onset = (next.start - self.start) as f64;
+        let duration = self.duration()? as f64;
+        Some(if duration >= inter_onset {
+            Articulation::Legato
+        } else if duration < inter_onset / 2.0 {
+            Articulation::Staccato
+        } else {
+            Articulation::NonLegato
+        })
     }
+}
 
-    return false;
+/// Pairs key downs with key ups, in chronological order of the key downs.
+///
+/// A key down for a key that is already down (on the same channel) ends the
+/// earlier note where the new one starts, and a key up that doesn't match any
+/// open note is ignored. Notes that haven't been released have no end.
+pub fn note_spans(chronological_key_events: &[KeyMessage]) -> Vec<NoteSpan> {
+    let mut spans: Vec<NoteSpan> = Vec::new();
+    let mut open: HashMap<(u8, u8), usize> = HashMap::new();
+    for event in chronological_key_events {
+        let id = (event.channel, event.key);
+        if let Some(idx) = open.remove(&id) {
+            spans[idx].end = Some(event.timestamp);
+        }
+        if event.message_type == MidiMessageTypes::NoteOn {
+            open.insert(id, spans.len());
+            spans.push(NoteSpan {
+                key: event.key,
+                velocity: event.velocity,
+                start: event.timestamp,
+                end: None,
+                channel: event.channel,
+            });
+        }
+    }
+    spans
 }
 
 ///
@@ -0,0 +1,466 @@
+use std::{fmt, str::FromStr};
+
+use super::pitch::{Key, PitchClass};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Major6,
+    Minor6,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Augmented7,
+    AugmentedMajor7,
+    Dominant7Sus4,
+    Add9,
+    MinorAdd9,
+    Add11,
+    Add13,
+    Dominant9,
+    Major9,
+    Minor9,
+    Dominant7Flat9,
+    Dominant7Sharp9,
+    Dominant7Sharp11,
+    Dominant7Flat5,
+    Dominant7Flat13,
+}
+
+impl ChordQuality {
+    pub const ALL: [ChordQuality; 29] = [
+        ChordQuality::Major,
+        ChordQuality::Minor,
+        ChordQuality::Diminished,
+        ChordQuality::Augmented,
+        ChordQuality::Sus2,
+        ChordQuality::Sus4,
+        ChordQuality::Major6,
+        ChordQuality::Minor6,
+        ChordQuality::Dominant7,
+        ChordQuality::Major7,
+        ChordQuality::Minor7,
+        ChordQuality::MinorMajor7,
+        ChordQuality::HalfDiminished7,
+        ChordQuality::Diminished7,
+        ChordQuality::Augmented7,
+        ChordQuality::AugmentedMajor7,
+        ChordQuality::Dominant7Sus4,
+        ChordQuality::Add9,
+        ChordQuality::MinorAdd9,
+        ChordQuality::Add11,
+        ChordQuality::Add13,
+        ChordQuality::Dominant9,
+        ChordQuality::Major9,
+        ChordQuality::Minor9,
+        ChordQuality::Dominant7Flat9,
+        ChordQuality::Dominant7Sharp9,
+        ChordQuality::Dominant7Sharp11,
+        ChordQuality::Dominant7Flat5,
+        ChordQuality::Dominant7Flat13,
+    ];
+
+    /// Semitones above the root, stacked in thirds so that the index of the
+    /// note in the bass is the inversion number (extensions are above 12)
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Sus2 => &[0, 2, 7],
+            ChordQuality::Sus4 => &[0, 5, 7],
+            ChordQuality::Major6 => &[0, 4, 7, 9],
+            ChordQuality::Minor6 => &[0, 3, 7, 9],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+            ChordQuality::MinorMajor7 => &[0, 3, 7, 11],
+            ChordQuality::HalfDiminished7 => &[0, 3, 6, 10],
+            ChordQuality::Diminished7 => &[0, 3, 6, 9],
+            ChordQuality::Augmented7 => &[0, 4, 8, 10],
+            ChordQuality::AugmentedMajor7 => &[0, 4, 8, 11],
+            ChordQuality::Dominant7Sus4 => &[0, 5, 7, 10],
+            ChordQuality::Add9 => &[0, 4, 7, 14],
+            ChordQuality::MinorAdd9 => &[0, 3, 7, 14],
+            ChordQuality::Add11 => &[0, 4, 7, 17],
+            ChordQuality::Add13 => &[0, 4, 7, 21],
+            ChordQuality::Dominant9 => &[0, 4, 7, 10, 14],
+            ChordQuality::Major9 => &[0, 4, 7, 11, 14],
+            ChordQuality::Minor9 => &[0, 3, 7, 10, 14],
+            ChordQuality::Dominant7Flat9 => &[0, 4, 7, 10, 13],
+            ChordQuality::Dominant7Sharp9 => &[0, 4, 7, 10, 15],
+            ChordQuality::Dominant7Sharp11 => &[0, 4, 7, 10, 18],
+            ChordQuality::Dominant7Flat5 => &[0, 4, 6, 10],
+            ChordQuality::Dominant7Flat13 => &[0, 4, 7, 10, 20],
+        }
+    }
+
+    /// Suffix for a chord symbol, e.g. "m7" in "Am7"
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Major6 => "6",
+            ChordQuality::Minor6 => "m6",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::MinorMajor7 => "m(maj7)",
+            ChordQuality::HalfDiminished7 => "m7b5",
+            ChordQuality::Diminished7 => "dim7",
+            ChordQuality::Augmented7 => "7#5",
+            ChordQuality::AugmentedMajor7 => "maj7#5",
+            ChordQuality::Dominant7Sus4 => "7sus4",
+            ChordQuality::Add9 => "add9",
+            ChordQuality::MinorAdd9 => "m(add9)",
+            ChordQuality::Add11 => "add11",
+            ChordQuality::Add13 => "add13",
+            ChordQuality::Dominant9 => "9",
+            ChordQuality::Major9 => "maj9",
+            ChordQuality::Minor9 => "m9",
+            ChordQuality::Dominant7Flat9 => "7b9",
+            ChordQuality::Dominant7Sharp9 => "7#9",
+            ChordQuality::Dominant7Sharp11 => "7#11",
+            ChordQuality::Dominant7Flat5 => "7b5",
+            ChordQuality::Dominant7Flat13 => "7b13",
+        }
+    }
+
+    /// Name as it would be spoken, e.g. "minor seventh"
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "major",
+            ChordQuality::Minor => "minor",
+            ChordQuality::Diminished => "diminished",
+            ChordQuality::Augmented => "augmented",
+            ChordQuality::Sus2 => "suspended second",
+            ChordQuality::Sus4 => "suspended fourth",
+            ChordQuality::Major6 => "major sixth",
+            ChordQuality::Minor6 => "minor sixth",
+            ChordQuality::Dominant7 => "dominant seventh",
+            ChordQuality::Major7 => "major seventh",
+            ChordQuality::Minor7 => "minor seventh",
+            ChordQuality::MinorMajor7 => "minor major seventh",
+            ChordQuality::HalfDiminished7 => "half diminished seventh",
+            ChordQuality::Diminished7 => "diminished seventh",
+            ChordQuality::Augmented7 => "augmented seventh",
+            ChordQuality::AugmentedMajor7 => "augmented major seventh",
+            ChordQuality::Dominant7Sus4 => "dominant seventh suspended fourth",
+            ChordQuality::Add9 => "add nine",
+            ChordQuality::MinorAdd9 => "minor add nine",
+            ChordQuality::Add11 => "add eleven",
+            ChordQuality::Add13 => "add thirteen",
+            ChordQuality::Dominant9 => "dominant ninth",
+            ChordQuality::Major9 => "major ninth",
+            ChordQuality::Minor9 => "minor ninth",
+            ChordQuality::Dominant7Flat9 => "seven flat nine",
+            ChordQuality::Dominant7Sharp9 => "seven sharp nine",
+            ChordQuality::Dominant7Sharp11 => "seven sharp eleven",
+            ChordQuality::Dominant7Flat5 => "seven flat five",
+            ChordQuality::Dominant7Flat13 => "seven flat thirteen",
+        }
+    }
+
+    // rough measure of how unusual a chord is, used to break ties between
+    // readings of the same notes
+    fn complexity(&self) -> i32 {
+        match self {
+            ChordQuality::Major | ChordQuality::Minor => 0,
+            ChordQuality::Dominant7 | ChordQuality::Major7 | ChordQuality::Minor7 => 1,
+            ChordQuality::Diminished | ChordQuality::Augmented => 1,
+            ChordQuality::Sus2 | ChordQuality::Sus4 => 2,
+            ChordQuality::Major6 | ChordQuality::Minor6 | ChordQuality::Add13 => 2,
+            ChordQuality::HalfDiminished7 | ChordQuality::Diminished7 => 2,
+            ChordQuality::Add9 | ChordQuality::MinorAdd9 => 2,
+            ChordQuality::Dominant9 | ChordQuality::Major9 | ChordQuality::Minor9 => 3,
+            ChordQuality::MinorMajor7
+            | ChordQuality::Augmented7
+            | ChordQuality::AugmentedMajor7
+            | ChordQuality::Dominant7Sus4
+            | ChordQuality::Add11 => 3,
+            _ => 4,
+        }
+    }
+
+    /// Close-position keys for the chord on `root` (a MIDI key), with the
+    /// lowest `inversion` notes moved up an octave, e.g. C E G becomes E G C
+    /// in first inversion
+    pub fn voicing(&self, root: u8, inversion: u8) -> Vec<u8> {
+        let intervals = self.intervals();
+        let inversion = usize::from(inversion).min(intervals.len() - 1);
+        let mut keys: Vec<u8> = intervals[inversion..]
+            .iter()
+            .map(|i| root + i)
+            .chain(intervals[..inversion].iter().map(|i| root + i + 12))
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Pitch classes as bits, bit 0 for C
+    pub fn pitch_class_mask(&self, root: u8) -> u16 {
+        self.intervals()
+            .iter()
+            .fold(0, |mask, i| mask | 1 << ((root + i) % 12))
+    }
+}
+
+impl FromStr for ChordQuality {
+    type Err = String;
+
+    /// A chord symbol suffix like "m7", "maj" for major, or a name like
+    /// "minor-seventh"
+    fn from_str(s: &str) -> Result<ChordQuality, String> {
+        let s = s.trim();
+        if s == "maj" || s == "M" {
+            return Ok(ChordQuality::Major);
+        }
+        let name = s.to_lowercase().replace(['-', '_'], " ");
+        ChordQuality::ALL
+            .into_iter()
+            .find(|quality| quality.symbol() == s || quality.name() == name)
+            .ok_or_else(|| format!("unknown chord quality \"{}\"", s))
+    }
+}
+
+/// One reading of a set of notes as a chord
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChordMatch {
+    pub root: PitchClass,
+    pub quality: ChordQuality,
+    pub bass: PitchClass,
+    /// 0 for root position, 1 for first inversion and so on. None when the
+    /// bass is an extension or not in the chord at all (e.g. C/D).
+    pub inversion: Option<u8>,
+    /// True if the chord is missing its perfect fifth
+    pub omitted_fifth: bool,
+    /// Higher is a more likely reading
+    pub score: i32,
+}
+
+impl ChordMatch {
+    pub fn is_slash_chord(&self) -> bool {
+        self.bass != self.root
+    }
+
+    /// e.g. "Cmaj7/E"
+    pub fn symbol(&self) -> String {
+        let mut symbol = format!("{}{}", self.root, self.quality.symbol());
+        if self.is_slash_chord() {
+            symbol.push_str(&format!("/{}", self.bass));
+        }
+        symbol
+    }
+
+    /// e.g. "C major seventh, first inversion" or "C major over D"
+    pub fn describe(&self) -> String {
+        self.describe_with(|pitch_class| pitch_class.to_string())
+    }
+
+    /// Like describe, with note names from `name_note` (e.g. for speech)
+    pub fn describe_with(&self, name_note: impl Fn(&PitchClass) -> String) -> String {
+        let chord = format!("{} {}", name_note(&self.root), self.quality.name());
+        match self.inversion {
+            Some(0) => chord,
+            Some(n) => format!("{}, {} inversion", chord, ordinal(n)),
+            None => format!("{} over {}", chord, name_note(&self.bass)),
+        }
+    }
+}
+
+impl fmt::Display for ChordMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+fn ordinal(n: u8) -> &'static str {
+    match n {
+        1 => "first",
+        2 => "second",
+        3 => "third",
+        _ => "fourth",
+    }
+}
+
+const PERFECT_FIFTH: u8 = 7;
+
+/// Identifies the chord formed by `keys` (MIDI keys sounding together),
+/// spelling roots as they'd be written in C major. See identify_chord_in_key.
+pub fn identify_chord(keys: &[u8]) -> Vec<ChordMatch> {
+    identify_chord_in_key(keys, &Key::C_MAJOR)
+}
+
+/// Returns every plausible reading of `keys` as a chord, best first. Needs
+/// at least three distinct pitch classes.
+///
+/// The root has to be played, but the perfect fifth may be left out of
+/// chords with four or more notes. A bass note that isn't part of the chord
+/// makes a slash chord. Add13 and 6 chords have the same notes, so a chord
+/// counts as add13 only if the sixth is voiced more than an octave above the
+/// lowest root.
+pub fn identify_chord_in_key(keys: &[u8], context: &Key) -> Vec<ChordMatch> {
+    let played = keys.iter().fold(0u16, |mask, k| mask | 1 << (k % 12));
+    if played.count_ones() < 3 {
+        return Vec::new();
+    }
+    let lowest = *keys.iter().min().unwrap();
+    let bass = lowest % 12;
+    let bass_bit = 1u16 << bass;
+
+    let mut matches = Vec::new();
+    for root in 0..12u8 {
+        let root_bit = 1u16 << root;
+        for quality in ChordQuality::ALL {
+            let tones = quality.pitch_class_mask(root);
+            let (body, foreign_bass) = if played & !tones == 0 {
+                (played, false)
+            } else if played & !tones == bass_bit {
+                (played & !bass_bit, true)
+            } else {
+                continue;
+            };
+            if body & root_bit == 0 || (foreign_bass && body.count_ones() < 3) {
+                continue;
+            }
+            let fifth_bit = 1u16 << ((root + PERFECT_FIFTH) % 12);
+            let missing = tones & !body;
+            let omitted_fifth = missing == fifth_bit && quality.intervals().len() >= 4;
+            if missing != 0 && !omitted_fifth {
+                continue;
+            }
+            if !sixth_voicing_matches(quality, keys, root) {
+                continue;
+            }
+
+            let inversion = if foreign_bass {
+                None
+            } else {
+                let bass_interval = (bass + 12 - root) % 12;
+                quality
+                    .intervals()
+                    .iter()
+                    .position(|i| *i == bass_interval)
+                    .map(|position| position as u8)
+            };
+            let mut score = 100 - 10 * quality.complexity();
+            // root position beats an inversion of a simpler chord, so C E G A
+            // is C6 rather than Am7/C
+            score -= inversion.map_or(50, |n| 15 * i32::from(n));
+            if omitted_fifth {
+                score -= 15;
+            }
+            if foreign_bass {
+                score -= 20;
+            }
+
+            matches.push(ChordMatch {
+                root: context.spell(root),
+                quality,
+                bass: context.spell(bass),
+                inversion,
+                omitted_fifth,
+                score,
+            });
+        }
+    }
+    matches.sort_by_key(|m| (-m.score, m.quality.complexity()));
+    matches
+}
+
+fn sixth_voicing_matches(quality: ChordQuality, keys: &[u8], root: u8) -> bool {
+    let is_add13 = match quality {
+        ChordQuality::Major6 => false,
+        ChordQuality::Add13 => true,
+        _ => return true,
+    };
+    let lowest_root = keys.iter().filter(|k| *k % 12 == root).min();
+    let sixth = (root + 9) % 12;
+    let voiced_as_thirteenth = match lowest_root {
+        Some(lowest_root) => keys
+            .iter()
+            .filter(|k| *k % 12 == sixth)
+            .all(|k| *k > lowest_root + 12),
+        None => false,
+    };
+    is_add13 == voiced_as_thirteenth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best(keys: &[u8]) -> ChordMatch {
+        identify_chord(keys)[0]
+    }
+
+    #[test]
+    fn triads_and_inversions() {
+        let c = best(&[48, 52, 55]);
+        assert_eq!(c.symbol(), "C");
+        assert_eq!(c.inversion, Some(0));
+        let first_inversion = best(&[52, 55, 60]);
+        assert_eq!(first_inversion.symbol(), "C/E");
+        assert_eq!(first_inversion.describe(), "C major, first inversion");
+        assert_eq!(best(&[55, 60, 64]).inversion, Some(2));
+        assert!(identify_chord(&[48, 60, 67]).is_empty());
+    }
+
+    #[test]
+    fn foreign_bass_makes_a_slash_chord() {
+        let c_over_f_sharp = best(&[54, 60, 64, 67]);
+        assert_eq!(c_over_f_sharp.symbol(), "C/F#");
+        assert_eq!(c_over_f_sharp.inversion, None);
+        assert_eq!(c_over_f_sharp.describe(), "C major over F#");
+
+        let g_over_a = best(&[45, 55, 59, 62]);
+        assert!(g_over_a.is_slash_chord());
+        assert_eq!(g_over_a.describe(), "G major over A");
+    }
+
+    #[test]
+    fn an_inversion_isnt_a_slash_chord() {
+        let seventh = best(&[46, 60, 64, 67]);
+        assert_eq!(seventh.symbol(), "C7/Bb");
+        assert_eq!(seventh.inversion, Some(3));
+    }
+
+    #[test]
+    fn sixth_within_an_octave_of_the_root_is_a_6_chord() {
+        let readings = identify_chord(&[48, 52, 55, 57]);
+        assert_eq!(readings[0].quality, ChordQuality::Major6);
+        assert!(readings.iter().all(|m| m.quality != ChordQuality::Add13));
+        // root position beats an inversion of a simpler chord
+        assert_eq!(readings[1].symbol(), "Am7/C");
+    }
+
+    #[test]
+    fn sixth_above_the_octave_is_an_add13_chord() {
+        let readings = identify_chord(&[48, 55, 60, 64, 69]);
+        assert_eq!(readings[0].symbol(), "Cadd13");
+        assert!(readings.iter().all(|m| m.quality != ChordQuality::Major6));
+        // any sixth below the thirteenth makes it a 6 chord again
+        assert_eq!(best(&[48, 57, 64, 67, 69]).quality, ChordQuality::Major6);
+    }
+
+    #[test]
+    fn fifth_may_be_left_out_of_bigger_chords() {
+        let seventh = best(&[48, 52, 58]);
+        assert_eq!(seventh.symbol(), "C7");
+        assert!(seventh.omitted_fifth);
+        assert!(identify_chord(&[48, 52, 60]).is_empty());
+    }
+}
@@ -0,0 +1,515 @@
+use std::fmt;
+
+use super::{Interval, Key, PitchClass};
+
+// alignment costs; an octave slip is cheaper than any other wrong note so that
+// it's preferred as an explanation
+const OCTAVE_SLIP_COST: u32 = 1;
+const EDIT_COST: u32 = 2;
+
+/// One difference between an expected run of keys and what was played.
+/// `index` is always a position in the expected keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NoteError {
+    WrongNote {
+        index: usize,
+        expected: u8,
+        played: u8,
+    },
+    /// The right note in the wrong octave
+    OctaveSlip {
+        index: usize,
+        expected: u8,
+        played: u8,
+    },
+    Missed {
+        index: usize,
+        expected: u8,
+    },
+    /// `played` came before expected[index], or after the last expected key
+    /// if index is the length of the run
+    Extra {
+        index: usize,
+        played: u8,
+    },
+    /// expected[index] and expected[index + 1] were played the other way around
+    Transposition {
+        index: usize,
+        first: u8,
+        second: u8,
+    },
+    /// Everything from expected[index] on was left out
+    Unfinished {
+        index: usize,
+    },
+}
+
+impl NoteError {
+    pub fn index(&self) -> usize {
+        match self {
+            NoteError::WrongNote { index, .. }
+            | NoteError::OctaveSlip { index, .. }
+            | NoteError::Missed { index, .. }
+            | NoteError::Extra { index, .. }
+            | NoteError::Transposition { index, .. }
+            | NoteError::Unfinished { index } => *index,
+        }
+    }
+}
+
+/// The cheapest alignment of played keys against expected keys, see diff_notes
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoteDiff {
+    pub expected: Vec<u8>,
+    pub played: Vec<u8>,
+    /// In the order they happened
+    pub errors: Vec<NoteError>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Step {
+    Match,
+    Substitute,
+    Transpose,
+    Miss,
+    Add,
+}
+
+/// Aligns `played` against `expected` (both chronological MIDI keys) with an
+/// edit distance that also allows swapping two neighbouring notes, and
+/// classifies every difference.
+pub fn diff_notes(expected: &[u8], played: &[u8]) -> NoteDiff {
+    let (n, m) = (expected.len(), played.len());
+    let substitution = |e: u8, p: u8| {
+        if e == p {
+            0
+        } else if e % 12 == p % 12 {
+            OCTAVE_SLIP_COST
+        } else {
+            EDIT_COST
+        }
+    };
+
+    // cost[i][j] aligns the first i expected keys with the first j played keys
+    let mut cost = vec![vec![0u32; m + 1]; n + 1];
+    for (i, row) in cost.iter_mut().enumerate() {
+        row[0] = i as u32 * EDIT_COST;
+    }
+    for (j, cell) in cost[0].iter_mut().enumerate() {
+        *cell = j as u32 * EDIT_COST;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let mut best = (cost[i - 1][j - 1] + substitution(expected[i - 1], played[j - 1]))
+                .min(cost[i - 1][j] + EDIT_COST)
+                .min(cost[i][j - 1] + EDIT_COST);
+            if is_transposition(expected, played, i, j) {
+                best = best.min(cost[i - 2][j - 2] + EDIT_COST);
+            }
+            cost[i][j] = best;
+        }
+    }
+
+    let mut steps = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let step = if i > 0
+            && j > 0
+            && cost[i][j] == cost[i - 1][j - 1] + substitution(expected[i - 1], played[j - 1])
+        {
+            if expected[i - 1] == played[j - 1] {
+                Step::Match
+            } else {
+                Step::Substitute
+            }
+        } else if is_transposition(expected, played, i, j)
+            && cost[i][j] == cost[i - 2][j - 2] + EDIT_COST
+        {
+            Step::Transpose
+        } else if i > 0 && cost[i][j] == cost[i - 1][j] + EDIT_COST {
+            Step::Miss
+        } else {
+            Step::Add
+        };
+        match step {
+            Step::Match | Step::Substitute => (i, j) = (i - 1, j - 1),
+            Step::Transpose => (i, j) = (i - 2, j - 2),
+            Step::Miss => i -= 1,
+            Step::Add => j -= 1,
+        }
+        steps.push((step, i, j));
+    }
+    steps.reverse();
+
+    let mut errors: Vec<NoteError> = steps
+        .iter()
+        .filter_map(|(step, i, j)| {
+            let (i, j) = (*i, *j);
+            match step {
+                Step::Match => None,
+                Step::Substitute if expected[i] % 12 == played[j] % 12 => {
+                    Some(NoteError::OctaveSlip {
+                        index: i,
+                        expected: expected[i],
+                        played: played[j],
+                    })
+                }
+                Step::Substitute => Some(NoteError::WrongNote {
+                    index: i,
+                    expected: expected[i],
+                    played: played[j],
+                }),
+                Step::Transpose => Some(NoteError::Transposition {
+                    index: i,
+                    first: expected[i],
+                    second: expected[i + 1],
+                }),
+                Step::Miss => Some(NoteError::Missed {
+                    index: i,
+                    expected: expected[i],
+                }),
+                Step::Add => Some(NoteError::Extra {
+                    index: i,
+                    played: played[j],
+                }),
+            }
+        })
+        .collect();
+
+    // notes missed after the last thing played mean the run stopped early
+    let trailing_misses = errors
+        .iter()
+        .rev()
+        .zip((0..n).rev())
+        .take_while(|(error, index)| {
+            matches!(error, NoteError::Missed { index: missed, .. } if missed == index)
+        })
+        .count();
+    if trailing_misses > 0 {
+        errors.truncate(errors.len() - trailing_misses);
+        errors.push(NoteError::Unfinished {
+            index: n - trailing_misses,
+        });
+    }
+
+    NoteDiff {
+        expected: expected.to_vec(),
+        played: played.to_vec(),
+        errors,
+    }
+}
+
+fn is_transposition(expected: &[u8], played: &[u8], i: usize, j: usize) -> bool {
+    i > 1
+        && j > 1
+        && expected[i - 1] != expected[i - 2]
+        && expected[i - 1] == played[j - 2]
+        && expected[i - 2] == played[j - 1]
+}
+
+impl NoteDiff {
+    pub fn is_match(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn first_error(&self) -> Option<&NoteError> {
+        self.errors.first()
+    }
+
+    /// The first mistake, in the terms it's explained in
+    pub fn mistake(&self) -> Option<Mistake> {
+        let error = self.first_error()?;
+        let mistake = match *error {
+            NoteError::WrongNote {
+                index: 0,
+                expected,
+                played,
+            } => Mistake::StartedOn { played, expected },
+            NoteError::WrongNote {
+                index,
+                expected,
+                played,
+            } => {
+                let from = self.expected[index - 1];
+                let wanted = i16::from(expected) - i16::from(from);
+                let went = i16::from(played) - i16::from(from);
+                if went == 0 {
+                    Mistake::Repeated { note: from, wanted }
+                } else if went.signum() == wanted.signum() {
+                    Mistake::WrongStep { from, went, wanted }
+                } else {
+                    Mistake::WrongDirection { from, went, wanted }
+                }
+            }
+            NoteError::OctaveSlip {
+                expected, played, ..
+            } => Mistake::WrongOctave {
+                note: expected,
+                octaves: (i16::from(played) - i16::from(expected)) / 12,
+            },
+            NoteError::Missed { index: 0, expected } => Mistake::LeftOutFirst { note: expected },
+            NoteError::Missed { index, expected } => Mistake::LeftOut {
+                note: expected,
+                after: self.expected[index - 1],
+            },
+            NoteError::Extra { index: 0, played } => Mistake::ExtraBefore { note: played },
+            NoteError::Extra { index, played } => Mistake::Extra {
+                note: played,
+                after: self.expected[index - 1],
+            },
+            NoteError::Transposition { first, second, .. } => Mistake::Swapped { first, second },
+            NoteError::Unfinished { index: 0 } => Mistake::NothingPlayed,
+            NoteError::Unfinished { index } => Mistake::Stopped {
+                after: self.expected[index - 1],
+                short: self.expected.len() - index,
+            },
+        };
+        Some(mistake)
+    }
+
+    /// A sentence explaining the first mistake, spelling notes in `key`
+    pub fn describe(&self, key: &Key) -> Option<String> {
+        self.describe_with(key, |pc| pc.to_string())
+    }
+
+    /// Like describe, with a custom name for each note, e.g. for speech
+    pub fn describe_with(&self, key: &Key, name: impl Fn(&PitchClass) -> String) -> Option<String> {
+        Some(self.mistake()?.describe_with(key, name))
+    }
+}
+
+/// The first mistake in a NoteDiff as it's put to the player. Notes are MIDI
+/// keys and steps are in semitones, negative going down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mistake {
+    StartedOn {
+        played: u8,
+        expected: u8,
+    },
+    /// Played `note` again instead of moving on
+    Repeated {
+        note: u8,
+        wanted: i16,
+    },
+    /// Went the right way from `from` but by the wrong step
+    WrongStep {
+        from: u8,
+        went: i16,
+        wanted: i16,
+    },
+    WrongDirection {
+        from: u8,
+        went: i16,
+        wanted: i16,
+    },
+    WrongOctave {
+        note: u8,
+        octaves: i16,
+    },
+    LeftOutFirst {
+        note: u8,
+    },
+    LeftOut {
+        note: u8,
+        after: u8,
+    },
+    ExtraBefore {
+        note: u8,
+    },
+    Extra {
+        note: u8,
+        after: u8,
+    },
+    /// Played `second` before `first`
+    Swapped {
+        first: u8,
+        second: u8,
+    },
+    NothingPlayed,
+    /// Stopped after `after` with `short` notes to go
+    Stopped {
+        after: u8,
+        short: usize,
+    },
+}
+
+impl Mistake {
+    /// A sentence in English, spelling notes in `key` and naming them with `name`
+    pub fn describe_with(&self, key: &Key, name: impl Fn(&PitchClass) -> String) -> String {
+        let note = |k: u8| name(&key.spell(k % 12));
+        match *self {
+            Mistake::StartedOn { played, expected } => format!(
+                "you started on {} instead of {}",
+                note(played),
+                note(expected)
+            ),
+            Mistake::Repeated { note: from, wanted } => format!(
+                "you repeated {} instead of going {}",
+                note(from),
+                movement(wanted)
+            ),
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "you went {} on {} instead of a {}",
+                movement(went),
+                note(from),
+                step_name(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "you went {} on {} instead of {}",
+                movement(went),
+                note(from),
+                movement(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 { "high" } else { "low" };
+                match octaves.abs() {
+                    1 => format!("you played {} an octave too {}", note(k), height),
+                    n => format!("you played {} {} octaves too {}", note(k), n, height),
+                }
+            }
+            Mistake::LeftOutFirst { note: k } => {
+                format!("you left out the first note, {}", note(k))
+            }
+            Mistake::LeftOut { note: k, after } => {
+                format!("you left out {} after {}", note(k), note(after))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!("you played an extra {} before starting", note(k))
+            }
+            Mistake::Extra { note: k, after } => {
+                format!("you played an extra {} after {}", note(k), note(after))
+            }
+            Mistake::Swapped { first, second } => {
+                format!("you played {} before {}", note(second), note(first))
+            }
+            Mistake::NothingPlayed => "you didn't play anything".to_string(),
+            Mistake::Stopped { after, short } => {
+                format!("you stopped after {}, {} notes short", note(after), short)
+            }
+        }
+    }
+}
+
+impl fmt::Display for NoteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.describe(&Key::C_MAJOR) {
+            Some(sentence) => write!(f, "{}", sentence),
+            None => write!(f, "no mistakes"),
+        }
+    }
+}
+
+fn movement(semitones: i16) -> String {
+    let direction = if semitones < 0 { "down" } else { "up" };
+    format!("{} a {}", direction, step_name(semitones.unsigned_abs()))
+}
+
+fn step_name(semitones: u16) -> String {
+    match semitones {
+        1 => "half step".to_string(),
+        2 => "whole step".to_string(),
+        6 => "tritone".to_string(),
+        n => match Interval::from_semitones(n as i16) {
+            Some(interval) => interval.name(),
+            None => format!("{} half steps", n),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const C_MAJOR_RUN: [u8; 8] = [60, 62, 64, 65, 67, 69, 71, 72];
+
+    fn errors(played: &[u8]) -> Vec<NoteError> {
+        diff_notes(&C_MAJOR_RUN, played).errors
+    }
+
+    #[test]
+    fn a_clean_run_matches() {
+        let diff = diff_notes(&C_MAJOR_RUN, &C_MAJOR_RUN);
+        assert!(diff.is_match());
+        assert_eq!(diff.to_string(), "no mistakes");
+    }
+
+    #[test]
+    fn neighbours_played_the_other_way_around_are_a_transposition() {
+        let played = [60, 62, 65, 64, 67, 69, 71, 72];
+        assert_eq!(
+            errors(&played),
+            [NoteError::Transposition {
+                index: 2,
+                first: 64,
+                second: 65
+            }]
+        );
+        let diff = diff_notes(&C_MAJOR_RUN, &played);
+        assert_eq!(diff.to_string(), "you played F before E");
+    }
+
+    #[test]
+    fn the_right_note_in_the_wrong_octave_is_an_octave_slip() {
+        let played = [60, 62, 64, 65, 55, 69, 71, 72];
+        assert_eq!(
+            errors(&played),
+            [NoteError::OctaveSlip {
+                index: 4,
+                expected: 67,
+                played: 55
+            }]
+        );
+        let diff = diff_notes(&C_MAJOR_RUN, &played);
+        assert_eq!(diff.to_string(), "you played G an octave too low");
+
+        let played = [60, 62, 64, 65, 67, 69, 71, 96];
+        assert_eq!(
+            diff_notes(&C_MAJOR_RUN, &played).to_string(),
+            "you played C 2 octaves too high"
+        );
+    }
+
+    #[test]
+    fn wrong_notes_are_explained_as_steps() {
+        let diff = diff_notes(&C_MAJOR_RUN, &[60, 62, 63, 65, 67, 69, 71, 72]);
+        assert_eq!(
+            diff.mistake(),
+            Some(Mistake::WrongStep {
+                from: 62,
+                went: 1,
+                wanted: 2
+            })
+        );
+        assert_eq!(
+            diff.to_string(),
+            "you went up a half step on D instead of a whole step"
+        );
+        let diff = diff_notes(&C_MAJOR_RUN, &[61, 62, 64, 65, 67, 69, 71, 72]);
+        assert_eq!(diff.to_string(), "you started on C# instead of C");
+    }
+
+    #[test]
+    fn missing_and_extra_notes() {
+        assert_eq!(
+            errors(&[60, 62, 65, 67, 69, 71, 72]),
+            [NoteError::Missed {
+                index: 2,
+                expected: 64
+            }]
+        );
+        assert_eq!(
+            errors(&[60, 62, 64, 66, 65, 67, 69, 71, 72]),
+            [NoteError::Extra {
+                index: 3,
+                played: 66
+            }]
+        );
+        let diff = diff_notes(&C_MAJOR_RUN, &[60, 62, 64, 65]);
+        assert_eq!(diff.errors, [NoteError::Unfinished { index: 4 }]);
+        assert_eq!(diff.to_string(), "you stopped after F, 4 notes short");
+        assert_eq!(
+            diff_notes(&C_MAJOR_RUN, &[]).mistake(),
+            Some(Mistake::NothingPlayed)
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use std::{fmt, str::FromStr};
+
+use super::pitch::Pitch;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntervalQuality {
+    Diminished,
+    Minor,
+    Perfect,
+    Major,
+    Augmented,
+}
+
+impl IntervalQuality {
+    pub const ALL: [IntervalQuality; 5] = [
+        IntervalQuality::Diminished,
+        IntervalQuality::Minor,
+        IntervalQuality::Perfect,
+        IntervalQuality::Major,
+        IntervalQuality::Augmented,
+    ];
+
+    /// The quality of the inverted interval, e.g. major becomes minor
+    pub fn inverted(self) -> IntervalQuality {
+        match self {
+            IntervalQuality::Diminished => IntervalQuality::Augmented,
+            IntervalQuality::Minor => IntervalQuality::Major,
+            IntervalQuality::Perfect => IntervalQuality::Perfect,
+            IntervalQuality::Major => IntervalQuality::Minor,
+            IntervalQuality::Augmented => IntervalQuality::Diminished,
+        }
+    }
+
+    /// "P", "M", "m", "A" or "d"
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            IntervalQuality::Diminished => "d",
+            IntervalQuality::Minor => "m",
+            IntervalQuality::Perfect => "P",
+            IntervalQuality::Major => "M",
+            IntervalQuality::Augmented => "A",
+        }
+    }
+}
+
+impl fmt::Display for IntervalQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IntervalQuality::Diminished => "diminished",
+            IntervalQuality::Minor => "minor",
+            IntervalQuality::Perfect => "perfect",
+            IntervalQuality::Major => "major",
+            IntervalQuality::Augmented => "augmented",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Semitones in the major or perfect interval of each simple number, unison to seventh
+const MAJOR_OR_PERFECT_SEMITONES: [i16; 7] = [0, 2, 4, 5, 7, 9, 11];
+
+/// The usual spelling of 0 to 11 semitones, with the tritone as an augmented fourth
+const SPELLINGS: [(IntervalQuality, u8); 12] = [
+    (IntervalQuality::Perfect, 1),
+    (IntervalQuality::Minor, 2),
+    (IntervalQuality::Major, 2),
+    (IntervalQuality::Minor, 3),
+    (IntervalQuality::Major, 3),
+    (IntervalQuality::Perfect, 4),
+    (IntervalQuality::Augmented, 4),
+    (IntervalQuality::Perfect, 5),
+    (IntervalQuality::Minor, 6),
+    (IntervalQuality::Major, 6),
+    (IntervalQuality::Minor, 7),
+    (IntervalQuality::Major, 7),
+];
+
+/// A spelled interval of up to two octaves, so an augmented fourth and a
+/// diminished fifth are different values even though both are six semitones.
+/// The number counts letters, so a unison is 1, an octave 8 and a double
+/// octave 15.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub quality: IntervalQuality,
+    pub number: u8,
+    pub descending: bool,
+}
+
+impl Interval {
+    pub const MAX_NUMBER: u8 = 15;
+
+    /// An ascending interval, or None if there's no such interval, e.g. a
+    /// major fifth or a diminished unison
+    pub fn new(quality: IntervalQuality, number: u8) -> Option<Interval> {
+        if !(1..=Interval::MAX_NUMBER).contains(&number) {
+            return None;
+        }
+        let interval = Interval {
+            quality,
+            number,
+            descending: false,
+        };
+        let perfect = interval.is_perfect_kind();
+        let valid = match quality {
+            IntervalQuality::Perfect => perfect,
+            IntervalQuality::Major | IntervalQuality::Minor => !perfect,
+            IntervalQuality::Augmented => true,
+            IntervalQuality::Diminished => number > 1,
+        };
+        valid.then_some(interval)
+    }
+
+    /// The usual spelling of a distance in semitones, negative going down,
+    /// e.g. 7 is a perfect fifth and 6 an augmented fourth. None beyond two
+    /// octaves.
+    pub fn from_semitones(semitones: i16) -> Option<Interval> {
+        let distance = semitones.unsigned_abs();
+        if distance > 24 {
+            return None;
+        }
+        let octaves = (distance / 12) as u8;
+        let (quality, number) = match distance % 12 {
+            0 if octaves > 0 => (IntervalQuality::Perfect, 1 + 7 * octaves),
+            remainder => {
+                let (quality, number) = SPELLINGS[remainder as usize];
+                (quality, number + 7 * octaves)
+            }
+        };
+        Some(Interval {
+            quality,
+            number,
+            descending: semitones < 0,
+        })
+    }
+
+    /// The interval from `from` to `to` as spelled, e.g. C4 to F#4 is an
+    /// augmented fourth and C4 to Gb4 a diminished fifth. None for
+    /// intervals wider than two octaves or doubly augmented or diminished.
+    pub fn between(from: &Pitch, to: &Pitch) -> Option<Interval> {
+        let position = |pitch: &Pitch| {
+            let letters = i32::from(pitch.octave) * 7 + i32::from(pitch.class.letter.index());
+            let semitones = i32::from(pitch.octave) * 12
+                + i32::from(pitch.class.letter.semitone())
+                + i32::from(pitch.class.accidental);
+            (letters, semitones)
+        };
+        let (from_letters, from_semitones) = position(from);
+        let (to_letters, to_semitones) = position(to);
+        let mut steps = to_letters - from_letters;
+        let mut semitones = to_semitones - from_semitones;
+        let descending = steps < 0 || (steps == 0 && semitones < 0);
+        if descending {
+            steps = -steps;
+            semitones = -semitones;
+        }
+
+        let number = u8::try_from(steps + 1)
+            .ok()
+            .filter(|n| *n <= Interval::MAX_NUMBER)?;
+        let mut interval = Interval {
+            quality: IntervalQuality::Perfect,
+            number,
+            descending,
+        };
+        let offset = semitones as i16 - interval.major_or_perfect_semitones();
+        interval.quality = match (interval.is_perfect_kind(), offset) {
+            (true, 0) => IntervalQuality::Perfect,
+            (true, -1) => IntervalQuality::Diminished,
+            (false, 0) => IntervalQuality::Major,
+            (false, -1) => IntervalQuality::Minor,
+            (false, -2) => IntervalQuality::Diminished,
+            (_, 1) => IntervalQuality::Augmented,
+            _ => return None,
+        };
+        Some(interval)
+    }
+
+    /// Unisons, fourths, fifths and their compounds are perfect, diminished
+    /// or augmented rather than major or minor
+    fn is_perfect_kind(&self) -> bool {
+        matches!(self.simple_number(), 1 | 4 | 5 | 8)
+    }
+
+    /// The number reduced to within an octave, keeping octaves as 8
+    pub fn simple_number(&self) -> u8 {
+        match self.number {
+            1 => 1,
+            n => (n - 2) % 7 + 2,
+        }
+    }
+
+    fn major_or_perfect_semitones(&self) -> i16 {
+        let letters = self.number - 1;
+        MAJOR_OR_PERFECT_SEMITONES[usize::from(letters % 7)] + 12 * i16::from(letters / 7)
+    }
+
+    /// Size in semitones, negative if descending
+    pub fn semitones(&self) -> i16 {
+        let offset = match (self.quality, self.is_perfect_kind()) {
+            (IntervalQuality::Perfect, _) | (IntervalQuality::Major, _) => 0,
+            (IntervalQuality::Minor, _) => -1,
+            (IntervalQuality::Augmented, _) => 1,
+            (IntervalQuality::Diminished, true) => -1,
+            (IntervalQuality::Diminished, false) => -2,
+        };
+        let size = self.major_or_perfect_semitones() + offset;
+        if self.descending {
+            -size
+        } else {
+            size
+        }
+    }
+
+    /// Unisons, octaves and double octaves are usually named without "perfect"
+    pub fn is_unison_or_octave(&self) -> bool {
+        self.quality == IntervalQuality::Perfect && matches!(self.number, 1 | 8 | 15)
+    }
+
+    /// Wider than an octave
+    pub fn is_compound(&self) -> bool {
+        self.number > 8
+    }
+
+    /// The interval with compound octaves taken off, e.g. a major tenth becomes
+    /// a major third. An octave stays an octave.
+    pub fn simple(&self) -> Interval {
+        Interval {
+            number: self.simple_number(),
+            ..*self
+        }
+    }
+
+    /// What's left of the octave, e.g. a major third inverts to a minor sixth
+    /// and a unison to an octave. Compound intervals are reduced first.
+    pub fn inversion(&self) -> Interval {
+        Interval {
+            quality: self.quality.inverted(),
+            number: 9 - self.simple_number(),
+            descending: self.descending,
+        }
+    }
+
+    /// The same interval going the other way
+    pub fn reversed(&self) -> Interval {
+        Interval {
+            descending: !self.descending,
+            ..*self
+        }
+    }
+
+    /// Other spellings of the same number of semitones, e.g. a diminished
+    /// fifth for an augmented fourth
+    pub fn enharmonics(&self) -> Vec<Interval> {
+        (1..=Interval::MAX_NUMBER)
+            .flat_map(|number| {
+                IntervalQuality::ALL
+                    .iter()
+                    .filter_map(move |quality| Interval::new(*quality, number))
+            })
+            .map(|interval| Interval {
+                descending: self.descending,
+                ..interval
+            })
+            .filter(|interval| interval.semitones() == self.semitones() && interval != self)
+            .collect()
+    }
+
+    /// Short form like "P5", "m3" or "A11", with a leading "-" if descending
+    pub fn abbreviation(&self) -> String {
+        let sign = if self.descending { "-" } else { "" };
+        format!("{}{}{}", sign, self.quality.abbreviation(), self.number)
+    }
+
+    /// The English name without direction, e.g. "major third", "octave" or
+    /// "augmented eleventh"
+    pub fn name(&self) -> String {
+        match self.number {
+            15 if self.is_unison_or_octave() => "double octave".to_string(),
+            number if self.is_unison_or_octave() => number_name(number).to_string(),
+            number => format!("{} {}", self.quality, number_name(number)),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    /// The name, e.g. "perfect fifth" or "descending minor tenth"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "descending ")?;
+        }
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    /// An abbreviation like "P5", "m3", "d7" or "M10", optionally with a
+    /// leading "-" for descending
+    fn from_str(s: &str) -> Result<Interval, String> {
+        let err = || format!("can't parse \"{}\" as an interval", s);
+        let s = s.trim();
+        let (descending, rest) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let mut chars = rest.chars();
+        let quality = match chars.next() {
+            Some('d') => IntervalQuality::Diminished,
+            Some('m') => IntervalQuality::Minor,
+            Some('P') => IntervalQuality::Perfect,
+            Some('M') => IntervalQuality::Major,
+            Some('A') => IntervalQuality::Augmented,
+            _ => return Err(err()),
+        };
+        let number = chars.as_str().parse::<u8>().map_err(|_| err())?;
+        let interval = Interval::new(quality, number).ok_or_else(err)?;
+        Ok(Interval {
+            descending,
+            ..interval
+        })
+    }
+}
+
+fn number_name(number: u8) -> &'static str {
+    match number {
+        1 => "unison",
+        2 => "second",
+        3 => "third",
+        4 => "fourth",
+        5 => "fifth",
+        6 => "sixth",
+        7 => "seventh",
+        8 => "octave",
+        9 => "ninth",
+        10 => "tenth",
+        11 => "eleventh",
+        12 => "twelfth",
+        13 => "thirteenth",
+        14 => "fourteenth",
+        _ => "fifteenth",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn between(from: &str, to: &str) -> Option<Interval> {
+        Interval::between(&from.parse().unwrap(), &to.parse().unwrap())
+    }
+
+    fn interval(s: &str) -> Interval {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn augmented_fourth_and_diminished_fifth_are_spelled_apart() {
+        let augmented_fourth = between("C4", "F#4").unwrap();
+        let diminished_fifth = between("C4", "Gb4").unwrap();
+        assert_eq!(augmented_fourth, interval("A4"));
+        assert_eq!(diminished_fifth, interval("d5"));
+        assert_ne!(augmented_fourth, diminished_fifth);
+        assert_eq!(augmented_fourth.semitones(), 6);
+        assert_eq!(diminished_fifth.semitones(), 6);
+
+        assert_eq!(augmented_fourth.inversion(), diminished_fifth);
+        assert!(augmented_fourth.enharmonics().contains(&diminished_fifth));
+        assert_eq!(Interval::from_semitones(6), Some(augmented_fourth));
+        assert_eq!(diminished_fifth.to_string(), "diminished fifth");
+    }
+
+    #[test]
+    fn compound_intervals() {
+        let tenth = between("C4", "E5").unwrap();
+        assert_eq!(tenth, interval("M10"));
+        assert!(tenth.is_compound());
+        assert_eq!(tenth.semitones(), 16);
+        assert_eq!(tenth.simple(), interval("M3"));
+        assert_eq!(tenth.inversion(), interval("m6"));
+        assert_eq!(tenth.name(), "major tenth");
+
+        assert_eq!(between("C4", "F#5").unwrap().name(), "augmented eleventh");
+        assert_eq!(Interval::from_semitones(19), Some(interval("P12")));
+        assert_eq!(between("E5", "C4").unwrap().abbreviation(), "-M10");
+
+        let octave = between("C4", "C5").unwrap();
+        assert!(!octave.is_compound());
+        assert_eq!(octave.simple(), octave);
+        assert_eq!(octave.name(), "octave");
+        assert_eq!(between("C4", "C6").unwrap().name(), "double octave");
+        assert_eq!(Interval::from_semitones(24), Some(interval("P15")));
+
+        assert_eq!(between("C4", "D6"), None);
+        assert_eq!(Interval::from_semitones(25), None);
+    }
+
+    #[test]
+    fn parses_abbreviations() {
+        assert_eq!(
+            interval("-m3"),
+            Interval {
+                quality: IntervalQuality::Minor,
+                number: 3,
+                descending: true
+            }
+        );
+        assert_eq!(interval("-m3").to_string(), "descending minor third");
+        for s in ["M5", "P3", "d1", "A16", "x4", ""] {
+            assert!(s.parse::<Interval>().is_err(), "{}", s);
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::{error::Error, fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Letter {
+    C,
+    D,
+    E,
+    F,
+    G,
+    A,
+    B,
+}
+
+const LETTERS: [Letter; 7] = [
+    Letter::C,
+    Letter::D,
+    Letter::E,
+    Letter::F,
+    Letter::G,
+    Letter::A,
+    Letter::B,
+];
+
+// letters in line-of-fifths order, starting at F (-1)
+const LETTERS_BY_FIFTHS: [Letter; 7] = [
+    Letter::F,
+    Letter::C,
+    Letter::G,
+    Letter::D,
+    Letter::A,
+    Letter::E,
+    Letter::B,
+];
+
+impl Letter {
+    /// Semitones above C
+    pub fn semitone(self) -> u8 {
+        [0, 2, 4, 5, 7, 9, 11][self.index() as usize]
+    }
+
+    /// Position in the C major scale, C = 0 and B = 6
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    /// Wraps around, so 7 is C again
+    pub fn from_index(index: i32) -> Letter {
+        LETTERS[index.rem_euclid(7) as usize]
+    }
+
+    fn fifths(self) -> i32 {
+        LETTERS_BY_FIFTHS.iter().position(|l| *l == self).unwrap() as i32 - 1
+    }
+}
+
+impl fmt::Display for Letter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PitchParseError(pub String);
+
+impl fmt::Display for PitchParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "can't parse \"{}\" as a pitch", self.0)
+    }
+}
+
+impl Error for PitchParseError {}
+
+/// A spelled pitch class, so C# and Db are different values even though they
+/// share a key on the piano. Accidentals run from -2 (double flat) to 2
+/// (double sharp).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PitchClass {
+    pub letter: Letter,
+    pub accidental: i8,
+}
+
+impl PitchClass {
+    pub const fn new(letter: Letter, accidental: i8) -> PitchClass {
+        PitchClass { letter, accidental }
+    }
+
+    pub const fn natural(letter: Letter) -> PitchClass {
+        PitchClass::new(letter, 0)
+    }
+
+    /// Like from_str without trimming, but usable in constants so that
+    /// practice_program! can check note names at compile time
+    pub const fn parse(s: &str) -> Option<PitchClass> {
+        let bytes = s.as_bytes();
+        if bytes.is_empty() {
+            return None;
+        }
+        let letter = match bytes[0].to_ascii_uppercase() {
+            b'C' => Letter::C,
+            b'D' => Letter::D,
+            b'E' => Letter::E,
+            b'F' => Letter::F,
+            b'G' => Letter::G,
+            b'A' => Letter::A,
+            b'B' => Letter::B,
+            _ => return None,
+        };
+        let mut accidental: i8 = 0;
+        let mut at = 1;
+        'symbols: while at < bytes.len() {
+            let mut i = 0;
+            while i < ACCIDENTALS.len() {
+                let (symbol, step) = ACCIDENTALS[i];
+                if starts_with_at(bytes, at, symbol.as_bytes()) {
+                    accidental += step;
+                    if accidental.abs() > 2 {
+                        return None;
+                    }
+                    at += symbol.len();
+                    continue 'symbols;
+                }
+                i += 1;
+            }
+            return None;
+        }
+        Some(PitchClass { letter, accidental })
+    }
+
+    /// Spells a pitch class the way it would be written in C major, i.e.
+    /// C C# D Eb E F F# G Ab A Bb B
+    pub fn from_semitone(semitone: u8) -> PitchClass {
+        Key::C_MAJOR.spell(semitone)
+    }
+
+    /// Semitones above C, 0 to 11
+    pub fn semitone(&self) -> u8 {
+        (i16::from(self.letter.semitone()) + i16::from(self.accidental)).rem_euclid(12) as u8
+    }
+
+    pub fn is_enharmonic(&self, other: &PitchClass) -> bool {
+        self.semitone() == other.semitone()
+    }
+
+    /// Position on the line of fifths: C = 0, G = 1, F = -1, F# = 6, Bb = -2
+    pub fn fifths(&self) -> i32 {
+        self.letter.fifths() + 7 * i32::from(self.accidental)
+    }
+
+    pub fn from_fifths(fifths: i32) -> PitchClass {
+        PitchClass {
+            letter: LETTERS_BY_FIFTHS[(fifths + 1).rem_euclid(7) as usize],
+            accidental: (fifths + 1).div_euclid(7) as i8,
+        }
+    }
+}
+
+impl fmt::Display for PitchClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let accidental = if self.accidental >= 0 { "#" } else { "b" };
+        write!(
+            f,
+            "{}{}",
+            self.letter,
+            accidental.repeat(self.accidental.unsigned_abs() as usize)
+        )
+    }
+}
+
+impl FromStr for PitchClass {
+    type Err = PitchParseError;
+
+    /// Accepts a letter followed by any of #, b, x (double sharp), ♯ and ♭,
+    /// e.g. "Bb", "F##", "Fx" or "Ebb"
+    fn from_str(s: &str) -> Result<PitchClass, PitchParseError> {
+        PitchClass::parse(s.trim()).ok_or_else(|| PitchParseError(s.to_string()))
+    }
+}
+
+// each accidental and how many semitones it raises a note by
+const ACCIDENTALS: [(&str, i8); 6] = [("#", 1), ("♯", 1), ("x", 2), ("𝄪", 2), ("b", -1), ("♭", -1)];
+
+const fn starts_with_at(bytes: &[u8], at: usize, prefix: &[u8]) -> bool {
+    if at + prefix.len() > bytes.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[at + i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A pitch in scientific pitch notation, where middle C (MIDI 60) is C4 and
+/// the octave number changes between B and C. B#3 and C4 are the same key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pitch {
+    pub class: PitchClass,
+    pub octave: i8,
+}
+
+impl Pitch {
+    pub const fn new(class: PitchClass, octave: i8) -> Pitch {
+        Pitch { class, octave }
+    }
+
+    /// Spells `key` the way it would be written in C major
+    pub fn from_midi(key: u8) -> Pitch {
+        Pitch::from_midi_in_key(key, &Key::C_MAJOR)
+    }
+
+    pub fn from_midi_in_key(key: u8, context: &Key) -> Pitch {
+        let class = context.spell(key % 12);
+        let c_based =
+            i32::from(key) - i32::from(class.letter.semitone()) - i32::from(class.accidental);
+        Pitch {
+            class,
+            octave: (c_based.div_euclid(12) - 1) as i8,
+        }
+    }
+
+    /// None if the pitch is outside the MIDI range
+    pub fn midi(&self) -> Option<u8> {
+        let key = (i32::from(self.octave) + 1) * 12
+            + i32::from(self.class.letter.semitone())
+            + i32::from(self.class.accidental);
+        u8::try_from(key).ok().filter(|k| *k < 128)
+    }
+}
+
+impl fmt::Display for Pitch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.class, self.octave)
+    }
+}
+
+impl FromStr for Pitch {
+    type Err = PitchParseError;
+
+    /// A pitch class followed by an octave number, e.g. "Bb3", "F##5" or "C-1"
+    fn from_str(s: &str) -> Result<Pitch, PitchParseError> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| c.is_ascii_digit() || c == '-')
+            .ok_or_else(|| PitchParseError(s.to_string()))?;
+        let class = s[..split].parse::<PitchClass>()?;
+        let octave = s[split..]
+            .parse::<i8>()
+            .map_err(|_| PitchParseError(s.to_string()))?;
+        Ok(Pitch { class, octave })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A key, used as context for spelling notes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub tonic: PitchClass,
+    pub mode: Mode,
+}
+
+impl Key {
+    pub const C_MAJOR: Key = Key::major(PitchClass::natural(Letter::C));
+
+    pub const fn major(tonic: PitchClass) -> Key {
+        Key {
+            tonic,
+            mode: Mode::Major,
+        }
+    }
+
+    pub const fn minor(tonic: PitchClass) -> Key {
+        Key {
+            tonic,
+            mode: Mode::Minor,
+        }
+    }
+
+    /// Number of sharps in the key signature, negative for flats
+    pub fn signature(&self) -> i32 {
+        match self.mode {
+            Mode::Major => self.tonic.fifths(),
+            Mode::Minor => self.tonic.fifths() - 3,
+        }
+    }
+
+    /// The seven notes of the key (natural minor for minor keys), starting at the tonic
+    pub fn scale(&self) -> Vec<PitchClass> {
+        let mut scale: Vec<PitchClass> = (self.signature() - 1..self.signature() + 6)
+            .map(PitchClass::from_fifths)
+            .collect();
+        scale.sort_by_key(|pc| {
+            (i32::from(pc.letter.index()) - i32::from(self.tonic.letter.index())).rem_euclid(7)
+        });
+        scale
+    }
+
+    /// Spells a semitone (0 = C) in this key. Notes in the key get their key
+    /// signature spelling, so F# major has E# and Gb major has Cb. Other notes
+    /// get the single sharp, flat or natural spelling closest to the key on
+    /// the circle of fifths, preferring flats on a tie.
+    pub fn spell(&self, semitone: u8) -> PitchClass {
+        let signature = self.signature();
+        let matches = |fifths: &i32| (fifths * 7).rem_euclid(12) == i32::from(semitone % 12);
+        if let Some(diatonic) = (signature - 1..signature + 6).find(matches) {
+            return PitchClass::from_fifths(diatonic);
+        }
+        // Fb (-8) through B# (12) are all the single-accidental spellings
+        let center = signature + 2;
+        let chromatic = (-8..=12)
+            .filter(matches)
+            .min_by_key(|fifths| (fifths - center).abs())
+            .unwrap();
+        PitchClass::from_fifths(chromatic)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            Mode::Major => write!(f, "{} major", self.tonic),
+            Mode::Minor => write!(f, "{} minor", self.tonic),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// A tonic, optionally followed by "major" or "minor", e.g. "Eb", "F#
+    /// minor" or "a-minor". A trailing "m" also means minor, as in "C#m".
+    fn from_str(s: &str) -> Result<Key, String> {
+        let err = || format!("can't parse \"{}\" as a key", s);
+        let lowercase = s.trim().to_lowercase().replace(['_', ' '], "-");
+        let (tonic, mode) = match lowercase.split_once('-') {
+            Some((tonic, "major")) => (tonic, Mode::Major),
+            Some((tonic, "minor")) => (tonic, Mode::Minor),
+            Some(_) => return Err(err()),
+            None if lowercase.len() > 1 && lowercase.ends_with('m') => {
+                (&lowercase[..lowercase.len() - 1], Mode::Minor)
+            }
+            None => (lowercase.as_str(), Mode::Major),
+        };
+        let tonic = tonic.parse::<PitchClass>().map_err(|_| err())?;
+        Ok(Key { tonic, mode })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pc(s: &str) -> PitchClass {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn fifths_round_trip() {
+        assert_eq!(pc("C").fifths(), 0);
+        assert_eq!(pc("F#").fifths(), 6);
+        assert_eq!(pc("Bb").fifths(), -2);
+        assert_eq!(PitchClass::from_fifths(-1), pc("F"));
+        assert_eq!(PitchClass::from_fifths(12), pc("B#"));
+        // Fbb to Bx, every spelling with up to two accidentals
+        for fifths in -15..=19 {
+            let class = PitchClass::from_fifths(fifths);
+            assert!(class.accidental.abs() <= 2, "{}", class);
+            assert_eq!(class.fifths(), fifths);
+        }
+    }
+
+    #[test]
+    fn spells_notes_in_the_key_signature() {
+        let f_sharp_major = Key::major(pc("F#"));
+        assert_eq!(f_sharp_major.spell(5), pc("E#"));
+        assert_eq!(Key::major(pc("Gb")).spell(11), pc("Cb"));
+        assert_eq!(Key::minor(pc("D")).spell(10), pc("Bb"));
+        assert_eq!(
+            f_sharp_major.scale(),
+            ["F#", "G#", "A#", "B", "C#", "D#", "E#"].map(pc)
+        );
+        // chromatic notes lean towards the key, flats on a tie
+        let c_major: Vec<String> = (0..12)
+            .map(|semitone| Key::C_MAJOR.spell(semitone).to_string())
+            .collect();
+        assert_eq!(
+            c_major,
+            ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"]
+        );
+        assert_eq!(Key::major(pc("E")).spell(3), pc("D#"));
+    }
+
+    #[test]
+    fn spells_midi_keys_with_the_right_octave() {
+        let c_sharp_major = Key::major(pc("C#"));
+        assert_eq!(Pitch::from_midi(60).to_string(), "C4");
+        assert_eq!(
+            Pitch::from_midi_in_key(60, &c_sharp_major).to_string(),
+            "B#3"
+        );
+        assert_eq!(
+            Pitch::from_midi_in_key(71, &Key::major(pc("Gb"))).to_string(),
+            "Cb5"
+        );
+        for key in [0, 59, 60, 71, 72, 127] {
+            assert_eq!(
+                Pitch::from_midi_in_key(key, &c_sharp_major).midi(),
+                Some(key)
+            );
+        }
+    }
+
+    #[test]
+    fn parses_pitches_and_pitch_classes() {
+        assert_eq!(pc("Fx"), PitchClass::new(Letter::F, 2));
+        assert_eq!(pc("bb"), PitchClass::new(Letter::B, -1));
+        assert_eq!(pc(" E♭ "), PitchClass::new(Letter::E, -1));
+        assert!("H".parse::<PitchClass>().is_err());
+        assert!("C###".parse::<PitchClass>().is_err());
+
+        let lowest: Pitch = "C-1".parse().unwrap();
+        assert_eq!(lowest, Pitch::new(pc("C"), -1));
+        assert_eq!(lowest.midi(), Some(0));
+        assert_eq!("Bb3".parse::<Pitch>().unwrap().midi(), Some(58));
+        assert_eq!("G9".parse::<Pitch>().unwrap().midi(), Some(127));
+        assert_eq!("G#9".parse::<Pitch>().unwrap().midi(), None);
+        assert!("C".parse::<Pitch>().is_err());
+        assert!("C4x".parse::<Pitch>().is_err());
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!("C#m".parse(), Ok(Key::minor(pc("C#"))));
+        assert_eq!("a-minor".parse(), Ok(Key::minor(pc("A"))));
+        assert_eq!("F# minor".parse(), Ok(Key::minor(pc("F#"))));
+        assert_eq!("Eb major".parse(), Ok(Key::major(pc("Eb"))));
+        assert_eq!("bb".parse(), Ok(Key::major(pc("Bb"))));
+        assert!("C dorian".parse::<Key>().is_err());
+        assert!("m".parse::<Key>().is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use std::{fmt, str::FromStr};
+
+use super::chord::{ChordMatch, ChordQuality};
+use super::pitch::{Key, Mode, PitchClass};
+
+/// Triad on each degree of a major key, tonic first
+const MAJOR_TRIADS: [ChordQuality; 7] = [
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Minor,
+    ChordQuality::Major,
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Diminished,
+];
+
+/// Seventh chord on each degree of a major key, tonic first
+const MAJOR_SEVENTHS: [ChordQuality; 7] = [
+    ChordQuality::Major7,
+    ChordQuality::Minor7,
+    ChordQuality::Minor7,
+    ChordQuality::Major7,
+    ChordQuality::Dominant7,
+    ChordQuality::Minor7,
+    ChordQuality::HalfDiminished7,
+];
+
+/// Triad on each degree of a minor key, with the dominant and leading-tone
+/// chords from harmonic minor as usual
+const MINOR_TRIADS: [ChordQuality; 7] = [
+    ChordQuality::Minor,
+    ChordQuality::Diminished,
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Major,
+    ChordQuality::Major,
+    ChordQuality::Diminished,
+];
+
+/// Seventh chord on each degree of a minor key, as for MINOR_TRIADS
+const MINOR_SEVENTHS: [ChordQuality; 7] = [
+    ChordQuality::Minor7,
+    ChordQuality::HalfDiminished7,
+    ChordQuality::Major7,
+    ChordQuality::Minor7,
+    ChordQuality::Dominant7,
+    ChordQuality::Major7,
+    ChordQuality::Diminished7,
+];
+
+fn diatonic_triads(mode: Mode) -> &'static [ChordQuality; 7] {
+    match mode {
+        Mode::Major => &MAJOR_TRIADS,
+        Mode::Minor => &MINOR_TRIADS,
+    }
+}
+
+fn diatonic_sevenths(mode: Mode) -> &'static [ChordQuality; 7] {
+    match mode {
+        Mode::Major => &MAJOR_SEVENTHS,
+        Mode::Minor => &MINOR_SEVENTHS,
+    }
+}
+
+const NUMERALS: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
+
+/// A chord named by its function in a key, e.g. ii, V7 or V/V
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RomanNumeral {
+    /// Degree of the root, 1 for the tonic
+    pub degree: u8,
+    pub quality: ChordQuality,
+    /// For secondary chords, the degree they lead to, e.g. 5 in V/V
+    pub of: Option<u8>,
+}
+
+impl RomanNumeral {
+    /// None unless `degree` and `of` are 1 to 7
+    pub fn new(degree: u8, quality: ChordQuality, of: Option<u8>) -> Option<RomanNumeral> {
+        let valid = |degree: u8| (1..=7).contains(&degree);
+        if !valid(degree) || of.is_some_and(|of| !valid(of)) {
+            return None;
+        }
+        Some(RomanNumeral {
+            degree,
+            quality,
+            of,
+        })
+    }
+
+    /// The root in `key`. Secondary chords count degrees in the major key of
+    /// the chord they lead to, so V/ii in C is A.
+    pub fn root(&self, key: &Key) -> PitchClass {
+        match self.of {
+            Some(of) => {
+                let tonic =
+                    Self::degree_root(key, of, diatonic_triads(key.mode)[usize::from(of - 1)]);
+                Key::major(tonic).scale()[usize::from(self.degree - 1)]
+            }
+            None => Self::degree_root(key, self.degree, self.quality),
+        }
+    }
+
+    /// The note `degree` of `key`, raised to the leading tone under a
+    /// minor or diminished chord on the seventh degree of a minor key, so
+    /// vii° in A minor is on G# but VII is on G
+    fn degree_root(key: &Key, degree: u8, quality: ChordQuality) -> PitchClass {
+        let note = key.scale()[usize::from(degree - 1)];
+        if key.mode == Mode::Minor && degree == 7 && RomanNumeral::is_lower_case(quality) {
+            return PitchClass::new(note.letter, note.accidental + 1);
+        }
+        note
+    }
+
+    /// Close-position keys in root position with the root at or above `lowest`
+    pub fn voicing(&self, key: &Key, lowest: u8) -> Vec<u8> {
+        let root = self.root(key).semitone();
+        let root_key = lowest + (root + 12 - lowest % 12) % 12;
+        self.quality.voicing(root_key, 0)
+    }
+
+    /// What `chord` does in `key`. Major and dominant seventh chords that
+    /// aren't diatonic are read as secondary dominants where possible, so D
+    /// major in C is V/V, but G major stays V. Whatever it's read as has the
+    /// chord's root as its root().
+    pub fn analyze(chord: &ChordMatch, key: &Key) -> Option<RomanNumeral> {
+        let root = chord.root.semitone();
+        let scale = key.scale();
+        // the leading tone of a minor key counts as its seventh degree too
+        let degree_of = |semitone: u8| {
+            (1..=7u8).find(|degree| {
+                let note = scale[usize::from(degree - 1)].semitone();
+                note == semitone % 12
+                    || (key.mode == Mode::Minor && *degree == 7 && (note + 1) % 12 == semitone % 12)
+            })
+        };
+        let candidates = [
+            degree_of(root)
+                .filter(|degree| {
+                    let i = usize::from(degree - 1);
+                    chord.quality == diatonic_triads(key.mode)[i]
+                        || chord.quality == diatonic_sevenths(key.mode)[i]
+                })
+                .and_then(|degree| RomanNumeral::new(degree, chord.quality, None)),
+            // the tonic's dominant is plain V
+            degree_of(root + 5)
+                .filter(|of| *of > 1)
+                .filter(|_| matches!(chord.quality, ChordQuality::Major | ChordQuality::Dominant7))
+                .and_then(|of| RomanNumeral::new(5, chord.quality, Some(of))),
+            degree_of(root).and_then(|degree| RomanNumeral::new(degree, chord.quality, None)),
+        ];
+        candidates
+            .into_iter()
+            .flatten()
+            .find(|numeral| numeral.root(key).semitone() == root)
+    }
+
+    /// Minor and diminished chords are written in lower case
+    fn is_lower_case(quality: ChordQuality) -> bool {
+        quality.intervals()[1] == 3
+    }
+}
+
+impl fmt::Display for RomanNumeral {
+    /// e.g. "ii", "V7", "vii°" or "V7/vi"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let numeral = NUMERALS[usize::from(self.degree - 1)];
+        if RomanNumeral::is_lower_case(self.quality) {
+            write!(f, "{}", numeral.to_lowercase())?;
+        } else {
+            write!(f, "{}", numeral)?;
+        }
+        // case already tells major from minor, and a bare 6 would read as
+        // first inversion
+        let suffix = match self.quality {
+            ChordQuality::Major | ChordQuality::Minor => "",
+            ChordQuality::Diminished => "°",
+            ChordQuality::Augmented => "+",
+            ChordQuality::Dominant7 | ChordQuality::Minor7 => "7",
+            ChordQuality::HalfDiminished7 => "ø7",
+            ChordQuality::Diminished7 => "°7",
+            ChordQuality::Major6 | ChordQuality::Minor6 => "add6",
+            ChordQuality::MinorMajor7 => "maj7",
+            ChordQuality::MinorAdd9 => "add9",
+            ChordQuality::Minor9 => "9",
+            quality => quality.symbol(),
+        };
+        write!(f, "{}", suffix)?;
+        if let Some(of) = self.of {
+            let numeral = NUMERALS[usize::from(of - 1)];
+            // cased as in a major key, since the numeral doesn't know its key
+            if RomanNumeral::is_lower_case(MAJOR_TRIADS[usize::from(of - 1)]) {
+                write!(f, "/{}", numeral.to_lowercase())?;
+            } else {
+                write!(f, "/{}", numeral)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RomanNumeral {
+    type Err = String;
+
+    /// A numeral in upper case for major or lower case for minor, then an
+    /// optional suffix ("7", "maj7", "°" or "o", "°7", "ø7", "+", "add6") and "/" with
+    /// the degree of a secondary chord, e.g. "ii7", "viio" or "V7/V"
+    fn from_str(s: &str) -> Result<RomanNumeral, String> {
+        let err = || format!("can't parse \"{}\" as a roman numeral", s);
+        let degree_prefix = |s: &str| {
+            let lower = s.to_lowercase();
+            // the longest match, so "iv" isn't read as "i"
+            (1..=7u8)
+                .map(|degree| (degree, NUMERALS[usize::from(degree - 1)].to_lowercase()))
+                .filter(|(_, numeral)| lower.starts_with(numeral.as_str()))
+                .max_by_key(|(_, numeral)| numeral.len())
+                .map(|(degree, numeral)| (degree, numeral.len()))
+        };
+
+        let s = s.trim();
+        let (chord, of) = match s.split_once('/') {
+            Some((chord, of)) => match degree_prefix(of) {
+                Some((degree, len)) if len == of.len() => (chord, Some(degree)),
+                _ => return Err(err()),
+            },
+            None => (s, None),
+        };
+        let (degree, len) = degree_prefix(chord).ok_or_else(err)?;
+        let upper = chord.starts_with(|c: char| c.is_ascii_uppercase());
+        let quality = match (upper, &chord[len..]) {
+            (true, "") => ChordQuality::Major,
+            (false, "") => ChordQuality::Minor,
+            (true, "7") => ChordQuality::Dominant7,
+            (false, "7") => ChordQuality::Minor7,
+            (true, "maj7") => ChordQuality::Major7,
+            (true, "+") => ChordQuality::Augmented,
+            (false, "°" | "o") => ChordQuality::Diminished,
+            (false, "°7" | "o7") => ChordQuality::Diminished7,
+            (false, "ø7" | "ø") => ChordQuality::HalfDiminished7,
+            (true, "add6") => ChordQuality::Major6,
+            (false, "add6") => ChordQuality::Minor6,
+            _ => return Err(err()),
+        };
+        RomanNumeral::new(degree, quality, of).ok_or_else(err)
+    }
+}
+
+/// Progressions asked for at each level, in major and the same in minor:
+/// plain cadences first, then the common pop and jazz progressions, then
+/// secondary dominants
+const PROGRESSIONS: [(u8, &str, &str); 13] = [
+    (1, "I IV V I", "i iv V i"),
+    (1, "I V I", "i V i"),
+    (1, "I IV I", "i iv i"),
+    (1, "I IV I V I", "i iv i V i"),
+    (2, "ii V I", "ii° V i"),
+    (2, "I vi IV V", "i VI iv V"),
+    (2, "I vi ii V", "i VI ii° V"),
+    (2, "ii7 V7 Imaj7", "iiø7 V7 i7"),
+    (2, "I IV vii° I", "i iv vii° i"),
+    (3, "I V/V V I", "i V/V V i"),
+    (3, "I V7/ii ii V7 I", "i V7/iv iv V7 i"),
+    (3, "I V7/IV IV V7 I", "i V7/VI VI V7 i"),
+    (3, "I V7/vi vi ii V I", "i V7/III III iv V i"),
+];
+
+/// Chords played one after the other
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progression {
+    pub chords: Vec<RomanNumeral>,
+}
+
+impl Progression {
+    pub const MAX_LEVEL: u8 = 3;
+
+    /// Built-in progressions in `mode` up to and including `level`
+    pub fn up_to_level(level: u8, mode: Mode) -> Vec<Progression> {
+        PROGRESSIONS
+            .iter()
+            .filter(|(progression_level, _, _)| *progression_level <= level)
+            .map(|(_, major, minor)| match mode {
+                Mode::Major => major.parse().unwrap(),
+                Mode::Minor => minor.parse().unwrap(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Progression {
+    /// e.g. "ii-V-I"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let chords: Vec<String> = self.chords.iter().map(|c| c.to_string()).collect();
+        write!(f, "{}", chords.join("-"))
+    }
+}
+
+impl FromStr for Progression {
+    type Err = String;
+
+    /// Roman numerals separated by spaces, commas or dashes, e.g. "ii V7 I"
+    fn from_str(s: &str) -> Result<Progression, String> {
+        let chords = s
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '-' || c == '–')
+            .filter(|chord| !chord.is_empty())
+            .map(|chord| chord.parse())
+            .collect::<Result<Vec<RomanNumeral>, String>>()?;
+        if chords.is_empty() {
+            return Err(format!("no chords in progression \"{}\"", s));
+        }
+        Ok(Progression { chords })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::chord::identify_chord_in_key;
+    use crate::music::pitch::Letter;
+
+    fn key(s: &str) -> Key {
+        s.parse().unwrap()
+    }
+
+    fn numeral(s: &str) -> RomanNumeral {
+        s.parse().unwrap()
+    }
+
+    /// Every reading of the chord the program plays for `numeral` in `key`,
+    /// with the root doubled in the bass
+    fn readings(numeral: &RomanNumeral, key: &Key) -> Vec<RomanNumeral> {
+        let mut keys = numeral.voicing(key, 55);
+        keys.insert(0, keys[0] - 12);
+        identify_chord_in_key(&keys, key)
+            .iter()
+            .filter_map(|chord| RomanNumeral::analyze(chord, key))
+            .collect()
+    }
+
+    #[test]
+    fn parses_case_suffixes_and_secondary_chords() {
+        assert_eq!(
+            numeral("ii"),
+            RomanNumeral::new(2, ChordQuality::Minor, None).unwrap()
+        );
+        assert_eq!(numeral("viio").quality, ChordQuality::Diminished);
+        assert_eq!(numeral("iiø7").quality, ChordQuality::HalfDiminished7);
+        assert_eq!(numeral("Imaj7").quality, ChordQuality::Major7);
+        assert_eq!(
+            numeral("V7/ii"),
+            RomanNumeral::new(5, ChordQuality::Dominant7, Some(2)).unwrap()
+        );
+        // "iv" is four, not one followed by "v"
+        assert_eq!(numeral("iv").degree, 4);
+        assert!("VIII".parse::<RomanNumeral>().is_err());
+        assert!("V/x".parse::<RomanNumeral>().is_err());
+        assert!("Vsus".parse::<RomanNumeral>().is_err());
+    }
+
+    #[test]
+    fn displays_what_it_parses() {
+        for s in [
+            "ii", "V7", "vii°", "iiø7", "vii°7", "Imaj7", "III+", "V7/ii", "V/V", "IVadd6",
+        ] {
+            assert_eq!(numeral(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn added_sixths_dont_read_as_inversions() {
+        let sixth = RomanNumeral::new(1, ChordQuality::Major6, None).unwrap();
+        assert_eq!(sixth.to_string(), "Iadd6");
+        let minor_sixth = RomanNumeral::new(2, ChordQuality::Minor6, None).unwrap();
+        assert_eq!(minor_sixth.to_string(), "iiadd6");
+    }
+
+    #[test]
+    fn roots_in_major() {
+        let c = key("C");
+        assert_eq!(numeral("V").root(&c), PitchClass::natural(Letter::G));
+        assert_eq!(numeral("vii°").root(&c), PitchClass::natural(Letter::B));
+        assert_eq!(numeral("V7/ii").root(&c), PitchClass::natural(Letter::A));
+        assert_eq!(numeral("V/V").root(&c), PitchClass::natural(Letter::D));
+        assert_eq!(
+            numeral("IV").root(&key("Eb")),
+            PitchClass::new(Letter::A, -1)
+        );
+    }
+
+    #[test]
+    fn roots_in_minor() {
+        let a = key("A minor");
+        assert_eq!(numeral("i").root(&a), PitchClass::natural(Letter::A));
+        assert_eq!(numeral("III").root(&a), PitchClass::natural(Letter::C));
+        assert_eq!(numeral("VI").root(&a), PitchClass::natural(Letter::F));
+        // the leading tone under vii°, the subtonic under VII
+        assert_eq!(numeral("vii°").root(&a), PitchClass::new(Letter::G, 1));
+        assert_eq!(numeral("VII").root(&a), PitchClass::natural(Letter::G));
+        assert_eq!(numeral("V7/III").root(&a), PitchClass::natural(Letter::G));
+        assert_eq!(numeral("V/V").root(&a), PitchClass::natural(Letter::B));
+        assert_eq!(numeral("V7/VI").root(&a), PitchClass::natural(Letter::C));
+    }
+
+    #[test]
+    fn analyzes_diatonic_and_secondary_chords_in_major() {
+        let c = key("C");
+        assert_eq!(readings(&numeral("vi"), &c)[0], numeral("vi"));
+        assert_eq!(readings(&numeral("V/V"), &c)[0], numeral("V/V"));
+        // the tonic's dominant isn't V/I
+        assert_eq!(readings(&numeral("V7"), &c)[0], numeral("V7"));
+        assert_eq!(readings(&numeral("V7/vi"), &c)[0], numeral("V7/vi"));
+    }
+
+    #[test]
+    fn analyzes_diatonic_and_secondary_chords_in_minor() {
+        let a = key("A minor");
+        assert_eq!(readings(&numeral("VI"), &a)[0], numeral("VI"));
+        assert_eq!(readings(&numeral("vii°"), &a)[0], numeral("vii°"));
+        assert_eq!(readings(&numeral("iiø7"), &a)[0], numeral("iiø7"));
+        assert_eq!(readings(&numeral("V7/iv"), &a)[0], numeral("V7/iv"));
+        assert_eq!(readings(&numeral("V7/III"), &a)[0], numeral("V7/III"));
+    }
+
+    #[test]
+    fn every_built_in_chord_reads_back_as_itself() {
+        for (mode, keys) in [
+            (Mode::Major, ["C", "G", "Eb", "F#", "Db"]),
+            (
+                Mode::Minor,
+                ["A minor", "E minor", "C minor", "F# minor", "Bb minor"],
+            ),
+        ] {
+            for key in keys.map(key) {
+                for progression in Progression::up_to_level(Progression::MAX_LEVEL, mode) {
+                    for chord in &progression.chords {
+                        assert!(
+                            readings(chord, &key).contains(chord),
+                            "{} in {} reads as {:?}",
+                            chord,
+                            key,
+                            readings(chord, &key)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_progressions() {
+        let progression: Progression = "ii7, V7 - Imaj7".parse().unwrap();
+        assert_eq!(progression.to_string(), "ii7-V7-Imaj7");
+        assert!("".parse::<Progression>().is_err());
+        assert!("I IV X".parse::<Progression>().is_err());
+    }
+}
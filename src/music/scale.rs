@@ -0,0 +1,766 @@
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use super::{
+    diff::{diff_notes, Mistake, NoteDiff},
+    pitch::{Key, Letter, Pitch, PitchClass},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Ascending,
+    Descending,
+    /// up from the starting note and back down to it
+    UpAndDown,
+    /// down from the starting note and back up to it
+    DownAndUp,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::Ascending,
+        Direction::Descending,
+        Direction::UpAndDown,
+        Direction::DownAndUp,
+    ];
+
+    /// What the other hand plays in contrary motion
+    pub fn mirrored(&self) -> Direction {
+        match self {
+            Direction::Ascending => Direction::Descending,
+            Direction::Descending => Direction::Ascending,
+            Direction::UpAndDown => Direction::DownAndUp,
+            Direction::DownAndUp => Direction::UpAndDown,
+        }
+    }
+
+    /// Like from_str without trimming, but usable in constants so that
+    /// practice_program! can check directions at compile time
+    pub const fn parse(s: &str) -> Option<Direction> {
+        let mut i = 0;
+        while i < DIRECTION_NAMES.len() {
+            if same_name(s, DIRECTION_NAMES[i].0) {
+                return Some(DIRECTION_NAMES[i].1);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// The one-way legs of a run, e.g. UpAndDown is [Ascending, Descending]
+    fn legs(&self) -> &'static [Direction] {
+        match self {
+            Direction::Ascending => &[Direction::Ascending],
+            Direction::Descending => &[Direction::Descending],
+            Direction::UpAndDown => &[Direction::Ascending, Direction::Descending],
+            Direction::DownAndUp => &[Direction::Descending, Direction::Ascending],
+        }
+    }
+}
+
+const DIRECTION_NAMES: [(&str, Direction); 6] = [
+    ("ascending", Direction::Ascending),
+    ("up", Direction::Ascending),
+    ("descending", Direction::Descending),
+    ("down", Direction::Descending),
+    ("up-and-down", Direction::UpAndDown),
+    ("down-and-up", Direction::DownAndUp),
+];
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        Direction::parse(s.trim()).ok_or_else(|| {
+            format!(
+                "unknown direction \"{}\" (expected ascending, descending, up-and-down or down-and-up)",
+                s
+            )
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Scale {
+    pub name: &'static str,
+    /// Semitone steps going up from the root, adding up to an octave
+    pub steps: &'static [u8],
+    /// Steps (still listed upwards from the root) used when going down, for
+    /// scales like melodic minor whose descending form is different
+    pub descending_steps: Option<&'static [u8]>,
+}
+
+pub const MAJOR: Scale = Scale::new("major", &[2, 2, 1, 2, 2, 2, 1]);
+pub const DORIAN: Scale = Scale::new("dorian", &[2, 1, 2, 2, 2, 1, 2]);
+pub const PHRYGIAN: Scale = Scale::new("phrygian", &[1, 2, 2, 2, 1, 2, 2]);
+pub const LYDIAN: Scale = Scale::new("lydian", &[2, 2, 2, 1, 2, 2, 1]);
+pub const MIXOLYDIAN: Scale = Scale::new("mixolydian", &[2, 2, 1, 2, 2, 1, 2]);
+pub const NATURAL_MINOR: Scale = Scale::new("natural minor", &[2, 1, 2, 2, 1, 2, 2]);
+pub const LOCRIAN: Scale = Scale::new("locrian", &[1, 2, 2, 1, 2, 2, 2]);
+pub const HARMONIC_MINOR: Scale = Scale::new("harmonic minor", &[2, 1, 2, 2, 1, 3, 1]);
+/// The classical melodic minor, which comes back down as a natural minor
+pub const MELODIC_MINOR: Scale = Scale {
+    name: "melodic minor",
+    steps: &[2, 1, 2, 2, 2, 2, 1],
+    descending_steps: Some(NATURAL_MINOR.steps),
+};
+pub const MAJOR_PENTATONIC: Scale = Scale::new("major pentatonic", &[2, 2, 3, 2, 3]);
+pub const MINOR_PENTATONIC: Scale = Scale::new("minor pentatonic", &[3, 2, 2, 3, 2]);
+pub const BLUES: Scale = Scale::new("blues", &[3, 2, 1, 1, 3, 2]);
+pub const WHOLE_TONE: Scale = Scale::new("whole tone", &[2, 2, 2, 2, 2, 2]);
+pub const OCTATONIC_HALF_WHOLE: Scale =
+    Scale::new("octatonic half whole", &[1, 2, 1, 2, 1, 2, 1, 2]);
+pub const OCTATONIC_WHOLE_HALF: Scale =
+    Scale::new("octatonic whole half", &[2, 1, 2, 1, 2, 1, 2, 1]);
+pub const CHROMATIC: Scale = Scale::new("chromatic", &[1; 12]);
+
+pub const SCALES: &[&Scale] = &[
+    &MAJOR,
+    &DORIAN,
+    &PHRYGIAN,
+    &LYDIAN,
+    &MIXOLYDIAN,
+    &NATURAL_MINOR,
+    &LOCRIAN,
+    &HARMONIC_MINOR,
+    &MELODIC_MINOR,
+    &MAJOR_PENTATONIC,
+    &MINOR_PENTATONIC,
+    &BLUES,
+    &WHOLE_TONE,
+    &OCTATONIC_HALF_WHOLE,
+    &OCTATONIC_WHOLE_HALF,
+    &CHROMATIC,
+];
+
+// names Scale::lookup accepts besides the scales' own
+const ALIASES: [(&str, &Scale); 3] = [
+    ("ionian", &MAJOR),
+    ("aeolian", &NATURAL_MINOR),
+    ("minor", &NATURAL_MINOR),
+];
+
+/// Whether two names are the same, ignoring ASCII case and which of " ", "_"
+/// and "-" separates their words
+const fn same_name(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        let separators = matches!(a[i], b' ' | b'_' | b'-') && matches!(b[i], b' ' | b'_' | b'-');
+        if !separators && !a[i].eq_ignore_ascii_case(&b[i]) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A played run that matched a scale from the catalog
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaleMatch {
+    pub scale: &'static Scale,
+    /// The first key played, i.e. the top root for runs that start going down
+    pub start_key: u8,
+    pub direction: Direction,
+    pub octaves: u8,
+}
+
+/// Longest run `recognize` looks for
+const MAX_RECOGNIZED_OCTAVES: u8 = 4;
+
+// notes past the length of a requested run before an attempt counts as finished
+const MAX_EXTRA_NOTES: usize = 4;
+
+impl Scale {
+    pub const fn new(name: &'static str, steps: &'static [u8]) -> Scale {
+        Scale {
+            name,
+            steps,
+            descending_steps: None,
+        }
+    }
+
+    /// Looks a scale up by name, ignoring case, "_" vs " " and a trailing
+    /// "scale", so "harmonic_minor_scale" finds HARMONIC_MINOR. The church
+    /// mode names ionian and aeolian also work.
+    pub fn by_name(name: &str) -> Option<&'static Scale> {
+        let normalized = name.trim().to_lowercase().replace(['_', '-'], " ");
+        Scale::lookup(normalized.trim_end_matches(" scale"))
+    }
+
+    /// Looks a scale up by its name or an alias like "aeolian", ignoring
+    /// ASCII case and taking "_" or "-" for " ". A const fn so that
+    /// practice_program! can check verifiers at compile time.
+    pub const fn lookup(name: &str) -> Option<&'static Scale> {
+        let mut i = 0;
+        while i < SCALES.len() {
+            if same_name(name, SCALES[i].name) {
+                return Some(SCALES[i]);
+            }
+            i += 1;
+        }
+        let mut i = 0;
+        while i < ALIASES.len() {
+            if same_name(name, ALIASES[i].0) {
+                return Some(ALIASES[i].1);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    fn steps_for(&self, descending: bool) -> &'static [u8] {
+        match (descending, self.descending_steps) {
+            (true, Some(steps)) => steps,
+            _ => self.steps,
+        }
+    }
+
+    /// Notes in one octave, counting the root once
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The signed interval between each pair of successive notes
+    pub fn deltas(&self, direction: Direction, octaves: u8) -> Vec<i8> {
+        let up: Vec<i8> = self
+            .steps
+            .iter()
+            .cycle()
+            .take(self.len() * usize::from(octaves))
+            .map(|s| *s as i8)
+            .collect();
+        let down: Vec<i8> = self
+            .steps_for(true)
+            .iter()
+            .rev()
+            .cycle()
+            .take(self.len() * usize::from(octaves))
+            .map(|s| -(*s as i8))
+            .collect();
+        match direction {
+            Direction::Ascending => up,
+            Direction::Descending => down,
+            Direction::UpAndDown => up.into_iter().chain(down).collect(),
+            Direction::DownAndUp => down.into_iter().chain(up).collect(),
+        }
+    }
+
+    /// MIDI keys for the scale, starting on `start_key` (the bottom root for
+    /// runs that start going up, the top root otherwise). None if the run
+    /// would leave the MIDI range.
+    pub fn keys(&self, start_key: u8, direction: Direction, octaves: u8) -> Option<Vec<u8>> {
+        let mut key = i16::from(start_key);
+        let mut keys = vec![start_key];
+        for delta in self.deltas(direction, octaves) {
+            key += i16::from(delta);
+            keys.push(u8::try_from(key).ok().filter(|k| *k < 128)?);
+        }
+        Some(keys)
+    }
+
+    /// The scale spelled from `root` for one octave in ascending order, without
+    /// repeating the root.
+    /// Seven-note scales use each letter once, so F# major has E# rather than F.
+    pub fn pitch_classes(&self, root: PitchClass, descending: bool) -> Vec<PitchClass> {
+        let steps = self.steps_for(descending);
+        let mut semitone = root.semitone();
+        let mut classes = vec![root];
+        for (i, step) in steps[..steps.len() - 1].iter().enumerate() {
+            semitone = (semitone + step) % 12;
+            classes.push(if steps.len() == 7 {
+                let letter = Letter::from_index(i32::from(root.letter.index()) + i as i32 + 1);
+                let accidental =
+                    (i16::from(semitone) - i16::from(letter.semitone()) + 6).rem_euclid(12) - 6;
+                PitchClass::new(letter, accidental as i8)
+            } else {
+                self.key(root).spell(semitone)
+            });
+        }
+        classes
+    }
+
+    /// The key notes of this scale are spelled in: major if it has a major
+    /// third, otherwise minor
+    pub fn key(&self, root: PitchClass) -> Key {
+        if self.steps.iter().take(2).sum::<u8>() == 4 {
+            Key::major(root)
+        } else {
+            Key::minor(root)
+        }
+    }
+
+    /// Every catalog scale that `chronological_keys` is a complete run of,
+    /// in any direction, for up to four octaves
+    pub fn recognize(chronological_keys: &[u8]) -> Vec<ScaleMatch> {
+        let mut matches = Vec::new();
+        let Some(start_key) = chronological_keys.first() else {
+            return matches;
+        };
+        for scale in SCALES {
+            for direction in Direction::ALL {
+                for octaves in 1..=MAX_RECOGNIZED_OCTAVES {
+                    let turns = matches!(direction, Direction::UpAndDown | Direction::DownAndUp);
+                    let expected_len =
+                        scale.len() * usize::from(octaves) * (1 + turns as usize) + 1;
+                    if expected_len == chronological_keys.len()
+                        && scale.keys(*start_key, direction, octaves).as_deref()
+                            == Some(chronological_keys)
+                    {
+                        matches.push(ScaleMatch {
+                            scale,
+                            start_key: *start_key,
+                            direction,
+                            octaves,
+                        });
+                    }
+                }
+            }
+        }
+        matches
+    }
+
+    /// The longest complete scale run at the end of `chronological_keys`
+    pub fn recognize_latest(chronological_keys: &[u8]) -> Option<ScaleMatch> {
+        let longest = 12 * 2 * usize::from(MAX_RECOGNIZED_OCTAVES) + 1;
+        let first = chronological_keys.len().saturating_sub(longest);
+        (first..chronological_keys.len()).find_map(|start| {
+            Scale::recognize(&chronological_keys[start..])
+                .first()
+                .copied()
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+/// One octave of a run going one way, e.g. C4 up to C5
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaleSection {
+    /// Only set for runs in contrary motion
+    pub hand: Option<Hand>,
+    /// Ascending or Descending
+    pub direction: Direction,
+    pub from_key: u8,
+    pub to_key: u8,
+}
+
+impl fmt::Display for ScaleSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(hand) = self.hand {
+            write!(f, "{:?} hand ", hand)?;
+        }
+        let direction = match self.direction {
+            Direction::Descending => "descending",
+            _ => "ascending",
+        };
+        write!(
+            f,
+            "{} from {} to {}",
+            direction,
+            Pitch::from_midi(self.from_key),
+            Pitch::from_midi(self.to_key)
+        )
+    }
+}
+
+/// A scale a student has been asked to play
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScaleRequest {
+    pub scale: &'static Scale,
+    pub root: PitchClass,
+    pub octaves: u8,
+    pub direction: Direction,
+    /// Octave of the first note in scientific pitch notation, None accepts any register
+    pub start_octave: Option<i8>,
+    /// Both hands start on the same key, the right hand plays `direction` and
+    /// the left hand its mirror image
+    pub contrary_motion: bool,
+}
+
+/// How much of a ScaleRequest was played, see ScaleRequest::verify
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScaleVerification {
+    /// The key the attempt was aligned to, None if no played key could start the run
+    pub start_key: Option<u8>,
+    /// Notes of the run played correctly, in order, from its start
+    pub matched_notes: usize,
+    pub expected_notes: usize,
+    pub completed_sections: Vec<ScaleSection>,
+}
+
+impl ScaleVerification {
+    pub fn is_complete(&self) -> bool {
+        self.expected_notes > 0 && self.matched_notes == self.expected_notes
+    }
+}
+
+impl ScaleRequest {
+    pub fn new(scale: &'static Scale, root: PitchClass) -> ScaleRequest {
+        ScaleRequest {
+            scale,
+            root,
+            octaves: 1,
+            direction: Direction::UpAndDown,
+            start_octave: None,
+            contrary_motion: false,
+        }
+    }
+
+    /// The notes each hand plays, starting at `start_key`. One line unless
+    /// the request is in contrary motion.
+    pub fn lines(&self, start_key: u8) -> Vec<(Option<Hand>, Direction, Vec<u8>)> {
+        let mut lines = Vec::new();
+        if self.contrary_motion {
+            for (hand, direction) in [
+                (Hand::Right, self.direction),
+                (Hand::Left, self.direction.mirrored()),
+            ] {
+                if let Some(keys) = self.scale.keys(start_key, direction, self.octaves) {
+                    lines.push((Some(hand), direction, keys));
+                }
+            }
+            if lines.len() < 2 {
+                lines.clear();
+            }
+        } else if let Some(keys) = self.scale.keys(start_key, self.direction, self.octaves) {
+            lines.push((None, self.direction, keys));
+        }
+        lines
+    }
+
+    /// Total key presses in the run. In contrary motion the shared first
+    /// (and, for runs that turn around, last) key is pressed once.
+    pub fn expected_notes(&self) -> usize {
+        let line = self.scale.len() * usize::from(self.octaves) * self.direction.legs().len() + 1;
+        if !self.contrary_motion {
+            return line;
+        }
+        let shared = if self.direction.legs().len() == 2 {
+            2
+        } else {
+            1
+        };
+        2 * line - shared
+    }
+
+    /// Octaves going one way in the run, counting each hand in contrary
+    /// motion, i.e. how many completed_sections a complete run has
+    pub fn sections(&self) -> usize {
+        let hands = if self.contrary_motion { 2 } else { 1 };
+        hands * usize::from(self.octaves) * self.direction.legs().len()
+    }
+
+    fn candidate_start_keys(&self, played: &[u8]) -> Vec<usize> {
+        let start_key = self
+            .start_octave
+            .and_then(|octave| Pitch::new(self.root, octave).midi());
+        (0..played.len())
+            .filter(|i| match start_key {
+                Some(key) => played[*i] == key,
+                None => played[*i] % 12 == self.root.semitone(),
+            })
+            .collect()
+    }
+
+    /// Lines up `chronological_keys` (key ups from the latest attempt, oldest
+    /// first) against the requested run and reports how far it got. The run
+    /// can start anywhere in the keys; the alignment that matched the most
+    /// notes wins, preferring later starts.
+    pub fn verify(&self, chronological_keys: &[u8]) -> ScaleVerification {
+        let mut best = ScaleVerification {
+            start_key: None,
+            matched_notes: 0,
+            expected_notes: self.expected_notes(),
+            completed_sections: Vec::new(),
+        };
+        for start in self.candidate_start_keys(chronological_keys) {
+            let start_key = chronological_keys[start];
+            let lines = self.lines(start_key);
+            if lines.is_empty() {
+                continue;
+            }
+            let keys: Vec<&[u8]> = lines.iter().map(|(_, _, keys)| keys.as_slice()).collect();
+            let (matched, progress) = match_interleaved(&keys, &chronological_keys[start..]);
+            if matched >= best.matched_notes {
+                best.start_key = Some(start_key);
+                best.matched_notes = matched;
+                best.completed_sections = lines
+                    .iter()
+                    .zip(progress)
+                    .flat_map(|((hand, direction, keys), played)| {
+                        self.completed_sections(*hand, *direction, keys, played)
+                    })
+                    .collect();
+            }
+        }
+        best
+    }
+
+    /// Whether an attempt at the run is over: it's at least as long as the
+    /// run and has landed back on the root, or has run well past its length
+    pub fn attempt_finished(&self, chronological_keys: &[u8]) -> bool {
+        let played = chronological_keys.len();
+        let expected = self.expected_notes();
+        let ended_on_root = chronological_keys
+            .last()
+            .is_some_and(|key| key % 12 == self.root.semitone());
+        (played >= expected && ended_on_root) || played >= expected + MAX_EXTRA_NOTES
+    }
+
+    /// Compares an attempt against the run starting on the root it was most
+    /// likely aimed at. None in contrary motion, where there's no single
+    /// expected order of notes.
+    pub fn diff(&self, chronological_keys: &[u8]) -> Option<NoteDiff> {
+        let first_played = *chronological_keys.first()?;
+        let start_key = match self.start_octave {
+            Some(octave) => Pitch::new(self.root, octave).midi()?,
+            None => {
+                // in i16, since the root below a key in the lowest octave is negative
+                let first_played = i16::from(first_played);
+                let below =
+                    first_played - (first_played - i16::from(self.root.semitone())).rem_euclid(12);
+                if below < 0 || (first_played - below > 6 && below + 12 < 128) {
+                    u8::try_from(below + 12).ok()?
+                } else {
+                    u8::try_from(below).ok()?
+                }
+            }
+        };
+        let lines = self.lines(start_key);
+        if lines.len() != 1 {
+            return None;
+        }
+        let (_, _, expected) = &lines[0];
+        Some(diff_notes(expected, chronological_keys))
+    }
+
+    /// A sentence explaining the first mistake in an attempt (see diff) from
+    /// `explain`, which is given the key to spell notes in
+    pub fn explain_mistake(
+        &self,
+        chronological_keys: &[u8],
+        explain: impl Fn(&Mistake, &Key) -> String,
+    ) -> Option<String> {
+        let mistake = self.diff(chronological_keys)?.mistake()?;
+        Some(explain(&mistake, &self.scale.key(self.root)))
+    }
+
+    fn completed_sections(
+        &self,
+        hand: Option<Hand>,
+        direction: Direction,
+        keys: &[u8],
+        played: usize,
+    ) -> Vec<ScaleSection> {
+        let section_len = self.scale.len();
+        let sections = direction
+            .legs()
+            .iter()
+            .flat_map(|leg| std::iter::repeat_n(*leg, usize::from(self.octaves)));
+        sections
+            .enumerate()
+            .take_while(|(i, _)| (i + 1) * section_len < played)
+            .map(|(i, leg)| ScaleSection {
+                hand,
+                direction: leg,
+                from_key: keys[i * section_len],
+                to_key: keys[(i + 1) * section_len],
+            })
+            .collect()
+    }
+}
+
+/// Matches `played` against one or two lines (hands) whose notes may be
+/// interleaved in any order. Both lines start on played[0]; if both end on
+/// the same key that key is only played once. Returns how many played notes
+/// matched before the first mistake, and how far each line got.
+fn match_interleaved(lines: &[&[u8]], played: &[u8]) -> (usize, Vec<usize>) {
+    if lines.len() == 1 {
+        let matched = lines[0]
+            .iter()
+            .zip(played)
+            .take_while(|(expected, played)| expected == played)
+            .count();
+        return (matched, vec![matched]);
+    }
+
+    let (a, b) = (lines[0], lines[1]);
+    if played.is_empty() || a[0] != played[0] || b[0] != played[0] {
+        return (0, vec![0, 0]);
+    }
+    // every (notes of a, notes of b) reachable after each played note
+    let mut states: HashSet<(usize, usize)> = HashSet::from([(1, 1)]);
+    let mut matched = 1;
+    let mut best = (1, 1);
+    for key in &played[1..] {
+        let mut next = HashSet::new();
+        for (i, j) in &states {
+            let (i, j) = (*i, *j);
+            if i + 1 == a.len() && j + 1 == b.len() && a[i] == *key && b[j] == *key {
+                next.insert((i + 1, j + 1));
+            }
+            if i < a.len() && a[i] == *key {
+                next.insert((i + 1, j));
+            }
+            if j < b.len() && b[j] == *key {
+                next.insert((i, j + 1));
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        matched += 1;
+        best = *next.iter().max_by_key(|(i, j)| (i + j, *i)).unwrap();
+        states = next;
+    }
+    (matched, vec![best.0, best.1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c_major() -> ScaleRequest {
+        ScaleRequest::new(&MAJOR, PitchClass::natural(Letter::C))
+    }
+
+    /// Both hands of `request` from `start_key`, right hand first at each step
+    fn contrary_motion_keys(request: &ScaleRequest, start_key: u8) -> Vec<u8> {
+        let lines = request.lines(start_key);
+        let (right, left) = (&lines[0].2, &lines[1].2);
+        let mut keys = vec![start_key];
+        for i in 1..right.len() - 1 {
+            keys.extend([right[i], left[i]]);
+        }
+        keys.push(*right.last().unwrap());
+        keys
+    }
+
+    #[test]
+    fn recognizes_runs_in_every_direction() {
+        let up = MAJOR.keys(60, Direction::Ascending, 1).unwrap();
+        assert_eq!(up, [60, 62, 64, 65, 67, 69, 71, 72]);
+        let matches = Scale::recognize(&up);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].scale, &MAJOR);
+        assert_eq!(matches[0].octaves, 1);
+
+        let down_and_up = HARMONIC_MINOR.keys(69, Direction::DownAndUp, 2).unwrap();
+        let matches = Scale::recognize(&down_and_up);
+        assert_eq!(matches[0].scale, &HARMONIC_MINOR);
+        assert_eq!(matches[0].direction, Direction::DownAndUp);
+        assert_eq!(matches[0].octaves, 2);
+
+        assert!(Scale::recognize(&up[..7]).is_empty());
+    }
+
+    #[test]
+    fn melodic_minor_comes_down_natural() {
+        let keys = MELODIC_MINOR.keys(57, Direction::UpAndDown, 1).unwrap();
+        assert_eq!(&keys[5..10], [66, 68, 69, 67, 65]);
+        assert_eq!(Scale::recognize(&keys)[0].scale, &MELODIC_MINOR);
+    }
+
+    #[test]
+    fn recognizes_the_latest_run() {
+        let mut keys = vec![40, 41, 90];
+        keys.extend(MAJOR.keys(67, Direction::Ascending, 1).unwrap());
+        let latest = Scale::recognize_latest(&keys).unwrap();
+        assert_eq!(latest.scale, &MAJOR);
+        assert_eq!(latest.start_key, 67);
+    }
+
+    #[test]
+    fn verifies_a_partial_run() {
+        let request = c_major();
+        let mut keys = vec![50];
+        keys.extend(MAJOR.keys(60, Direction::UpAndDown, 1).unwrap());
+        keys[12] = 66;
+        let verification = request.verify(&keys);
+        assert_eq!(verification.start_key, Some(60));
+        assert_eq!(verification.matched_notes, 11);
+        assert_eq!(verification.expected_notes, 15);
+        assert!(!verification.is_complete());
+        assert_eq!(verification.completed_sections.len(), 1);
+        assert_eq!(request.sections(), 2);
+        assert_eq!(
+            verification.completed_sections[0].to_string(),
+            "ascending from C4 to C5"
+        );
+    }
+
+    #[test]
+    fn verifies_contrary_motion() {
+        let request = ScaleRequest {
+            contrary_motion: true,
+            ..c_major()
+        };
+        assert_eq!(request.expected_notes(), 28);
+        assert_eq!(request.sections(), 4);
+        let keys = contrary_motion_keys(&request, 60);
+        assert_eq!(&keys[..5], [60, 62, 59, 64, 57]);
+        let verification = request.verify(&keys);
+        assert!(verification.is_complete());
+        assert_eq!(verification.completed_sections.len(), 4);
+
+        // each hand has reached its octave
+        let verification = request.verify(&keys[..15]);
+        assert_eq!(verification.matched_notes, 15);
+        let sections: Vec<String> = verification
+            .completed_sections
+            .iter()
+            .map(|section| section.to_string())
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                "Right hand ascending from C4 to C5",
+                "Left hand descending from C4 to C3"
+            ]
+        );
+
+        // a wrong note in the left hand stops both
+        let mut wrong = keys.clone();
+        wrong[4] = 58;
+        let verification = request.verify(&wrong);
+        assert_eq!(verification.matched_notes, 4);
+        assert!(verification.completed_sections.is_empty());
+        assert_eq!(request.diff(&keys), None);
+    }
+
+    #[test]
+    fn diff_starts_on_the_nearest_root() {
+        let request = c_major();
+        let diff = request.diff(&[72, 74, 76]).unwrap();
+        assert_eq!(diff.expected[0], 72);
+        let diff = request.diff(&[67, 69, 71]).unwrap();
+        assert_eq!(diff.expected[0], 72);
+
+        // the root below the first key would be under MIDI key 0
+        let b_major = ScaleRequest::new(&MAJOR, PitchClass::natural(Letter::B));
+        let diff = b_major.diff(&[1, 3, 4]).unwrap();
+        assert_eq!(diff.expected[0], 11);
+    }
+
+    #[test]
+    fn attempt_finishes_on_the_root_or_well_past_the_end() {
+        let request = c_major();
+        let run = MAJOR.keys(60, Direction::UpAndDown, 1).unwrap();
+        assert!(!request.attempt_finished(&run[..14]));
+        assert!(request.attempt_finished(&run));
+        let mut overshoot = run[..14].to_vec();
+        overshoot.extend([61, 63, 66, 68, 70]);
+        assert!(request.attempt_finished(&overshoot));
+    }
+}
@@ -1,17 +1,44 @@
 use std::{
     sync::mpsc::SyncSender,
     sync::{mpsc::Receiver, Arc},
+    time::{Duration, Instant},
 };
 
-use log::{info, trace};
+use log::{info, trace, warn};
 use rand::Rng;
 
 use crate::{
     key_handler::{ControlMessage, KeyDb},
-    midi::KeyMessage,
-    speech::{get_interval_name, get_pronunciation, say},
+    midi::{KeyMessage, MidiMessageTypes, DEFAULT_VELOCITY},
+    music::{
+        identify_chord,
+        scale::{Direction, Scale, ScaleRequest, ScaleVerification, MAJOR},
+        Interval, Key, Letter, Pitch, PitchClass,
+    },
+    speech::{Message, Priority, Speaker},
 };
 
+pub mod chord_ear_training;
+pub mod chord_progressions;
+pub mod drills;
+pub mod ear_training_stats;
+pub mod feedback;
+mod macros;
+pub mod melodic_dictation;
+pub mod note_player;
+pub mod scheduler;
+pub mod script;
+
+pub use chord_ear_training::{ChordEarTrainingPracticeProgram, VoicingStrictness};
+pub use chord_progressions::{ChordProgressionPracticeProgram, ProgressionAnswer};
+pub use drills::{harmonic_minor_scales_random_order, MajorScalesCircleOfFourths};
+pub use ear_training_stats::{EarTrainingStats, Skill, Tally};
+pub use feedback::{Cue, Earcon, Earcons, Feedback};
+pub use melodic_dictation::{MelodicDictationPracticeProgram, Melody, MelodySettings};
+pub use note_player::NotePlayer;
+pub use scheduler::{Card, Grade, Scheduler};
+pub use script::{Script, ScriptError, ScriptedPracticeProgram};
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PracticeProgramState {
     INITIALIZING,
@@ -26,11 +53,17 @@ pub trait PracticeProgram {
     fn run(self);
 }
 
+// four octaves up and down a chromatic scale
+const MAX_FREE_PLAY_RUN_LENGTH: usize = 97;
+
 pub struct FreePlayPracticeProgram {
     state: PracticeProgramState,
     ctrl_sender: SyncSender<ControlMessage>,
     key_receiver: Receiver<KeyMessage>,
     key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    // set by a key down, cleared when the chord it was part of gets announced
+    chord_pending: bool,
 }
 
 impl PracticeProgram for FreePlayPracticeProgram {
@@ -53,64 +86,75 @@ impl FreePlayPracticeProgram {
         ctrl_sender: SyncSender<ControlMessage>,
         key_receiver: Receiver<KeyMessage>,
         key_db: Arc<KeyDb>,
+        speaker: Speaker,
     ) -> FreePlayPracticeProgram {
         FreePlayPracticeProgram {
             state: PracticeProgramState::INITIALIZING,
             ctrl_sender,
             key_receiver,
             key_db,
+            speaker,
+            chord_pending: false,
         }
     }
 
-    fn on_keypress(&self, latest: KeyMessage) {
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
         log::trace!("received KeyMessage {}", latest.to_string());
-        let kmsg_log = self.key_db.flat_message_log();
-        let major_scale_deltas = [2, 2, 1, 2, 2, 2, 1];
-        let major_scale_up_and_down_deltas = [2, 2, 1, 2, 2, 2, 1, -1, -2, -2, -2, -1, -2, -2];
-
-        let harmonic_minor_scale_deltas = [2, 1, 2, 2, 1, 3, 1];
-
-        let reverse_chron_key_events = &self.key_db.last_n_key_ups_reversed(15);
-        trace!("reverse_chron_key_events = {:?}", reverse_chron_key_events);
-        if reverse_chron_key_events.len() > 7 {
-            if let Some(msg) =
-                crate::music::detect_run(&reverse_chron_key_events[0..8], &major_scale_deltas)
-            {
-                log::info!(
-                    "user played ascending section of major scale starting at {}",
-                    msg.note_name()
-                );
-            }
-            if let Some(msg) = crate::music::detect_run(
-                &reverse_chron_key_events[0..8],
-                &harmonic_minor_scale_deltas,
-            ) {
-                log::info!(
-                    "user played harmonic minor scale starting at {}",
-                    msg.note_name()
-                );
-                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
-            }
+        if latest.message_type == MidiMessageTypes::NoteOff {
+            self.announce_scale();
         }
-        if reverse_chron_key_events.len() > 14 {
-            if let Some(msg) = crate::music::detect_run(
-                &reverse_chron_key_events[0..15],
-                &major_scale_up_and_down_deltas,
-            ) {
-                log::info!(
-                    "user played up-and-down major scale starting at {}",
-                    msg.note_name()
-                );
+        self.announce_chord(latest);
+    }
+
+    fn announce_scale(&self) {
+        let chronological_key_ups: Vec<u8> = self
+            .key_db
+            .last_n_key_ups_reversed(MAX_FREE_PLAY_RUN_LENGTH)
+            .iter()
+            .rev()
+            .map(|m| m.key)
+            .collect();
+        trace!("chronological_key_ups = {:?}", chronological_key_ups);
+        if let Some(run) = Scale::recognize_latest(&chronological_key_ups) {
+            log::info!(
+                "user played {} scale {:?} for {} octave(s) starting at {}",
+                run.scale.name,
+                run.direction,
+                run.octaves,
+                Pitch::from_midi(run.start_key)
+            );
+            // an ascending run might still turn around, so only reset once it has
+            if matches!(run.direction, Direction::UpAndDown | Direction::DownAndUp) {
                 self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
             }
         }
+    }
 
-        let result = crate::music::is_minor_maj_7_chord(&kmsg_log);
-        if result {
-            log::info!(
-                "user played minor-maj7 chord starting at {}",
-                kmsg_log[0].readable_note()
-            );
+    /// Announces the chord that was sounding right before the first key up
+    /// following one or more key downs, i.e. when the chord was fullest. Keys
+    /// sustained by the pedals count, so broken chords played over the pedal
+    /// are announced too.
+    fn announce_chord(&mut self, latest: KeyMessage) {
+        if latest.message_type == MidiMessageTypes::NoteOn {
+            self.chord_pending = true;
+            return;
+        }
+        if !self.chord_pending {
+            return;
+        }
+        self.chord_pending = false;
+
+        let sounding = self
+            .key_db
+            .sounding_keys_at(latest.timestamp.saturating_sub(1));
+        if let Some(chord) = identify_chord(&sounding).first() {
+            log::info!("user played {} ({})", chord.symbol(), chord.describe());
+            // only the latest chord is worth announcing
+            self.speaker.cancel(Priority::Feedback);
+            self.speaker
+                .feedback(self.speaker.locale().describe_chord(chord));
             self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
         }
     }
@@ -121,76 +165,228 @@ pub struct CircleOfFourthsPracticeProgram {
     ctrl_sender: SyncSender<ControlMessage>,
     key_receiver: Receiver<KeyMessage>,
     key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    // key ups until this time were played over feedback and aren't part of an attempt
+    ignore_until: u64,
     current_key: usize,
+    octaves: u8,
+    direction: Direction,
+    start_octave: Option<i8>,
+    contrary_motion: bool,
+    scheduler: Option<Scheduler>,
+    asked_at: Instant,
+    // whether the current key's first attempt is already reviewed
+    current_answered: bool,
 }
 
-const KEYS_IN_CIRCLE_OF_FOURTHS_ORDER: &'static [&'static str] = &[
-    "C", "F", "Bb", "Eb", "Ab", "C#", "F#", "B", "E", "A", "D", "G",
+/// How long a note of a scale may take for the scale to count as played quickly
+const SCALE_NOTE_TIME: Duration = Duration::from_millis(600);
+
+const KEYS_IN_CIRCLE_OF_FOURTHS_ORDER: &[PitchClass] = &[
+    PitchClass::natural(Letter::C),
+    PitchClass::natural(Letter::F),
+    PitchClass::new(Letter::B, -1),
+    PitchClass::new(Letter::E, -1),
+    PitchClass::new(Letter::A, -1),
+    PitchClass::new(Letter::C, 1),
+    PitchClass::new(Letter::F, 1),
+    PitchClass::natural(Letter::B),
+    PitchClass::natural(Letter::E),
+    PitchClass::natural(Letter::A),
+    PitchClass::natural(Letter::D),
+    PitchClass::natural(Letter::G),
 ];
 
 impl CircleOfFourthsPracticeProgram {
+    /// With a `scheduler`, keys are asked for when they're due rather than
+    /// in circle of fourths order, and the program finishes when none are
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ctrl_sender: SyncSender<ControlMessage>,
         key_receiver: Receiver<KeyMessage>,
         key_db: Arc<KeyDb>,
+        speaker: Speaker,
+        octaves: u8,
+        direction: Direction,
+        start_octave: Option<i8>,
+        contrary_motion: bool,
+        scheduler: Option<Scheduler>,
     ) -> CircleOfFourthsPracticeProgram {
         CircleOfFourthsPracticeProgram {
             state: PracticeProgramState::INITIALIZING,
             ctrl_sender,
             key_receiver,
             key_db,
+            speaker,
+            ignore_until: 0,
             current_key: 0,
+            octaves,
+            direction,
+            start_octave,
+            contrary_motion,
+            scheduler,
+            asked_at: Instant::now(),
+            current_answered: false,
+        }
+    }
+
+    /// What the scheduler calls each key's scale, in circle of fourths order
+    fn scheduler_items() -> Vec<String> {
+        KEYS_IN_CIRCLE_OF_FOURTHS_ORDER
+            .iter()
+            .map(|root| format!("major-scale:{}", root))
+            .collect()
+    }
+
+    fn scale_request(&self, root: PitchClass) -> ScaleRequest {
+        ScaleRequest {
+            scale: &MAJOR,
+            root,
+            octaves: self.octaves,
+            direction: self.direction,
+            start_octave: self.start_octave,
+            contrary_motion: self.contrary_motion,
         }
     }
 
+    fn say(&self, priority: Priority, message: Message) {
+        let text = self.speaker.locale().message(&message);
+        self.speaker.say(text, priority);
+    }
+
     fn request_current_key(&mut self) {
         if self.state != PracticeProgramState::FINISHED {
             self.state = PracticeProgramState::PROMPTING;
-            say(format!(
-                "play {} mayjur",
-                get_pronunciation(KEYS_IN_CIRCLE_OF_FOURTHS_ORDER[self.current_key])
-            ));
+            self.say(
+                Priority::Prompt,
+                Message::PlayMajorScale {
+                    root: KEYS_IN_CIRCLE_OF_FOURTHS_ORDER[self.current_key],
+                    octaves: self.octaves,
+                    start_octave: self.start_octave,
+                    direction: self.direction,
+                    contrary_motion: self.contrary_motion,
+                },
+            );
+            self.asked_at = Instant::now();
             self.state = PracticeProgramState::LISTENING;
         }
     }
 
     fn advance_current_key(&mut self) {
-        if self.current_key + 1 < KEYS_IN_CIRCLE_OF_FOURTHS_ORDER.len() {
-            self.current_key += 1;
-        } else {
-            say("you've finished the program. good job!".into());
-            self.state = PracticeProgramState::FINISHED;
+        self.current_answered = false;
+        let next = match self.scheduler.as_ref() {
+            Some(scheduler) => scheduler.next_due(&Self::scheduler_items()),
+            None => {
+                Some(self.current_key + 1).filter(|i| *i < KEYS_IN_CIRCLE_OF_FOURTHS_ORDER.len())
+            }
+        };
+        match next {
+            Some(key) => self.current_key = key,
+            None => {
+                self.say(Priority::Feedback, Message::ProgramFinished);
+                self.state = PracticeProgramState::FINISHED;
+            }
         }
     }
 
-    fn on_keypress(&mut self, _latest: KeyMessage) {
-        if self.state == PracticeProgramState::FINISHED {
+    /// Tells the scheduler, if any, how the first attempt at the current key went
+    fn review(&mut self, correct: bool) {
+        if self.current_answered {
             return;
         }
+        self.current_answered = true;
+        let root = KEYS_IN_CIRCLE_OF_FOURTHS_ORDER[self.current_key];
+        let target = SCALE_NOTE_TIME * self.scale_request(root).expected_notes() as u32;
+        let grade = Grade::from_answer(correct, self.asked_at.elapsed(), target);
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.review(&Self::scheduler_items()[self.current_key], grade);
+        }
+    }
 
-        let reverse_chron_key_events = &self.key_db.last_n_key_ups_reversed(15);
-        let major_scale_up_and_down_deltas = [2, 2, 1, 2, 2, 2, 1, -1, -2, -2, -2, -1, -2, -2];
-
-        if reverse_chron_key_events.len() > 14 {
-            if let Some(msg) =
-                crate::music::detect_run(&reverse_chron_key_events, &major_scale_up_and_down_deltas)
-            {
-                log::info!(
-                    "user played major scale starting at {}",
-                    msg.readable_note()
-                );
-                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+    /// Asks for the first scale
+    pub fn start(&mut self) {
+        if self.scheduler.is_some() {
+            self.current_key = 0;
+            self.advance_current_key();
+        }
+        // doesn't ask if the scheduler has nothing due
+        self.request_current_key();
+    }
 
-                if msg.note_name() == KEYS_IN_CIRCLE_OF_FOURTHS_ORDER[self.current_key] {
-                    self.advance_current_key();
-                    self.request_current_key();
-                } else {
-                    say("You've played a major scale but in the wrong key.".into());
-                    self.request_current_key();
-                }
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
+        if self.state == PracticeProgramState::FINISHED {
+            return;
+        }
+        if self.speaker.speaking() == Some(Priority::Feedback) {
+            self.ignore_until = latest.timestamp;
+            return;
+        }
+        // no need to finish the prompt once the player has started
+        self.speaker.cancel(Priority::Prompt);
+
+        // the key db is cleared at the start of every attempt
+        let chronological_key_ups: Vec<u8> = self
+            .key_db
+            .flat_message_log()
+            .iter()
+            .filter(|m| m.message_type == MidiMessageTypes::NoteOff)
+            .filter(|m| m.timestamp > self.ignore_until)
+            .map(|m| m.key)
+            .collect();
+        let requested = KEYS_IN_CIRCLE_OF_FOURTHS_ORDER[self.current_key];
+        let request = self.scale_request(requested);
+        let verification = request.verify(&chronological_key_ups);
+        trace!("verification = {:?}", verification);
+
+        if verification.is_complete() {
+            log::info!(
+                "user played major scale starting at {}",
+                Pitch::from_midi_in_key(verification.start_key.unwrap(), &Key::major(requested))
+            );
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.review(true);
+            self.advance_current_key();
+            self.request_current_key();
+        } else if let Some(played) = self.other_key_played(requested, &chronological_key_ups) {
+            log::info!("user played major scale in {} instead", played);
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.review(false);
+            self.say(Priority::Feedback, Message::WrongKey);
+            self.request_current_key();
+        } else if request.attempt_finished(&chronological_key_ups) {
+            let locale = self.speaker.locale();
+            let explanation = request
+                .explain_mistake(&chronological_key_ups, |mistake, key| {
+                    locale.explain_mistake(mistake, key)
+                })
+                .unwrap_or_else(|| locale.message(&Message::NotQuiteRight));
+            log::info!("user made a mistake: {}", explanation);
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.review(false);
+            if let Some(message) = partly_right(&request, &verification) {
+                self.say(Priority::Feedback, message);
             }
+            self.speaker.feedback(explanation);
+            self.request_current_key();
         }
     }
+
+    fn other_key_played(
+        &self,
+        requested: PitchClass,
+        chronological_key_ups: &[u8],
+    ) -> Option<PitchClass> {
+        (0..12)
+            .filter(|semitone| *semitone != requested.semitone())
+            .map(PitchClass::from_semitone)
+            .find(|root| {
+                self.scale_request(*root)
+                    .verify(chronological_key_ups)
+                    .is_complete()
+            })
+    }
 }
 
 impl PracticeProgram for CircleOfFourthsPracticeProgram {
@@ -200,8 +396,7 @@ impl PracticeProgram for CircleOfFourthsPracticeProgram {
 
     fn run(mut self) {
         info!("starting CircleOfFourthsPracticeProgram");
-        self.request_current_key();
-        self.state = PracticeProgramState::LISTENING;
+        self.start();
         std::thread::spawn(move || loop {
             let msg = self.key_receiver.recv().unwrap();
             self.on_keypress(msg);
@@ -209,143 +404,288 @@ impl PracticeProgram for CircleOfFourthsPracticeProgram {
     }
 }
 
-enum IntervalPlaybackMode {
+/// What to tell a player about the part of a failed run they got right, if
+/// they got any of it
+fn partly_right(request: &ScaleRequest, verification: &ScaleVerification) -> Option<Message> {
+    if verification.completed_sections.is_empty() {
+        return None;
+    }
+    let sections: Vec<String> = verification
+        .completed_sections
+        .iter()
+        .map(|section| section.to_string())
+        .collect();
+    info!("user got right: {}", sections.join(", "));
+    Some(Message::PartlyRight {
+        completed: sections.len(),
+        sections: request.sections(),
+    })
+}
+
+/// How ear training plays an interval
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IntervalPlaybackMode {
+    /// One note after the other
     Open,
+    /// Both notes together
     Closed,
 }
 
+/// Lowest and highest keys the lower note of an interval can be
+const LOWEST_INTERVAL_KEY: u8 = 22;
+const HIGHEST_INTERVAL_KEY: u8 = 78;
+
 pub struct EarTrainingPracticeProgram {
     state: PracticeProgramState,
     ctrl_sender: SyncSender<ControlMessage>,
-    midi_out_sender: SyncSender<KeyMessage>,
+    player: NotePlayer,
     key_receiver: Receiver<KeyMessage>,
     key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    feedback: Feedback,
+    // key downs until this time were played over speech, or already judged,
+    // and aren't answers
+    ignore_until: u64,
     randomize_playback_modes: bool,
+    descending_intervals: bool,
     current_base_key: u8,
-    current_interval: u8,
+    current_interval: Interval,
     current_playback_mode: IntervalPlaybackMode,
+    stats: EarTrainingStats,
+    scheduler: Option<Scheduler>,
+    asked_at: Instant,
+    // whether the current question's first answer is already in the stats
+    current_answered: bool,
 }
 
+/// How long an interval may take to play back for it to count as quick
+const INTERVAL_ANSWER_TIME: Duration = Duration::from_secs(3);
+
 const SOS_KEY: u8 = 21;
 
 impl EarTrainingPracticeProgram {
+    /// With `earcons`, right and wrong answers and new questions are cued by
+    /// short phrases on the instrument instead of speech. With
+    /// `descending_intervals`, about half the intervals start on the upper note.
+    /// Intervals, registers and playback modes that `stats` show are often
+    /// answered wrong come up more often, and harder intervals are only asked
+    /// once the easier ones are answered well. With a `scheduler`, it picks
+    /// the interval instead.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ctrl_sender: SyncSender<ControlMessage>,
-        midi_out_sender: SyncSender<KeyMessage>,
+        player: NotePlayer,
         key_receiver: Receiver<KeyMessage>,
         key_db: Arc<KeyDb>,
+        speaker: Speaker,
+        earcons: Option<Earcons>,
         randomize_playback_modes: bool,
+        descending_intervals: bool,
+        stats: EarTrainingStats,
+        scheduler: Option<Scheduler>,
     ) -> EarTrainingPracticeProgram {
-        let (base_key, interval) = Self::key_and_interval();
+        let (base_key, interval) =
+            Self::key_and_interval(descending_intervals, &stats, scheduler.as_ref());
 
         EarTrainingPracticeProgram {
             state: PracticeProgramState::INITIALIZING,
-            midi_out_sender,
             ctrl_sender,
+            feedback: Feedback::new(speaker.clone(), player.clone(), key_db.clone(), earcons),
+            player,
             key_receiver,
             key_db,
+            speaker,
+            ignore_until: 0,
             randomize_playback_modes,
+            descending_intervals,
             current_base_key: base_key,
             current_interval: interval,
             current_playback_mode: IntervalPlaybackMode::Closed,
+            stats,
+            scheduler,
+            asked_at: Instant::now(),
+            current_answered: false,
+        }
+    }
+
+    /// A base key and the interval from it to the second key
+    fn key_and_interval(
+        descending_intervals: bool,
+        stats: &EarTrainingStats,
+        scheduler: Option<&Scheduler>,
+    ) -> (u8, Interval) {
+        let mut rng = rand::thread_rng();
+        let sign = if descending_intervals && rng.gen::<bool>() {
+            -1
+        } else {
+            1
+        };
+        let sizes = stats.unlocked_intervals();
+        let interval_of = |n: u8| Interval::from_semitones(sign * i16::from(n)).unwrap();
+        let scheduled = scheduler.and_then(|scheduler| {
+            let items: Vec<String> = sizes
+                .iter()
+                .map(|n| Self::scheduler_item(&interval_of(*n)))
+                .collect();
+            scheduler.next(&items)
+        });
+        let semitones = match scheduled {
+            Some(i) => sizes[i],
+            None => stats.pick(sizes, |n| Skill::Interval(interval_of(n)), &mut rng),
+        };
+        let interval = interval_of(semitones);
+
+        let registers: Vec<i8> =
+            (Self::register(LOWEST_INTERVAL_KEY)..=Self::register(HIGHEST_INTERVAL_KEY)).collect();
+        let register = stats.pick(&registers, Skill::Register, &mut rng);
+        let octave_start = (register as u8 + 1) * 12;
+        let lower_key = rng.gen_range(
+            octave_start.max(LOWEST_INTERVAL_KEY)..=(octave_start + 11).min(HIGHEST_INTERVAL_KEY),
+        );
+        if interval.descending {
+            return (lower_key + semitones, interval);
         }
+        return (lower_key, interval);
+    }
+
+    /// What the scheduler calls an interval, e.g. "interval:-m3"
+    fn scheduler_item(interval: &Interval) -> String {
+        format!("interval:{}", interval.abbreviation())
     }
 
-    fn key_and_interval() -> (u8, u8) {
-        let key = rand::thread_rng().gen_range(22..=78);
-        let interval = rand::thread_rng().gen_range(0..=12);
-        return (key, interval);
+    /// The octave `key` is in, 4 from middle C up to B4
+    fn register(key: u8) -> i8 {
+        (key / 12) as i8 - 1
+    }
+
+    /// What the current question tests
+    fn current_skills(&self) -> [Skill; 3] {
+        let lower_key = self.current_base_key.min(self.second_key());
+        [
+            Skill::Interval(self.current_interval),
+            Skill::Register(Self::register(lower_key)),
+            Skill::PlaybackMode(self.current_playback_mode),
+        ]
+    }
+
+    /// Counts the first answer to each question
+    fn record_answer(&mut self, right: bool) {
+        if self.current_answered {
+            return;
+        }
+        self.current_answered = true;
+        self.stats.record(&self.current_skills(), right);
+        if let Err(err) = self.stats.save() {
+            warn!("couldn't save ear training stats: {}", err);
+        }
+        let grade = Grade::from_answer(right, self.asked_at.elapsed(), INTERVAL_ANSWER_TIME);
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.review(&Self::scheduler_item(&self.current_interval), grade);
+        }
     }
 
     fn second_key(&self) -> u8 {
-        return self.current_base_key + self.current_interval;
+        return (i16::from(self.current_base_key) + self.current_interval.semitones()) as u8;
     }
 
-    fn on_keypress(&mut self, _latest: KeyMessage) {
+    /// Plays the first interval
+    pub fn start(&mut self) {
+        self.state = PracticeProgramState::LISTENING;
+        self.speaker
+            .prompt(self.speaker.locale().message(&Message::StartingEarTraining));
+        self.next_test();
+    }
+
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
         if self.state == PracticeProgramState::FINISHED {
             return;
         }
+        if self.speaker.is_speaking() || self.feedback.was_cueing_at(latest.timestamp) {
+            self.ignore_until = latest.timestamp;
+            return;
+        }
 
-        let last_keys = self.key_db.last_n_key_downs_reversed(2);
-        if last_keys.len() == 2 {
-            if last_keys[1].key == self.current_base_key && last_keys[0].key == self.second_key() {
-                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
-                say("perfect match".into());
-                self.next_test();
-            } else if (last_keys[1].key as i16 - last_keys[0].key as i16)
-                == self.current_interval.into()
-            {
-                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
-                say(format!(
-                    "correct interval, {}",
-                    get_interval_name(self.current_interval)
-                )
-                .into());
-                self.next_test();
-            } else if last_keys[1].key == SOS_KEY && last_keys[0].key == SOS_KEY {
-                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
-                say("here's the chord".into());
-                self.play_pair();
-            }
+        let last_keys: Vec<KeyMessage> = self
+            .key_db
+            .last_n_key_downs_reversed(2)
+            .into_iter()
+            .filter(|m| m.timestamp > self.ignore_until)
+            .collect();
+        if last_keys.len() != 2 {
+            return;
+        }
+        // either order counts, and so do both keys together
+        let (older, newer) = (last_keys[1].key, last_keys[0].key);
+        let mut answer = [older, newer];
+        answer.sort();
+        let mut question = [self.current_base_key, self.second_key()];
+        question.sort();
+
+        if older == SOS_KEY && newer == SOS_KEY {
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.feedback.give(Cue::Prompt, Message::HeresTheChord);
+            self.play_pair();
+        } else if answer == question {
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.record_answer(true);
+            self.feedback.give(Cue::Correct, Message::PerfectMatch);
+            self.next_test();
+        } else if answer[1] - answer[0] == question[1] - question[0] {
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.record_answer(true);
+            self.feedback.give(
+                Cue::Correct,
+                Message::CorrectInterval(self.current_interval),
+            );
+            self.next_test();
+        } else if newer != SOS_KEY {
+            // the second key could be the start of asking for the chord again
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.ignore_until = latest.timestamp;
+            self.record_answer(false);
+            self.feedback.give(Cue::Incorrect, Message::TryAgain);
         }
     }
 
     fn next_test(&mut self) {
-        (self.current_base_key, self.current_interval) = Self::key_and_interval();
+        (self.current_base_key, self.current_interval) = Self::key_and_interval(
+            self.descending_intervals,
+            &self.stats,
+            self.scheduler.as_ref(),
+        );
         if self.randomize_playback_modes {
-            self.current_playback_mode = if rand::random::<bool>() {
-                IntervalPlaybackMode::Open
-            } else {
-                IntervalPlaybackMode::Closed
-            }
+            self.current_playback_mode = self.stats.pick(
+                &[IntervalPlaybackMode::Open, IntervalPlaybackMode::Closed],
+                Skill::PlaybackMode,
+                &mut rand::thread_rng(),
+            );
         }
-        say("new chord".into());
+        self.current_answered = false;
+        self.feedback.give(Cue::Prompt, Message::NewChord);
 
         self.play_pair();
-    }
-
-    fn play_note(&self, key: u8, duration_millis: u64) {
-        self.send_note_on(key);
-        std::thread::sleep(std::time::Duration::from_millis(duration_millis));
-        self.send_note_off(key);
-    }
-
-    fn send_note_off(&self, key: u8) {
-        let up = KeyMessage {
-            timestamp: 0,
-            message_type: crate::midi::MidiMessageTypes::NoteOff,
-            key: key,
-        };
-        self.midi_out_sender.send(up).unwrap();
-    }
-
-    fn send_note_on(&self, key: u8) {
-        let down = KeyMessage {
-            timestamp: 0,
-            message_type: crate::midi::MidiMessageTypes::NoteOn,
-            key: key,
-        };
-        // await channel readiness
-        loop {
-            match self.midi_out_sender.try_send(down) {
-                Ok(_) => break,
-                Err(_) => std::thread::sleep(std::time::Duration::from_millis(100)),
-            }
-        }
+        self.asked_at = Instant::now();
     }
 
     fn play_pair(&self) {
+        // the notes would be drowned out by speech
+        self.speaker.wait_until_idle();
+        let note_length = Duration::from_millis(1000);
         match self.current_playback_mode {
             IntervalPlaybackMode::Open => {
-                self.play_note(self.current_base_key, 1000);
-                self.play_note(self.second_key(), 1000);
+                self.player
+                    .play_note(self.current_base_key, DEFAULT_VELOCITY, note_length);
+                self.player
+                    .play_note(self.second_key(), DEFAULT_VELOCITY, note_length);
             }
             IntervalPlaybackMode::Closed => {
-                self.send_note_on(self.current_base_key);
-                self.send_note_on(self.second_key());
-                std::thread::sleep(std::time::Duration::from_millis(1000));
-                self.send_note_off(self.current_base_key);
-                self.send_note_off(self.second_key());
+                self.player.play_chord(
+                    &[self.current_base_key, self.second_key()],
+                    DEFAULT_VELOCITY,
+                    note_length,
+                );
             }
         }
     }
@@ -358,12 +698,9 @@ impl PracticeProgram for EarTrainingPracticeProgram {
 
     fn run(mut self) {
         info!("starting EarTrainingPracticeProgram");
-        self.state = PracticeProgramState::LISTENING;
 
         std::thread::spawn(move || {
-            say("starting ear training".into());
-
-            self.next_test();
+            self.start();
 
             loop {
                 let msg = self.key_receiver.recv().unwrap();
@@ -372,3 +709,59 @@ impl PracticeProgram for EarTrainingPracticeProgram {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::practice_program::script::MAX_OCTAVES;
+
+    /// Every key of the run from `start_key`, hands alternating in contrary motion
+    fn run_keys(request: &ScaleRequest, start_key: u8) -> Vec<u8> {
+        let lines = request.lines(start_key);
+        let right = &lines[0].2;
+        let Some((_, _, left)) = lines.get(1) else {
+            return right.clone();
+        };
+        let mut keys = vec![start_key];
+        for (right, left) in right.iter().zip(left).skip(1) {
+            keys.push(*right);
+            if left != right {
+                keys.push(*left);
+            }
+        }
+        keys
+    }
+
+    #[test]
+    fn partly_right_never_counts_more_octaves_than_asked_for() {
+        for octaves in 1..=MAX_OCTAVES {
+            for direction in Direction::ALL {
+                for contrary_motion in [false, true] {
+                    let request = ScaleRequest {
+                        octaves,
+                        direction,
+                        contrary_motion,
+                        ..ScaleRequest::new(&MAJOR, PitchClass::natural(Letter::C))
+                    };
+                    let run = run_keys(&request, 60);
+                    // carrying on past the end of the run with another one
+                    let keys = [run.clone(), run].concat();
+                    for played in 1..=keys.len() {
+                        let verification = request.verify(&keys[..played]);
+                        match partly_right(&request, &verification) {
+                            Some(Message::PartlyRight {
+                                completed,
+                                sections,
+                            }) => {
+                                assert_eq!(sections, request.sections());
+                                assert!(completed <= sections, "{:?}", request);
+                            }
+                            Some(message) => panic!("unexpected {:?}", message),
+                            None => assert!(verification.completed_sections.is_empty()),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    str::FromStr,
+    sync::mpsc::{Receiver, SyncSender},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    key_handler::{ControlMessage, KeyDb},
+    midi::{KeyMessage, MidiMessageTypes, DEFAULT_VELOCITY},
+    music::{ChordMatch, ChordQuality, Key},
+    speech::{Message, Speaker},
+};
+
+use super::{
+    Cue, Earcons, Feedback, Grade, NotePlayer, PracticeProgram, PracticeProgramState, Scheduler,
+    SOS_KEY,
+};
+
+/// Triads and seventh chords asked for unless told otherwise
+pub const DEFAULT_CHORD_QUALITIES: [ChordQuality; 9] = [
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Diminished,
+    ChordQuality::Augmented,
+    ChordQuality::Dominant7,
+    ChordQuality::Major7,
+    ChordQuality::Minor7,
+    ChordQuality::HalfDiminished7,
+    ChordQuality::Diminished7,
+];
+
+/// How closely a chord has to be played back to count
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoicingStrictness {
+    /// The same notes in any octave and order
+    AnyVoicing,
+    /// The same notes with the same one in the bass
+    SameInversion,
+    /// The same keys
+    Exact,
+}
+
+impl VoicingStrictness {
+    /// Whether `answer` (keys held together) counts as `question`, assuming
+    /// both have the same pitch classes
+    fn accepts(&self, question: &[u8], answer: &[u8]) -> bool {
+        match self {
+            VoicingStrictness::AnyVoicing => true,
+            VoicingStrictness::SameInversion => {
+                question.iter().min().map(|k| k % 12) == answer.iter().min().map(|k| k % 12)
+            }
+            VoicingStrictness::Exact => {
+                let mut answer = answer.to_vec();
+                answer.sort();
+                answer.dedup();
+                question == answer
+            }
+        }
+    }
+}
+
+impl FromStr for VoicingStrictness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<VoicingStrictness, String> {
+        match s.trim().to_lowercase().replace(['_', ' '], "-").as_str() {
+            "any-voicing" | "any" => Ok(VoicingStrictness::AnyVoicing),
+            "same-inversion" | "inversion" => Ok(VoicingStrictness::SameInversion),
+            "exact" => Ok(VoicingStrictness::Exact),
+            _ => Err(format!(
+                "unknown voicing strictness \"{}\" (expected any-voicing, same-inversion or exact)",
+                s
+            )),
+        }
+    }
+}
+
+/// The lowest and highest roots asked for, so chords stay in the middle of
+/// the keyboard
+const LOWEST_ROOT: u8 = 48;
+const HIGHEST_ROOT: u8 = 66;
+
+fn pitch_class_mask(keys: &[u8]) -> u16 {
+    keys.iter().fold(0, |mask, k| mask | 1 << (k % 12))
+}
+
+/// Plays a triad or seventh chord and waits for it to be played back
+pub struct ChordEarTrainingPracticeProgram {
+    state: PracticeProgramState,
+    ctrl_sender: SyncSender<ControlMessage>,
+    player: NotePlayer,
+    key_receiver: Receiver<KeyMessage>,
+    key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    feedback: Feedback,
+    // key downs until this time were played over speech, or already judged,
+    // and aren't answers
+    ignore_until: u64,
+    // set by a key down, so the next key up is judged
+    chord_pending: bool,
+    qualities: Vec<ChordQuality>,
+    inversions: Vec<u8>,
+    strictness: VoicingStrictness,
+    current_root: u8,
+    current_quality: ChordQuality,
+    current_inversion: u8,
+    scheduler: Option<Scheduler>,
+    asked_at: Instant,
+    // whether the current chord's first answer is already reviewed
+    current_answered: bool,
+}
+
+/// How long a chord may take to play back for it to count as quick
+const CHORD_ANSWER_TIME: Duration = Duration::from_secs(4);
+
+impl ChordEarTrainingPracticeProgram {
+    /// Asks for chords of one of `qualities` (all of DEFAULT_CHORD_QUALITIES
+    /// if empty) in one of `inversions` (root position if empty); inversions
+    /// a chord doesn't have are skipped. With a `scheduler`, it picks the
+    /// quality.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ctrl_sender: SyncSender<ControlMessage>,
+        player: NotePlayer,
+        key_receiver: Receiver<KeyMessage>,
+        key_db: Arc<KeyDb>,
+        speaker: Speaker,
+        earcons: Option<Earcons>,
+        qualities: Vec<ChordQuality>,
+        inversions: Vec<u8>,
+        strictness: VoicingStrictness,
+        scheduler: Option<Scheduler>,
+    ) -> ChordEarTrainingPracticeProgram {
+        let qualities = if qualities.is_empty() {
+            DEFAULT_CHORD_QUALITIES.to_vec()
+        } else {
+            qualities
+        };
+        let inversions = if inversions.is_empty() {
+            vec![0]
+        } else {
+            inversions
+        };
+
+        ChordEarTrainingPracticeProgram {
+            state: PracticeProgramState::INITIALIZING,
+            ctrl_sender,
+            feedback: Feedback::new(speaker.clone(), player.clone(), key_db.clone(), earcons),
+            player,
+            key_receiver,
+            key_db,
+            speaker,
+            ignore_until: 0,
+            chord_pending: false,
+            current_root: LOWEST_ROOT,
+            current_quality: qualities[0],
+            current_inversion: 0,
+            qualities,
+            inversions,
+            strictness,
+            scheduler,
+            asked_at: Instant::now(),
+            current_answered: false,
+        }
+    }
+
+    /// Keys of the chord being asked for, lowest first
+    pub fn current_keys(&self) -> Vec<u8> {
+        self.current_quality
+            .voicing(self.current_root, self.current_inversion)
+    }
+
+    /// What the scheduler calls a chord quality, e.g. "chord:minor seventh"
+    fn scheduler_item(quality: &ChordQuality) -> String {
+        format!("chord:{}", quality.name())
+    }
+
+    /// Tells the scheduler, if any, how the first answer to the current chord went
+    fn review(&mut self, correct: bool) {
+        if self.current_answered {
+            return;
+        }
+        self.current_answered = true;
+        let grade = Grade::from_answer(correct, self.asked_at.elapsed(), CHORD_ANSWER_TIME);
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.review(&Self::scheduler_item(&self.current_quality), grade);
+        }
+    }
+
+    fn current_chord(&self) -> ChordMatch {
+        let keys = self.current_keys();
+        ChordMatch {
+            root: Key::C_MAJOR.spell(self.current_root % 12),
+            quality: self.current_quality,
+            bass: Key::C_MAJOR.spell(keys[0] % 12),
+            inversion: Some(self.current_inversion),
+            omitted_fifth: false,
+            score: 0,
+        }
+    }
+
+    /// Plays the first chord
+    pub fn start(&mut self) {
+        self.state = PracticeProgramState::LISTENING;
+        self.speaker
+            .prompt(self.speaker.locale().message(&Message::StartingEarTraining));
+        self.next_test();
+    }
+
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
+        if self.state == PracticeProgramState::FINISHED {
+            return;
+        }
+        if self.speaker.is_speaking() || self.feedback.was_cueing_at(latest.timestamp) {
+            self.ignore_until = latest.timestamp;
+            self.chord_pending = false;
+            return;
+        }
+
+        if latest.message_type == MidiMessageTypes::NoteOn {
+            self.chord_pending = true;
+            let last_keys: Vec<KeyMessage> = self
+                .key_db
+                .last_n_key_downs_reversed(2)
+                .into_iter()
+                .filter(|m| m.timestamp > self.ignore_until)
+                .collect();
+            if last_keys.len() == 2 && last_keys.iter().all(|m| m.key == SOS_KEY) {
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                self.chord_pending = false;
+                self.feedback.give(Cue::Prompt, Message::HeresTheChord);
+                self.play_chord();
+            }
+            return;
+        }
+        if latest.message_type != MidiMessageTypes::NoteOff || !self.chord_pending {
+            return;
+        }
+        self.chord_pending = false;
+
+        // the chord is judged when it's fullest, right before the first key up
+        let answer: Vec<u8> = self
+            .key_db
+            .held_keys_at(latest.timestamp.saturating_sub(1))
+            .into_iter()
+            .filter(|key| *key != SOS_KEY)
+            .collect();
+        if answer.is_empty() {
+            return;
+        }
+        let question = self.current_keys();
+        self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+        if pitch_class_mask(&answer) != pitch_class_mask(&question) {
+            info!("user played {:?} for {:?}", answer, question);
+            self.ignore_until = latest.timestamp;
+            self.review(false);
+            self.feedback.give(Cue::Incorrect, Message::TryAgain);
+        } else if !self.strictness.accepts(&question, &answer) {
+            info!("user voiced {:?} as {:?}", question, answer);
+            self.ignore_until = latest.timestamp;
+            self.review(false);
+            self.feedback.give(Cue::Incorrect, Message::WrongVoicing);
+        } else {
+            self.review(true);
+            self.feedback
+                .give(Cue::Correct, Message::CorrectChord(self.current_chord()));
+            self.next_test();
+        }
+    }
+
+    fn next_test(&mut self) {
+        let mut rng = rand::thread_rng();
+        let scheduled = self.scheduler.as_ref().and_then(|scheduler| {
+            let items: Vec<String> = self.qualities.iter().map(Self::scheduler_item).collect();
+            scheduler.next(&items)
+        });
+        self.current_quality = match scheduled {
+            Some(i) => self.qualities[i],
+            None => *self.qualities.choose(&mut rng).unwrap(),
+        };
+        self.current_answered = false;
+        let chord_size = self.current_quality.intervals().len() as u8;
+        self.current_inversion = self
+            .inversions
+            .iter()
+            .copied()
+            .filter(|inversion| *inversion < chord_size)
+            .collect::<Vec<u8>>()
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or(0);
+        self.current_root = rng.gen_range(LOWEST_ROOT..=HIGHEST_ROOT);
+        info!("asking for {:?}", self.current_chord());
+        self.feedback.give(Cue::Prompt, Message::NewChord);
+
+        self.play_chord();
+        self.asked_at = Instant::now();
+    }
+
+    fn play_chord(&self) {
+        // the notes would be drowned out by speech
+        self.speaker.wait_until_idle();
+        self.player.play_chord(
+            &self.current_keys(),
+            DEFAULT_VELOCITY,
+            Duration::from_millis(1500),
+        );
+    }
+}
+
+impl PracticeProgram for ChordEarTrainingPracticeProgram {
+    fn get_state(&self) -> PracticeProgramState {
+        return self.state;
+    }
+
+    fn run(mut self) {
+        info!("starting ChordEarTrainingPracticeProgram");
+
+        std::thread::spawn(move || {
+            self.start();
+
+            loop {
+                let msg = self.key_receiver.recv().unwrap();
+                self.on_keypress(msg);
+            }
+        });
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    str::FromStr,
+    sync::mpsc::{Receiver, SyncSender},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::info;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    key_handler::{ControlMessage, KeyDb},
+    midi::{KeyMessage, MidiMessageTypes, DEFAULT_VELOCITY},
+    music::{identify_chord_in_key, Key, Mode, PitchClass, Progression, RomanNumeral},
+    speech::{Message, Speaker},
+};
+
+use super::{
+    Cue, Earcons, Feedback, Grade, NotePlayer, PracticeProgram, PracticeProgramState, Scheduler,
+    SOS_KEY,
+};
+
+/// What gets played back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressionAnswer {
+    /// The root of each chord, one note at a time
+    BassLine,
+    /// Each chord, in any voicing
+    Chords,
+}
+
+impl FromStr for ProgressionAnswer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ProgressionAnswer, String> {
+        match s.trim().to_lowercase().replace(['_', ' '], "-").as_str() {
+            "bass-line" | "bass" => Ok(ProgressionAnswer::BassLine),
+            "chords" => Ok(ProgressionAnswer::Chords),
+            _ => Err(format!(
+                "unknown answer \"{}\" (expected bass-line or chords)",
+                s
+            )),
+        }
+    }
+}
+
+/// Chords are voiced from here up, with the root an octave lower in the bass
+const LOWEST_CHORD_KEY: u8 = 55;
+
+/// How long each chord may take to play back for a progression to count as quick
+const CHORD_ANSWER_TIME: Duration = Duration::from_secs(3);
+
+/// Plays a diatonic chord progression and waits for its bass line or chords
+/// to be played back, naming the first wrong chord by its function
+pub struct ChordProgressionPracticeProgram {
+    state: PracticeProgramState,
+    ctrl_sender: SyncSender<ControlMessage>,
+    player: NotePlayer,
+    key_receiver: Receiver<KeyMessage>,
+    key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    feedback: Feedback,
+    // key downs until this time were played over speech, or already judged,
+    // and aren't answers
+    ignore_until: u64,
+    // set by a key down, so the next key up is judged (chords only)
+    chord_pending: bool,
+    progressions: Vec<Progression>,
+    answer: ProgressionAnswer,
+    fixed_key: Option<Key>,
+    current_key: Key,
+    current_progression: Progression,
+    // chords of the current progression already played back right
+    answered: usize,
+    scheduler: Option<Scheduler>,
+    asked_at: Instant,
+    // whether the current progression's first attempt is already reviewed
+    current_reviewed: bool,
+}
+
+impl ChordProgressionPracticeProgram {
+    /// Asks for progressions up to `level` (1 to Progression::MAX_LEVEL) in
+    /// `key`, major or minor, or a random major key for each progression if None.
+    /// With a `scheduler`, it picks the progression.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ctrl_sender: SyncSender<ControlMessage>,
+        player: NotePlayer,
+        key_receiver: Receiver<KeyMessage>,
+        key_db: Arc<KeyDb>,
+        speaker: Speaker,
+        earcons: Option<Earcons>,
+        level: u8,
+        answer: ProgressionAnswer,
+        key: Option<Key>,
+        scheduler: Option<Scheduler>,
+    ) -> ChordProgressionPracticeProgram {
+        let progressions = Progression::up_to_level(
+            level.clamp(1, Progression::MAX_LEVEL),
+            key.map_or(Mode::Major, |key| key.mode),
+        );
+
+        ChordProgressionPracticeProgram {
+            state: PracticeProgramState::INITIALIZING,
+            ctrl_sender,
+            feedback: Feedback::new(speaker.clone(), player.clone(), key_db.clone(), earcons),
+            player,
+            key_receiver,
+            key_db,
+            speaker,
+            ignore_until: 0,
+            chord_pending: false,
+            current_key: key.unwrap_or(Key::C_MAJOR),
+            current_progression: progressions[0].clone(),
+            progressions,
+            answer,
+            fixed_key: key,
+            answered: 0,
+            scheduler,
+            asked_at: Instant::now(),
+            current_reviewed: false,
+        }
+    }
+
+    pub fn current_key(&self) -> Key {
+        self.current_key
+    }
+
+    pub fn current_progression(&self) -> &Progression {
+        &self.current_progression
+    }
+
+    /// Plays the first progression
+    pub fn start(&mut self) {
+        self.state = PracticeProgramState::LISTENING;
+        self.speaker
+            .prompt(self.speaker.locale().message(&Message::StartingEarTraining));
+        self.next_test();
+    }
+
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
+        if self.state == PracticeProgramState::FINISHED {
+            return;
+        }
+        if self.speaker.is_speaking() || self.feedback.was_cueing_at(latest.timestamp) {
+            self.ignore_until = latest.timestamp;
+            self.chord_pending = false;
+            return;
+        }
+
+        if latest.message_type == MidiMessageTypes::NoteOn {
+            let last_keys: Vec<KeyMessage> = self
+                .key_db
+                .last_n_key_downs_reversed(2)
+                .into_iter()
+                .filter(|m| m.timestamp > self.ignore_until)
+                .collect();
+            if last_keys.len() == 2 && last_keys.iter().all(|m| m.key == SOS_KEY) {
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                self.ignore_until = latest.timestamp;
+                self.chord_pending = false;
+                self.answered = 0;
+                self.feedback
+                    .give(Cue::Prompt, Message::HeresTheProgression);
+                self.play_progression();
+                return;
+            }
+            if latest.key == SOS_KEY {
+                return;
+            }
+            match self.answer {
+                ProgressionAnswer::BassLine => self.judge_bass_note(latest),
+                ProgressionAnswer::Chords => self.chord_pending = true,
+            }
+            return;
+        }
+        if latest.message_type != MidiMessageTypes::NoteOff || !self.chord_pending {
+            return;
+        }
+        self.chord_pending = false;
+
+        // the chord is judged when it's fullest, right before the first key up
+        let keys: Vec<u8> = self
+            .key_db
+            .held_keys_at(latest.timestamp.saturating_sub(1))
+            .into_iter()
+            .filter(|key| *key != SOS_KEY)
+            .collect();
+        if keys.is_empty() {
+            return;
+        }
+        let expected = self.current_progression.chords[self.answered];
+        let readings: Vec<RomanNumeral> = identify_chord_in_key(&keys, &self.current_key)
+            .iter()
+            .filter_map(|chord| RomanNumeral::analyze(chord, &self.current_key))
+            .collect();
+        if readings.contains(&expected) {
+            self.correct_chord(latest);
+        } else {
+            info!("user played {:?} for {}", keys, expected);
+            self.wrong_chord(
+                latest,
+                Message::WrongProgressionChord {
+                    position: self.answered + 1,
+                    expected,
+                    played: readings.first().copied(),
+                },
+            );
+        }
+    }
+
+    fn judge_bass_note(&mut self, latest: KeyMessage) {
+        let expected = self.current_progression.chords[self.answered].root(&self.current_key);
+        if latest.key % 12 == expected.semitone() {
+            self.correct_chord(latest);
+        } else {
+            info!("user played {} for {}", latest.key, expected);
+            self.wrong_chord(
+                latest,
+                Message::WrongMelodyNote {
+                    position: self.answered + 1,
+                    expected,
+                    played: self.current_key.spell(latest.key % 12),
+                },
+            );
+        }
+    }
+
+    /// What the scheduler calls a progression, e.g. "progression:ii-V-I"
+    fn scheduler_item(progression: &Progression) -> String {
+        format!("progression:{}", progression)
+    }
+
+    /// Tells the scheduler, if any, how the first attempt at the current
+    /// progression went
+    fn review(&mut self, correct: bool) {
+        if self.current_reviewed {
+            return;
+        }
+        self.current_reviewed = true;
+        let target = CHORD_ANSWER_TIME * self.current_progression.chords.len() as u32;
+        let grade = Grade::from_answer(correct, self.asked_at.elapsed(), target);
+        if let Some(scheduler) = self.scheduler.as_mut() {
+            scheduler.review(&Self::scheduler_item(&self.current_progression), grade);
+        }
+    }
+
+    fn correct_chord(&mut self, latest: KeyMessage) {
+        self.answered += 1;
+        if self.answered < self.current_progression.chords.len() {
+            return;
+        }
+        self.review(true);
+        self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+        self.ignore_until = latest.timestamp;
+        self.feedback.give(Cue::Correct, Message::PerfectMatch);
+        self.next_test();
+    }
+
+    /// Tells the user what went wrong; they start again from the first chord
+    fn wrong_chord(&mut self, latest: KeyMessage, message: Message) {
+        self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+        self.ignore_until = latest.timestamp;
+        self.answered = 0;
+        self.review(false);
+        self.feedback.give(Cue::Incorrect, message);
+    }
+
+    fn next_test(&mut self) {
+        let mut rng = rand::thread_rng();
+        self.current_key = self
+            .fixed_key
+            .unwrap_or_else(|| Key::major(PitchClass::from_fifths(rng.gen_range(-6..=6))));
+        let scheduled = self.scheduler.as_ref().and_then(|scheduler| {
+            let items: Vec<String> = self.progressions.iter().map(Self::scheduler_item).collect();
+            scheduler.next(&items)
+        });
+        self.current_progression = match scheduled {
+            Some(i) => self.progressions[i].clone(),
+            None => self.progressions.choose(&mut rng).unwrap().clone(),
+        };
+        self.answered = 0;
+        self.current_reviewed = false;
+        info!(
+            "asking for {} in {}",
+            self.current_progression, self.current_key
+        );
+        self.feedback.give(Cue::Prompt, Message::NewProgression);
+
+        self.play_progression();
+        self.asked_at = Instant::now();
+    }
+
+    fn play_progression(&self) {
+        // the chords would be drowned out by speech
+        self.speaker.wait_until_idle();
+        for numeral in &self.current_progression.chords {
+            let mut keys = numeral.voicing(&self.current_key, LOWEST_CHORD_KEY);
+            keys.insert(0, keys[0] - 12);
+            self.player
+                .play_chord(&keys, DEFAULT_VELOCITY, Duration::from_millis(1000));
+        }
+    }
+}
+
+impl PracticeProgram for ChordProgressionPracticeProgram {
+    fn get_state(&self) -> PracticeProgramState {
+        return self.state;
+    }
+
+    fn run(mut self) {
+        info!("starting ChordProgressionPracticeProgram");
+
+        std::thread::spawn(move || {
+            self.start();
+
+            loop {
+                let msg = self.key_receiver.recv().unwrap();
+                self.on_keypress(msg);
+            }
+        });
+    }
+}
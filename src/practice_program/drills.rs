@@ -0,0 +1,28 @@
+//! Built-in drills, defined with the practice_program! macro or embedded
+//! from drills/
+
+use crate::practice_program;
+
+use super::Script;
+
+practice_program! {
+    /// One octave of each major scale up and down, going around the circle of
+    /// fourths. A simpler take on CircleOfFourthsPracticeProgram.
+    pub struct MajorScalesCircleOfFourths "major-scales-circle-of-fourths" {
+        sequence keys, ["C", "F", "Bb", "Eb", "Ab", "C#", "F#", "B", "E", "A", "D", "G"]
+        foreach_verifying key in keys {
+            prompt "play a major scale in the key of ${key}"
+            listen max_notes=20, max_listen_seconds=60
+            verify_major_scale key
+        }
+        inform "you are done"
+    }
+}
+
+/// One octave of each harmonic minor scale up and down, in a random order.
+/// Kept as a script so drills/minor.txt doubles as an example.
+pub fn harmonic_minor_scales_random_order() -> Script {
+    include_str!("../../drills/minor.txt")
+        .parse()
+        .expect("drills/minor.txt is checked by the script tests")
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use log::warn;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::music::Interval;
+
+use super::IntervalPlaybackMode;
+
+/// Interval sizes in semitones, from easiest to hardest to hear. Questions
+/// start with the first few and the rest unlock one at a time.
+const INTERVAL_DIFFICULTY: [u8; 13] = [12, 7, 4, 0, 5, 3, 9, 2, 8, 10, 1, 11, 6];
+const FIRST_UNLOCKED: usize = 4;
+
+/// Every unlocked interval needs this many answers at UNLOCK_ACCURACY before
+/// the next one unlocks
+const UNLOCK_ANSWERS: u32 = 5;
+const UNLOCK_ACCURACY: f64 = 0.8;
+
+/// Even a mastered skill still comes up now and then
+const WEIGHT_FLOOR: f64 = 0.1;
+
+/// Something ear training keeps score of
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Skill {
+    /// Ascending and descending intervals are scored separately
+    Interval(Interval),
+    /// The octave the lower note is in, 4 from middle C up to B4
+    Register(i8),
+    PlaybackMode(IntervalPlaybackMode),
+}
+
+impl fmt::Display for Skill {
+    /// Tab separated, as stored in the stats file, e.g. "interval\t-m3"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Skill::Interval(interval) => write!(f, "interval\t{}", interval.abbreviation()),
+            Skill::Register(octave) => write!(f, "register\t{}", octave),
+            Skill::PlaybackMode(IntervalPlaybackMode::Open) => write!(f, "playback\topen"),
+            Skill::PlaybackMode(IntervalPlaybackMode::Closed) => write!(f, "playback\tclosed"),
+        }
+    }
+}
+
+impl FromStr for Skill {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Skill, String> {
+        let err = || format!("unknown skill \"{}\"", s);
+        match s.split_once('\t').ok_or_else(err)? {
+            ("interval", interval) => Ok(Skill::Interval(interval.parse()?)),
+            ("register", octave) => Ok(Skill::Register(octave.parse().map_err(|_| err())?)),
+            ("playback", "open") => Ok(Skill::PlaybackMode(IntervalPlaybackMode::Open)),
+            ("playback", "closed") => Ok(Skill::PlaybackMode(IntervalPlaybackMode::Closed)),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// First answers to questions involving a skill
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tally {
+    pub right: u32,
+    pub wrong: u32,
+}
+
+impl Tally {
+    pub fn answers(&self) -> u32 {
+        self.right + self.wrong
+    }
+
+    /// Share of right answers, counting one extra right and one extra wrong
+    /// so a skill that was never asked about is at 50%
+    pub fn accuracy(&self) -> f64 {
+        f64::from(self.right + 1) / f64::from(self.answers() + 2)
+    }
+}
+
+/// Accuracy per interval, register and playback mode, used to ask about weak
+/// spots more often. Stored in a file between sessions if given one.
+#[derive(Clone, Debug, Default)]
+pub struct EarTrainingStats {
+    tallies: HashMap<Skill, Tally>,
+    path: Option<PathBuf>,
+}
+
+impl EarTrainingStats {
+    /// Stats that are only kept in memory
+    pub fn new() -> EarTrainingStats {
+        EarTrainingStats::default()
+    }
+
+    /// Reads stats from `path`, starting afresh if it doesn't exist yet.
+    /// save() writes them back there.
+    pub fn load(path: &Path) -> io::Result<EarTrainingStats> {
+        let mut stats = EarTrainingStats {
+            tallies: HashMap::new(),
+            path: Some(path.to_path_buf()),
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(stats),
+            Err(err) => return Err(err),
+        };
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match Self::parse_line(line) {
+                Ok((skill, tally)) => {
+                    stats.tallies.insert(skill, tally);
+                }
+                Err(err) => warn!("skipping line \"{}\" of {}: {}", line, path.display(), err),
+            }
+        }
+        Ok(stats)
+    }
+
+    /// "<kind>\t<skill>\t<right>\t<wrong>"
+    fn parse_line(line: &str) -> Result<(Skill, Tally), String> {
+        let mut fields = line.rsplitn(3, '\t');
+        let wrong = fields.next().and_then(|n| n.parse().ok());
+        let right = fields.next().and_then(|n| n.parse().ok());
+        match (fields.next(), right, wrong) {
+            (Some(skill), Some(right), Some(wrong)) => Ok((skill.parse()?, Tally { right, wrong })),
+            _ => Err("expected a skill followed by right and wrong counts".to_string()),
+        }
+    }
+
+    /// Writes the stats to the file they were loaded from, if any
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut lines: Vec<String> = self
+            .tallies
+            .iter()
+            .map(|(skill, tally)| format!("{}\t{}\t{}\n", skill, tally.right, tally.wrong))
+            .collect();
+        lines.sort();
+        fs::write(path, lines.concat())
+    }
+
+    pub fn tally(&self, skill: Skill) -> Tally {
+        self.tallies.get(&skill).copied().unwrap_or_default()
+    }
+
+    /// Counts a first answer to a question involving all of `skills`
+    pub fn record(&mut self, skills: &[Skill], right: bool) {
+        for skill in skills {
+            let tally = self.tallies.entry(*skill).or_default();
+            if right {
+                tally.right += 1;
+            } else {
+                tally.wrong += 1;
+            }
+        }
+    }
+
+    /// Interval sizes in semitones that can be asked about, easiest first.
+    /// The next one unlocks once all of these are answered well enough, up
+    /// and down together.
+    pub fn unlocked_intervals(&self) -> &'static [u8] {
+        let mastered = |semitones: &u8| {
+            let semitones = i16::from(*semitones);
+            // a unison is the same going up or down, so it's only counted once
+            let mut directions = vec![semitones, -semitones];
+            directions.dedup();
+            let tally = directions
+                .iter()
+                .filter_map(|n| Interval::from_semitones(*n))
+                .map(|interval| self.tally(Skill::Interval(interval)))
+                .fold(Tally::default(), |sum, tally| Tally {
+                    right: sum.right + tally.right,
+                    wrong: sum.wrong + tally.wrong,
+                });
+            tally.answers() >= UNLOCK_ANSWERS && tally.accuracy() >= UNLOCK_ACCURACY
+        };
+        let mut unlocked = FIRST_UNLOCKED;
+        while unlocked < INTERVAL_DIFFICULTY.len()
+            && INTERVAL_DIFFICULTY[..unlocked].iter().all(mastered)
+        {
+            unlocked += 1;
+        }
+        &INTERVAL_DIFFICULTY[..unlocked]
+    }
+
+    /// One of `options`, favouring those whose skill has the lowest accuracy
+    pub fn pick<T: Copy>(
+        &self,
+        options: &[T],
+        skill: impl Fn(T) -> Skill,
+        rng: &mut impl Rng,
+    ) -> T {
+        *options
+            .choose_weighted(rng, |option| {
+                (1.0 - self.tally(skill(*option)).accuracy()).max(WEIGHT_FLOOR)
+            })
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(stats: &mut EarTrainingStats, semitones: i16, times: usize) {
+        let skill = Skill::Interval(Interval::from_semitones(semitones).unwrap());
+        for _ in 0..times {
+            stats.record(&[skill], true);
+        }
+    }
+
+    #[test]
+    fn unlocks_the_next_interval_once_the_unlocked_ones_are_mastered() {
+        let mut stats = EarTrainingStats::new();
+        assert_eq!(stats.unlocked_intervals(), [12, 7, 4, 0]);
+        answer(&mut stats, 12, 5);
+        // ascending and descending answers count together
+        answer(&mut stats, 7, 3);
+        answer(&mut stats, -7, 2);
+        answer(&mut stats, -4, 5);
+        // the unison's answers aren't counted twice
+        answer(&mut stats, 0, 3);
+        assert_eq!(stats.unlocked_intervals().len(), FIRST_UNLOCKED);
+        answer(&mut stats, 0, 2);
+        assert_eq!(stats.unlocked_intervals(), [12, 7, 4, 0, 5]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fmt, str::FromStr, sync::Arc, time::Duration};
+
+use crate::{
+    key_handler::KeyDb,
+    midi::DEFAULT_VELOCITY,
+    music::Pitch,
+    speech::{Message, Speaker},
+};
+
+use super::NotePlayer;
+
+// how long each step of an earcon lasts unless it says otherwise
+const DEFAULT_STEP_MILLIS: u64 = 150;
+
+/// What a bit of feedback is telling the player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cue {
+    /// Get ready for the next question
+    Prompt,
+    Correct,
+    Incorrect,
+}
+
+/// One step of an earcon: keys played together, then released
+#[derive(Clone, Debug, PartialEq)]
+pub struct EarconStep {
+    pub keys: Vec<u8>,
+    pub duration: Duration,
+}
+
+/// A short phrase played on the instrument in place of speech
+#[derive(Clone, Debug, PartialEq)]
+pub struct Earcon {
+    pub steps: Vec<EarconStep>,
+}
+
+impl FromStr for Earcon {
+    type Err = String;
+
+    /// Steps separated by spaces, each a pitch or pitches joined by "+" to
+    /// play them together, optionally followed by ":" and a length in
+    /// milliseconds, e.g. "C5 E5" or "C2+Db2:300"
+    fn from_str(s: &str) -> Result<Earcon, String> {
+        let steps = s
+            .split_whitespace()
+            .map(|step| {
+                let (pitches, millis) = match step.split_once(':') {
+                    Some((pitches, millis)) => (
+                        pitches,
+                        millis.parse().map_err(|_| {
+                            format!("bad length \"{}\" in earcon \"{}\"", millis, s)
+                        })?,
+                    ),
+                    None => (step, DEFAULT_STEP_MILLIS),
+                };
+                let keys = pitches
+                    .split('+')
+                    .map(|pitch| {
+                        pitch
+                            .parse::<Pitch>()
+                            .ok()
+                            .and_then(|pitch| pitch.midi())
+                            .ok_or_else(|| format!("bad pitch \"{}\" in earcon \"{}\"", pitch, s))
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                Ok(EarconStep {
+                    keys,
+                    duration: Duration::from_millis(millis),
+                })
+            })
+            .collect::<Result<Vec<EarconStep>, String>>()?;
+        if steps.is_empty() {
+            return Err("an earcon needs at least one note".to_string());
+        }
+        Ok(Earcon { steps })
+    }
+}
+
+impl fmt::Display for Earcon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| {
+                let pitches: Vec<String> = step
+                    .keys
+                    .iter()
+                    .map(|key| Pitch::from_midi(*key).to_string())
+                    .collect();
+                format!("{}:{}", pitches.join("+"), step.duration.as_millis())
+            })
+            .collect();
+        write!(f, "{}", steps.join(" "))
+    }
+}
+
+/// The earcon for each cue
+#[derive(Clone, Debug, PartialEq)]
+pub struct Earcons {
+    pub prompt: Earcon,
+    pub correct: Earcon,
+    pub incorrect: Earcon,
+}
+
+impl Earcons {
+    pub fn new(prompt: Earcon, correct: Earcon, incorrect: Earcon) -> Earcons {
+        Earcons {
+            prompt,
+            correct,
+            incorrect,
+        }
+    }
+
+    pub fn get(&self, cue: Cue) -> &Earcon {
+        match cue {
+            Cue::Prompt => &self.prompt,
+            Cue::Correct => &self.correct,
+            Cue::Incorrect => &self.incorrect,
+        }
+    }
+}
+
+impl Default for Earcons {
+    /// A high tap to get ready, a rising major third for correct and a low
+    /// minor second for wrong
+    fn default() -> Earcons {
+        Earcons::new(
+            "A5:80".parse().unwrap(),
+            "C5 E5:250".parse().unwrap(),
+            "C2+Db2:400".parse().unwrap(),
+        )
+    }
+}
+
+/// Gives feedback by speaking, or with earcons on the instrument if the
+/// program was given some
+#[derive(Clone)]
+pub struct Feedback {
+    speaker: Speaker,
+    player: NotePlayer,
+    key_db: Arc<KeyDb>,
+    earcons: Option<Earcons>,
+    // key messages up to this time came in before the last earcon was over
+    earcon_until: u64,
+}
+
+impl Feedback {
+    pub fn new(
+        speaker: Speaker,
+        player: NotePlayer,
+        key_db: Arc<KeyDb>,
+        earcons: Option<Earcons>,
+    ) -> Feedback {
+        Feedback {
+            speaker,
+            player,
+            key_db,
+            earcons,
+            earcon_until: 0,
+        }
+    }
+
+    /// Says `message` in the speaker's locale, or plays the earcon for `cue`
+    /// instead. Earcons are played before returning.
+    pub fn give(&mut self, cue: Cue, message: Message) {
+        match &self.earcons {
+            Some(earcons) => {
+                // they'd be drowned out by speech
+                self.speaker.wait_until_idle();
+                for step in &earcons.get(cue).steps {
+                    self.player
+                        .play_chord(&step.keys, DEFAULT_VELOCITY, step.duration);
+                }
+                // the program couldn't react while playing, so whatever was
+                // played meanwhile is still waiting to be handled
+                let latest = self.key_db.last_n_messages_reverse_chron(None, 1);
+                if let Some(latest) = latest.first() {
+                    self.earcon_until = self.earcon_until.max(latest.timestamp);
+                }
+            }
+            None => {
+                let text = self.speaker.locale().message(&message);
+                match cue {
+                    Cue::Prompt => self.speaker.prompt(text),
+                    Cue::Correct | Cue::Incorrect => self.speaker.feedback(text),
+                }
+            }
+        }
+    }
+
+    /// Whether a key message at `timestamp` came in while an earcon was
+    /// playing, like Speaker::is_speaking for spoken feedback. Such keys
+    /// aren't meant as answers.
+    pub fn was_cueing_at(&self, timestamp: u64) -> bool {
+        timestamp <= self.earcon_until
+    }
+}
@@ -0,0 +1,375 @@
+/// Defines a practice program at compile time using the same statements as
+/// scripts (see practice_program::script). The result is a struct with
+/// `new(ctrl_sender, key_receiver, speaker)` that implements PracticeProgram,
+/// and `script()` for the statements it runs.
+///
+/// ```
+/// use midi_hack::practice_program;
+///
+/// practice_program! {
+///     /// Major scales going around the circle of fourths
+///     pub struct MajorScales "major-scales" {
+///         sequence keys, ["C", "F", "Bb"]
+///         foreach_verifying key in keys {
+///             prompt if_first="play a major scale in the key of ${key}"
+///             listen max_notes=20, max_listen_seconds=60
+///             verify_major_scale key
+///         }
+///         verify_dorian_scale "D" octaves=2, direction=ascending
+///         inform "you are done"
+///     }
+/// }
+///
+/// assert_eq!(MajorScales::script().statements.len(), 4);
+/// ```
+///
+/// Unknown verifiers, options and note names are compile errors, as are
+/// sequences and variables that aren't declared in an enclosing block:
+///
+/// ```compile_fail
+/// # use midi_hack::practice_program;
+/// practice_program! {
+///     pub struct Drill "drill" {
+///         verify_major_scale "H"
+///     }
+/// }
+/// # fn main() { Drill::script(); }
+/// ```
+///
+/// ```compile_fail
+/// # use midi_hack::practice_program;
+/// practice_program! {
+///     pub struct Drill "drill" {
+///         verify_bebop_scale "C"
+///     }
+/// }
+/// # fn main() { Drill::script(); }
+/// ```
+///
+/// ```compile_fail
+/// # use midi_hack::practice_program;
+/// practice_program! {
+///     pub struct Drill "drill" {
+///         verify_major_scale "C" octaves=5
+///     }
+/// }
+/// # fn main() { Drill::script(); }
+/// ```
+///
+/// ```compile_fail
+/// # use midi_hack::practice_program;
+/// practice_program! {
+///     pub struct Drill "drill" {
+///         foreach_verifying key in keys {
+///             verify_major_scale key
+///         }
+///     }
+/// }
+/// # fn main() { Drill::script(); }
+/// ```
+///
+/// ```compile_fail
+/// # use midi_hack::practice_program;
+/// practice_program! {
+///     pub struct Drill "drill" {
+///         sequence keys, ["C", "F"]
+///         foreach_verifying key in keys {
+///             verify_major_scale note
+///         }
+///     }
+/// }
+/// # fn main() { Drill::script(); }
+/// ```
+#[macro_export]
+macro_rules! practice_program {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $program:ident $name:literal { $($body:tt)* }
+    ) => {
+        $(#[$meta])*
+        $vis struct $program {
+            inner: $crate::practice_program::ScriptedPracticeProgram,
+        }
+
+        impl $program {
+            $vis fn new(
+                ctrl_sender: ::std::sync::mpsc::SyncSender<$crate::key_handler::ControlMessage>,
+                key_receiver: ::std::sync::mpsc::Receiver<$crate::midi::KeyMessage>,
+                speaker: $crate::speech::Speaker,
+            ) -> $program {
+                $program {
+                    inner: $crate::practice_program::ScriptedPracticeProgram::new(
+                        ctrl_sender,
+                        key_receiver,
+                        speaker,
+                        Self::script(),
+                    ),
+                }
+            }
+
+            $vis fn script() -> $crate::practice_program::Script {
+                $crate::practice_program::Script {
+                    name: $name.to_string(),
+                    statements: $crate::practice_program!(@statements [] [] [] $($body)*),
+                }
+            }
+        }
+
+        impl $crate::practice_program::PracticeProgram for $program {
+            fn get_state(&self) -> $crate::practice_program::PracticeProgramState {
+                self.inner.get_state()
+            }
+
+            fn run(self) {
+                self.inner.run()
+            }
+        }
+    };
+
+    // statements are munched one at a time into a list of expressions,
+    // along with the sequences and variables in scope
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]) => {
+        vec![$($done),*]
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        sequence $name:ident, randomize([$($item:literal),* $(,)?]) $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Sequence {
+                name: stringify!($name).to_string(),
+                items: vec![$($item.to_string()),*],
+                randomize: true,
+            }
+        ] [$($sequences)* $name] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        sequence $name:ident, [$($item:literal),* $(,)?] $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Sequence {
+                name: stringify!($name).to_string(),
+                items: vec![$($item.to_string()),*],
+                randomize: false,
+            }
+        ] [$($sequences)* $name] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        foreach_verifying $variable:ident in $sequence:ident { $($body:tt)* } $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::ForeachVerifying {
+                variable: stringify!($variable).to_string(),
+                sequence: {
+                    const {
+                        assert!(
+                            $crate::practice_program::script::declares(
+                                &[$(stringify!($sequences)),*],
+                                stringify!($sequence),
+                            ),
+                            concat!("no sequence named ", stringify!($sequence))
+                        );
+                    }
+                    stringify!($sequence).to_string()
+                },
+                body: $crate::practice_program!(@statements []
+                    [$($sequences)*] [$($variables)* $variable] $($body)*),
+            }
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        prompt if_first = $text:literal $($rest:tt)*) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Prompt {
+                text: $text.to_string(),
+                if_first: true,
+            }
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        prompt $text:literal $($rest:tt)*) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Prompt {
+                text: $text.to_string(),
+                if_first: false,
+            }
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        listen $($rest:tt)*) => {
+        $crate::practice_program!(@listen [$($done),*] [$($sequences)*] [$($variables)*] []
+            $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        inform $text:literal $($rest:tt)*) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Inform {
+                text: $text.to_string(),
+            }
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        $verifier:ident $root:ident $($rest:tt)*) => {
+        $crate::practice_program!(@verify [$($done),*] [$($sequences)*] [$($variables)*] $verifier
+            [$crate::practice_program::script::Argument::Variable({
+                const {
+                    assert!(
+                        $crate::practice_program::script::declares(
+                            &[$(stringify!($variables)),*],
+                            stringify!($root),
+                        ),
+                        concat!("no variable named ", stringify!($root))
+                    );
+                }
+                stringify!($root).to_string()
+            })]
+            [] $($rest)*)
+    };
+    (@statements [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        $verifier:ident $root:literal $($rest:tt)*) => {
+        $crate::practice_program!(@verify [$($done),*] [$($sequences)*] [$($variables)*] $verifier
+            [$crate::practice_program::script::Argument::PitchClass(const {
+                match $crate::music::PitchClass::parse($root) {
+                    Some(root) => root,
+                    None => panic!(concat!("\"", $root, "\" isn't a note")),
+                }
+            })]
+            [] $($rest)*)
+    };
+
+    // listen options are munched separately, since a comma after one could
+    // also be the start of the next statement as far as macro_rules knows
+    (@listen [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        [$($options:tt)*] $option:ident = $value:literal, $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@listen [$($done),*] [$($sequences)*] [$($variables)*]
+            [$($options)* $option $value] $($rest)*)
+    };
+    (@listen [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        [$($option:ident $value:literal)*] $last:ident = $last_value:literal $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Listen({
+                let mut listen = $crate::practice_program::script::Listen::default();
+                $(listen.$option = Some($value);)*
+                listen.$last = Some($last_value);
+                listen
+            })
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+
+    // verify options, munched the same way
+    (@verify [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        $verifier:ident [$root:expr] [$($options:tt)*] $option:ident = $value:tt, $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@verify [$($done),*] [$($sequences)*] [$($variables)*]
+            $verifier [$root] [$($options)* $option $value] $($rest)*)
+    };
+    (@verify [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        $verifier:ident [$root:expr] [$($options:tt)*] $option:ident = $value:tt $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@verify [$($done),*] [$($sequences)*] [$($variables)*]
+            $verifier [$root] [$($options)* $option $value] $($rest)*)
+    };
+    (@verify [$($done:expr),*] [$($sequences:ident)*] [$($variables:ident)*]
+        $verifier:ident [$root:expr] [$($option:ident $value:tt)*] $($rest:tt)*
+    ) => {
+        $crate::practice_program!(@statements [$($done,)*
+            $crate::practice_program::script::Statement::Verify {
+                verifier: {
+                    #[allow(unused_mut)]
+                    let mut verifier = $crate::practice_program!(@verifier $verifier);
+                    $($crate::practice_program!(@verify_option verifier $option $value);)*
+                    verifier
+                },
+                root: $root,
+            }
+        ] [$($sequences)*] [$($variables)*] $($rest)*)
+    };
+
+    // verifiers are looked up in the scale catalog as the script parser
+    // does, but at compile time
+    (@verifier $verifier:ident) => {
+        const {
+            match $crate::practice_program::script::Verifier::by_keyword(stringify!($verifier)) {
+                Some(verifier) => verifier,
+                None => panic!(concat!("unknown verifier ", stringify!($verifier))),
+            }
+        }
+    };
+
+    (@verify_option $verifier:ident octaves $octaves:literal) => {
+        let $crate::practice_program::script::Verifier::Scale { octaves, .. } = &mut $verifier;
+        *octaves = const {
+            assert!(
+                $octaves >= 1 && $octaves <= $crate::practice_program::script::MAX_OCTAVES,
+                "octaves must be between 1 and 4"
+            );
+            $octaves
+        };
+    };
+    (@verify_option $verifier:ident direction $direction:ident) => {
+        let $crate::practice_program::script::Verifier::Scale { direction, .. } = &mut $verifier;
+        *direction = const {
+            match $crate::music::Direction::parse(stringify!($direction)) {
+                Some(direction) => direction,
+                None => panic!(concat!("unknown direction ", stringify!($direction))),
+            }
+        };
+    };
+    (@verify_option $verifier:ident $option:ident $value:tt) => {
+        compile_error!(concat!("unknown verify option ", stringify!($option)))
+    };
+}
+
+#[cfg(test)]
+// the drill is only expanded, never run, so its new() is unused
+#[allow(dead_code)]
+mod tests {
+    use crate::practice_program::Script;
+
+    practice_program! {
+        struct Everything "everything" {
+            sequence roots, ["C", "F#"]
+            sequence shuffled, randomize(["Bb", "Eb",])
+            foreach_verifying root in roots {
+                prompt "play ${root}"
+                prompt if_first="only once"
+                listen max_notes=30
+                verify_harmonic_minor_scale root octaves=2
+                foreach_verifying other in shuffled {
+                    listen max_listen_seconds=5, max_notes=10
+                    verify_dorian_scale other direction=down_and_up, octaves=3
+                }
+            }
+            verify_aeolian_scale "Ab"
+            verify_major_scale "Bb" direction=descending
+            inform "you are done"
+        }
+    }
+
+    const SCRIPT: &str = r#"
+        practice_program "everything" {
+            sequence roots, ["C", "F#"]
+            sequence shuffled, randomize(["Bb", "Eb",])
+            foreach_verifying root in roots {
+                prompt "play ${root}"
+                prompt if_first="only once"
+                listen max_notes=30
+                verify_harmonic_minor_scale root octaves=2
+                foreach_verifying other in shuffled {
+                    listen max_listen_seconds=5, max_notes=10
+                    verify_dorian_scale other direction=down_and_up, octaves=3
+                }
+            }
+            verify_aeolian_scale "Ab"
+            verify_major_scale "Bb" direction=descending
+            inform "you are done"
+        }
+    "#;
+
+    #[test]
+    fn expands_to_what_the_script_parser_reads() {
+        let parsed: Script = SCRIPT.parse().unwrap();
+        assert_eq!(Everything::script(), parsed);
+    }
+}
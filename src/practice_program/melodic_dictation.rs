@@ -0,0 +1,386 @@
+use std::{
+    ops::RangeInclusive,
+    sync::mpsc::{Receiver, SyncSender},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::info;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    key_handler::{ControlMessage, KeyDb},
+    midi::{KeyMessage, MidiMessageTypes, DEFAULT_VELOCITY},
+    music::{Key, Pitch, PitchClass},
+    speech::{Message, Speaker},
+};
+
+use super::{Cue, Earcons, Feedback, NotePlayer, PracticeProgram, PracticeProgramState, SOS_KEY};
+
+/// Length of a beat when melodies are played
+const BEAT_MILLIS: u64 = 500;
+
+/// Note lengths in beats when rhythm is on, weighted towards plain beats
+const RHYTHM_BEATS: [f32; 6] = [0.5, 0.5, 1.0, 1.0, 1.5, 2.0];
+
+/// Scale steps from one note to the next, weighted towards small steps
+const MELODY_STEPS: [i32; 14] = [-4, -3, -2, -2, -1, -1, -1, 1, 1, 1, 2, 2, 3, 4];
+
+/// How many notes a melody may have
+pub const MELODY_NOTES: RangeInclusive<usize> = 3..=8;
+
+/// What melodies to ask for
+#[derive(Clone, Copy, Debug)]
+pub struct MelodySettings {
+    /// A random major key for each melody if None
+    pub key: Option<Key>,
+    /// Lowest and highest keys a melody may use, at least an octave apart
+    pub lowest: u8,
+    pub highest: u8,
+    /// Both within MELODY_NOTES
+    pub min_notes: usize,
+    pub max_notes: usize,
+    /// Vary the note lengths instead of playing every note for a beat
+    pub rhythm: bool,
+}
+
+impl Default for MelodySettings {
+    /// Three to eight notes from G3 to G5 in any major key, without rhythm
+    fn default() -> MelodySettings {
+        MelodySettings {
+            key: None,
+            lowest: 55,
+            highest: 79,
+            min_notes: 3,
+            max_notes: 8,
+            rhythm: false,
+        }
+    }
+}
+
+impl MelodySettings {
+    /// Explains what's wrong with the settings, if anything
+    pub fn validate(&self) -> Result<(), String> {
+        if self.highest > 127 {
+            return Err(format!(
+                "the highest melody key {} isn't a MIDI key",
+                self.highest
+            ));
+        }
+        if u16::from(self.highest) < u16::from(self.lowest) + 11 {
+            return Err(format!(
+                "melodies need a range of at least an octave, not {} to {}",
+                Pitch::from_midi(self.lowest),
+                Pitch::from_midi(self.highest)
+            ));
+        }
+        for notes in [self.min_notes, self.max_notes] {
+            if !MELODY_NOTES.contains(&notes) {
+                return Err(format!(
+                    "melodies have {} to {} notes, not {}",
+                    MELODY_NOTES.start(),
+                    MELODY_NOTES.end(),
+                    notes
+                ));
+            }
+        }
+        if self.max_notes < self.min_notes {
+            return Err(format!(
+                "the most notes in a melody ({}) can't be fewer than the fewest ({})",
+                self.max_notes, self.min_notes
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Keys played one after the other, each held for its duration
+#[derive(Clone, Debug, PartialEq)]
+pub struct Melody {
+    pub key: Key,
+    pub notes: Vec<(u8, Duration)>,
+}
+
+impl Melody {
+    /// A random melody in the key of `settings` that starts on the tonic and
+    /// moves mostly by step. `settings` have to be valid.
+    pub fn random(settings: &MelodySettings) -> Melody {
+        let mut rng = rand::thread_rng();
+        let key = settings
+            .key
+            .unwrap_or_else(|| Key::major(PitchClass::from_fifths(rng.gen_range(-6..=6))));
+        let semitones: Vec<u8> = key.scale().iter().map(|pc| pc.semitone()).collect();
+        let diatonic: Vec<u8> = (settings.lowest..=settings.highest)
+            .filter(|k| semitones.contains(&(k % 12)))
+            .collect();
+        let tonics: Vec<usize> = (0..diatonic.len())
+            .filter(|i| diatonic[*i] % 12 == key.tonic.semitone())
+            .collect();
+
+        let length = rng.gen_range(settings.min_notes..=settings.max_notes);
+        let mut index = *tonics.choose(&mut rng).unwrap() as i32;
+        let mut notes = vec![];
+        for i in 0..length {
+            if i > 0 {
+                let step = *MELODY_STEPS.choose(&mut rng).unwrap();
+                // turn around at the edges of the range, or stay put if the
+                // range is too small to step either way
+                let in_range = |index: i32| (0..diatonic.len() as i32).contains(&index);
+                if in_range(index + step) {
+                    index += step;
+                } else if in_range(index - step) {
+                    index -= step;
+                }
+            }
+            let beats = if settings.rhythm && i < length - 1 {
+                *RHYTHM_BEATS.choose(&mut rng).unwrap()
+            } else if i < length - 1 {
+                1.0
+            } else {
+                // the last note rings a little longer
+                2.0
+            };
+            notes.push((
+                diatonic[index as usize],
+                Duration::from_millis((beats * BEAT_MILLIS as f32) as u64),
+            ));
+        }
+        Melody { key, notes }
+    }
+
+    pub fn keys(&self) -> Vec<u8> {
+        self.notes.iter().map(|(key, _)| *key).collect()
+    }
+}
+
+/// Plays a short melody and waits for it to be played back, pointing out
+/// the first wrong note. Only the notes are judged, not the rhythm.
+pub struct MelodicDictationPracticeProgram {
+    state: PracticeProgramState,
+    ctrl_sender: SyncSender<ControlMessage>,
+    player: NotePlayer,
+    key_receiver: Receiver<KeyMessage>,
+    key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    feedback: Feedback,
+    // key downs until this time were played over speech, or already judged,
+    // and aren't answers
+    ignore_until: u64,
+    settings: MelodySettings,
+    current_melody: Melody,
+}
+
+impl MelodicDictationPracticeProgram {
+    pub fn new(
+        ctrl_sender: SyncSender<ControlMessage>,
+        player: NotePlayer,
+        key_receiver: Receiver<KeyMessage>,
+        key_db: Arc<KeyDb>,
+        speaker: Speaker,
+        earcons: Option<Earcons>,
+        settings: MelodySettings,
+    ) -> MelodicDictationPracticeProgram {
+        if let Err(err) = settings.validate() {
+            panic!("{}", err);
+        }
+
+        MelodicDictationPracticeProgram {
+            state: PracticeProgramState::INITIALIZING,
+            ctrl_sender,
+            feedback: Feedback::new(speaker.clone(), player.clone(), key_db.clone(), earcons),
+            player,
+            key_receiver,
+            key_db,
+            speaker,
+            ignore_until: 0,
+            current_melody: Melody::random(&settings),
+            settings,
+        }
+    }
+
+    pub fn current_melody(&self) -> &Melody {
+        &self.current_melody
+    }
+
+    /// Plays the first melody
+    pub fn start(&mut self) {
+        self.state = PracticeProgramState::LISTENING;
+        self.speaker
+            .prompt(self.speaker.locale().message(&Message::StartingEarTraining));
+        self.next_test();
+    }
+
+    /// Reacts to a key message that's already in the key db. run() calls
+    /// this for every message; tests can call it directly.
+    pub fn on_keypress(&mut self, latest: KeyMessage) {
+        if self.state == PracticeProgramState::FINISHED {
+            return;
+        }
+        if self.speaker.is_speaking() || self.feedback.was_cueing_at(latest.timestamp) {
+            self.ignore_until = latest.timestamp;
+            return;
+        }
+        if latest.message_type != MidiMessageTypes::NoteOn {
+            return;
+        }
+
+        let keys: Vec<u8> = self
+            .key_db
+            .flat_message_log()
+            .iter()
+            .filter(|m| {
+                m.message_type == MidiMessageTypes::NoteOn && m.timestamp > self.ignore_until
+            })
+            .map(|m| m.key)
+            .collect();
+        if keys.ends_with(&[SOS_KEY, SOS_KEY]) {
+            self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+            self.ignore_until = latest.timestamp;
+            self.feedback.give(Cue::Prompt, Message::HeresTheMelody);
+            self.play_melody();
+            return;
+        }
+
+        let answer: Vec<u8> = keys.into_iter().filter(|key| *key != SOS_KEY).collect();
+        let question = self.current_melody.keys();
+        let first_wrong = answer
+            .iter()
+            .zip(question.iter())
+            .position(|(played, expected)| played != expected);
+        match first_wrong {
+            Some(index) => {
+                info!("user played {:?} for {:?}", answer, question);
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                self.ignore_until = latest.timestamp;
+                let message = self.wrong_note(index, answer[index]);
+                self.feedback.give(Cue::Incorrect, message);
+            }
+            None if answer.len() >= question.len() => {
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                self.ignore_until = latest.timestamp;
+                self.feedback.give(Cue::Correct, Message::PerfectMatch);
+                self.next_test();
+            }
+            None => (),
+        }
+    }
+
+    /// Explains playing `played` as the note at `index`
+    fn wrong_note(&self, index: usize, played: u8) -> Message {
+        let key = self.current_melody.key;
+        let expected = self.current_melody.notes[index].0;
+        if played % 12 == expected % 12 {
+            return Message::WrongMelodyOctave {
+                position: index + 1,
+                note: key.spell(expected % 12),
+                octaves: (i16::from(played) - i16::from(expected)) / 12,
+            };
+        }
+        return Message::WrongMelodyNote {
+            position: index + 1,
+            expected: key.spell(expected % 12),
+            played: key.spell(played % 12),
+        };
+    }
+
+    fn next_test(&mut self) {
+        self.current_melody = Melody::random(&self.settings);
+        info!(
+            "asking for {:?} in {}",
+            self.current_melody.keys(),
+            self.current_melody.key
+        );
+        self.feedback.give(Cue::Prompt, Message::NewMelody);
+
+        self.play_melody();
+    }
+
+    fn play_melody(&self) {
+        // the notes would be drowned out by speech
+        self.speaker.wait_until_idle();
+        for (key, duration) in &self.current_melody.notes {
+            self.player.play_note(*key, DEFAULT_VELOCITY, *duration);
+        }
+    }
+}
+
+impl PracticeProgram for MelodicDictationPracticeProgram {
+    fn get_state(&self) -> PracticeProgramState {
+        return self.state;
+    }
+
+    fn run(mut self) {
+        info!("starting MelodicDictationPracticeProgram");
+
+        std::thread::spawn(move || {
+            self.start();
+
+            loop {
+                let msg = self.key_receiver.recv().unwrap();
+                self.on_keypress(msg);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_settings_are_valid() {
+        assert_eq!(MelodySettings::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_settings_that_cant_make_a_melody() {
+        let defaults = MelodySettings::default();
+        for settings in [
+            MelodySettings {
+                highest: 65,
+                ..defaults
+            },
+            MelodySettings {
+                lowest: 80,
+                ..defaults
+            },
+            MelodySettings {
+                highest: 200,
+                ..defaults
+            },
+            MelodySettings {
+                min_notes: 0,
+                ..defaults
+            },
+            MelodySettings {
+                max_notes: 9,
+                ..defaults
+            },
+            MelodySettings {
+                min_notes: 6,
+                max_notes: 4,
+                ..defaults
+            },
+        ] {
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn melodies_stay_within_the_settings() {
+        let settings = MelodySettings {
+            key: Some("D".parse().unwrap()),
+            lowest: 60,
+            highest: 72,
+            min_notes: 4,
+            max_notes: 5,
+            rhythm: true,
+        };
+        for _ in 0..100 {
+            let melody = Melody::random(&settings);
+            assert!((4..=5).contains(&melody.notes.len()));
+            assert_eq!(melody.keys()[0] % 12, 2);
+            assert!(melody.keys().iter().all(|key| (60..=72).contains(key)));
+        }
+    }
+}
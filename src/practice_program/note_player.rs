@@ -0,0 +1,75 @@
+use std::{sync::mpsc::SyncSender, thread, time::Duration};
+
+use crate::midi::{KeyMessage, MidiMessageTypes};
+
+/// Plays notes on the instrument through the MIDI out channel. Clones share
+/// the same channel.
+#[derive(Clone)]
+pub struct NotePlayer {
+    midi_out_sender: SyncSender<KeyMessage>,
+    // whether notes are held for their duration
+    timed: bool,
+}
+
+impl NotePlayer {
+    pub fn new(midi_out_sender: SyncSender<KeyMessage>) -> NotePlayer {
+        NotePlayer {
+            midi_out_sender,
+            timed: true,
+        }
+    }
+
+    /// Lets go of notes right away instead of holding them, for tests where
+    /// nobody's listening
+    pub fn untimed(midi_out_sender: SyncSender<KeyMessage>) -> NotePlayer {
+        NotePlayer {
+            midi_out_sender,
+            timed: false,
+        }
+    }
+
+    pub fn note_on(&self, key: u8, velocity: u8) {
+        let down = KeyMessage {
+            timestamp: 0,
+            message_type: MidiMessageTypes::NoteOn,
+            key,
+            channel: 0,
+            velocity,
+        };
+        // await channel readiness
+        loop {
+            match self.midi_out_sender.try_send(down) {
+                Ok(_) => break,
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+
+    pub fn note_off(&self, key: u8) {
+        let up = KeyMessage {
+            timestamp: 0,
+            message_type: MidiMessageTypes::NoteOff,
+            key,
+            channel: 0,
+            velocity: 0,
+        };
+        self.midi_out_sender.send(up).unwrap();
+    }
+
+    /// Plays `keys` together for `duration`, then lets go of them
+    pub fn play_chord(&self, keys: &[u8], velocity: u8, duration: Duration) {
+        for key in keys {
+            self.note_on(*key, velocity);
+        }
+        if self.timed {
+            thread::sleep(duration);
+        }
+        for key in keys {
+            self.note_off(*key);
+        }
+    }
+
+    pub fn play_note(&self, key: u8, velocity: u8, duration: Duration) {
+        self.play_chord(&[key], velocity, duration)
+    }
+}
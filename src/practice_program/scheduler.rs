@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+/// A failed item comes back this much later in the same session
+const RELEARN_SECONDS: u64 = 60;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const STARTING_EASE: f64 = 2.5;
+const MINIMUM_EASE: f64 = 1.3;
+
+/// How well an item was answered, from the answer and how long it took
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grade {
+    /// Wrong
+    Again,
+    /// Right, but slowly
+    Hard,
+    Good,
+    /// Right and quick
+    Easy,
+}
+
+impl Grade {
+    /// Right answers within `target` are easy, within twice that good and
+    /// anything slower hard
+    pub fn from_answer(correct: bool, elapsed: Duration, target: Duration) -> Grade {
+        if !correct {
+            Grade::Again
+        } else if elapsed <= target {
+            Grade::Easy
+        } else if elapsed <= target * 2 {
+            Grade::Good
+        } else {
+            Grade::Hard
+        }
+    }
+
+    /// SM-2's 0 to 5 response quality
+    fn quality(self) -> f64 {
+        match self {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        }
+    }
+}
+
+/// Review state of one item, as in SM-2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Card {
+    /// Right answers in a row
+    pub repetitions: u32,
+    pub interval_days: f64,
+    pub ease: f64,
+    /// Unix time in seconds the item should be asked again
+    pub due: u64,
+}
+
+impl Default for Card {
+    fn default() -> Card {
+        Card {
+            repetitions: 0,
+            interval_days: 0.0,
+            ease: STARTING_EASE,
+            due: 0,
+        }
+    }
+}
+
+impl Card {
+    fn review(&mut self, grade: Grade, now: u64) {
+        if grade == Grade::Again {
+            self.repetitions = 0;
+            self.interval_days = 0.0;
+            self.due = now + RELEARN_SECONDS;
+        } else {
+            self.interval_days = match self.repetitions {
+                0 => 1.0,
+                1 => 6.0,
+                _ => self.interval_days * self.ease,
+            };
+            self.repetitions += 1;
+            self.due = now + (self.interval_days * SECONDS_PER_DAY) as u64;
+        }
+        let missing = 5.0 - grade.quality();
+        self.ease = (self.ease + 0.1 - missing * (0.08 + missing * 0.02)).max(MINIMUM_EASE);
+    }
+}
+
+/// Picks which item a drill asks about next, like flashcards: items answered
+/// well come back after longer and longer breaks, and failed ones come back
+/// soon. Items are named by the drills, e.g. "major-scale:Bb", so one
+/// scheduler file can hold all of them.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    cards: HashMap<String, Card>,
+    path: Option<PathBuf>,
+}
+
+impl Scheduler {
+    /// A scheduler that's only kept in memory
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Reads cards from `path`, starting afresh if it doesn't exist yet.
+    /// Every review is written back there.
+    pub fn load(path: &Path) -> io::Result<Scheduler> {
+        let mut scheduler = Scheduler {
+            cards: HashMap::new(),
+            path: Some(path.to_path_buf()),
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(scheduler),
+            Err(err) => return Err(err),
+        };
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match Self::parse_line(line) {
+                Some((item, card)) => {
+                    scheduler.cards.insert(item, card);
+                }
+                None => warn!("skipping line \"{}\" of {}", line, path.display()),
+            }
+        }
+        Ok(scheduler)
+    }
+
+    /// "<item>\t<repetitions>\t<interval days>\t<ease>\t<due>"
+    fn parse_line(line: &str) -> Option<(String, Card)> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields[..] {
+            [item, repetitions, interval_days, ease, due] => Some((
+                item.to_string(),
+                Card {
+                    repetitions: repetitions.parse().ok()?,
+                    interval_days: interval_days.parse().ok()?,
+                    ease: ease.parse().ok()?,
+                    due: due.parse().ok()?,
+                },
+            )),
+            _ => None,
+        }
+    }
+
+    /// Writes the cards to the file they were loaded from, if any
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(());
+        };
+        let mut lines: Vec<String> = self
+            .cards
+            .iter()
+            .map(|(item, card)| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\n",
+                    item, card.repetitions, card.interval_days, card.ease, card.due
+                )
+            })
+            .collect();
+        lines.sort();
+        fs::write(path, lines.concat())
+    }
+
+    /// The card for `item`; items that were never reviewed get a new one
+    pub fn card(&self, item: &str) -> Card {
+        self.cards.get(item).copied().unwrap_or_default()
+    }
+
+    /// Index of the item in `items` that's most overdue, counting items
+    /// failed a moment ago as due. Items never reviewed come after those,
+    /// in the order given. None if nothing is due.
+    pub fn next_due(&self, items: &[String]) -> Option<usize> {
+        let soon = now() + RELEARN_SECONDS;
+        let reviewed = (0..items.len())
+            .filter(|i| self.cards.contains_key(&items[*i]))
+            .filter(|i| self.card(&items[*i]).due <= soon)
+            .min_by_key(|i| self.card(&items[*i]).due);
+        reviewed.or_else(|| (0..items.len()).find(|i| !self.cards.contains_key(&items[*i])))
+    }
+
+    /// Like next_due, but if nothing is due picks the item due soonest, for
+    /// drills that go on for as long as they're played. None only if there
+    /// are no items.
+    pub fn next(&self, items: &[String]) -> Option<usize> {
+        self.next_due(items)
+            .or_else(|| (0..items.len()).min_by_key(|i| self.card(&items[*i]).due))
+    }
+
+    /// Reschedules `item` after it was answered with `grade`, and saves
+    pub fn review(&mut self, item: &str, grade: Grade) {
+        let card = self.cards.entry(item.to_string()).or_default();
+        card.review(grade, now());
+        info!(
+            "{} was {:?}, next due in {:.1} days",
+            item, grade, card.interval_days
+        );
+        if let Err(err) = self.save() {
+            warn!("couldn't save the schedule: {}", err);
+        }
+    }
+}
+
+/// Unix time in seconds
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn days(n: f64) -> u64 {
+        NOW + (n * SECONDS_PER_DAY) as u64
+    }
+
+    fn items(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn intervals_grow_by_the_ease() {
+        let mut card = Card::default();
+        card.review(Grade::Good, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (1, 1.0));
+        assert_eq!(card.due, days(1.0));
+        card.review(Grade::Good, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (2, 6.0));
+        card.review(Grade::Good, NOW);
+        assert_eq!((card.repetitions, card.interval_days), (3, 15.0));
+        assert_eq!(card.due, days(15.0));
+        assert_eq!(card.ease, STARTING_EASE);
+    }
+
+    #[test]
+    fn ease_follows_the_grade() {
+        let ease_after = |grade| {
+            let mut card = Card::default();
+            card.review(grade, NOW);
+            card.ease
+        };
+        assert!((ease_after(Grade::Easy) - 2.6).abs() < 1e-9);
+        assert!((ease_after(Grade::Good) - 2.5).abs() < 1e-9);
+        assert!((ease_after(Grade::Hard) - 2.36).abs() < 1e-9);
+        assert!((ease_after(Grade::Again) - 1.96).abs() < 1e-9);
+
+        let mut card = Card::default();
+        for _ in 0..5 {
+            card.review(Grade::Again, NOW);
+        }
+        assert_eq!(card.ease, MINIMUM_EASE);
+    }
+
+    #[test]
+    fn again_starts_the_item_over() {
+        let mut card = Card::default();
+        for _ in 0..3 {
+            card.review(Grade::Easy, NOW);
+        }
+        card.review(Grade::Again, NOW);
+        assert_eq!(card.repetitions, 0);
+        assert_eq!(card.interval_days, 0.0);
+        assert_eq!(card.due, NOW + RELEARN_SECONDS);
+        card.review(Grade::Good, NOW);
+        assert_eq!(card.interval_days, 1.0);
+    }
+
+    #[test]
+    fn grades_answers_by_time() {
+        let target = Duration::from_secs(2);
+        let grade = |correct, secs| Grade::from_answer(correct, Duration::from_secs(secs), target);
+        assert_eq!(grade(true, 2), Grade::Easy);
+        assert_eq!(grade(true, 4), Grade::Good);
+        assert_eq!(grade(true, 5), Grade::Hard);
+        assert_eq!(grade(false, 1), Grade::Again);
+    }
+
+    #[test]
+    fn failed_items_come_before_new_ones() {
+        let mut scheduler = Scheduler::new();
+        let items = items(&["a", "b", "c"]);
+        assert_eq!(scheduler.next_due(&items), Some(0));
+        scheduler.review("a", Grade::Good);
+        assert_eq!(scheduler.next_due(&items), Some(1));
+        scheduler.review("c", Grade::Again);
+        assert_eq!(scheduler.next_due(&items), Some(2));
+        scheduler.review("b", Grade::Good);
+        scheduler.review("c", Grade::Good);
+        assert_eq!(scheduler.next_due(&items), None);
+        assert_eq!(scheduler.next(&items), Some(0));
+        assert_eq!(scheduler.next(&[]), None);
+    }
+
+    #[test]
+    fn saves_and_loads_cards() {
+        let path = std::env::temp_dir().join(format!("scheduler-test-{}", std::process::id()));
+        let mut scheduler = Scheduler::load(&path).unwrap();
+        scheduler.review("major-scale:Bb", Grade::Hard);
+        scheduler.review("interval:P5", Grade::Again);
+        let loaded = Scheduler::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            loaded.card("major-scale:Bb"),
+            scheduler.card("major-scale:Bb")
+        );
+        assert_eq!(loaded.card("interval:P5"), scheduler.card("interval:P5"));
+        assert_eq!(loaded.card("unknown"), Card::default());
+    }
+}
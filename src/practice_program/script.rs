@@ -0,0 +1,1078 @@
+//! Practice programs loaded from text files, so drills can be written without
+//! touching Rust. A script looks like
+//!
+//! ```text
+//! practice_program "harmonic-minor-scales-random-order" {
+//!     sequence keys, randomize(["C", "F", "Bb"])
+//!     foreach_verifying key in keys {
+//!         prompt if_first="play a harmonic minor scale in the key of ${key}"
+//!         listen max_notes=20, max_listen_seconds=60
+//!         verify_harmonic_minor_scale key
+//!     }
+//!     inform "you are done"
+//! }
+//! ```
+//!
+//! The `practice_program "name" { ... }` wrapper is optional. `#` and `//`
+//! start comments.
+//!
+//! * `sequence NAME, [ITEMS]` declares a list of strings, shuffled each time
+//!   the statement runs if wrapped in `randomize(...)`
+//! * `foreach_verifying VAR in NAME { ... }` runs its body once per item,
+//!   only moving on to the next item once every verification in the body passes
+//! * `prompt "TEXT"` says TEXT on every attempt, `prompt if_first="TEXT"` only
+//!   on the first attempt at an item
+//! * `listen max_notes=N, max_listen_seconds=S` limits how long the following
+//!   verifications in the block wait; both are optional
+//! * `verify_<scale>_scale ROOT` waits for the scale to be played, e.g.
+//!   `verify_major_scale key` or `verify_dorian_scale "D" octaves=2 direction=ascending`.
+//!   A failed attempt is explained and the body starts over.
+//! * `inform "TEXT"` says TEXT
+//!
+//! `${VAR}` in any text is replaced with the variable's value.
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
+    sync::mpsc::{Receiver, RecvTimeoutError, SyncSender},
+    time::{Duration, Instant},
+};
+
+use log::{info, trace};
+use rand::seq::SliceRandom;
+
+use crate::{
+    key_handler::ControlMessage,
+    midi::{KeyMessage, MidiMessageTypes},
+    music::{Direction, PitchClass, Scale, ScaleRequest},
+    speech::{Locale, Message, Priority, Speaker},
+};
+
+use super::{partly_right, PracticeProgram, PracticeProgramState};
+
+/// Most octaves a verification can ask for
+pub const MAX_OCTAVES: u8 = 4;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(err) => write!(f, "can't read script: {}", err),
+            ScriptError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(err: io::Error) -> ScriptError {
+        ScriptError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Script {
+    pub name: String,
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Sequence {
+        name: String,
+        items: Vec<String>,
+        randomize: bool,
+    },
+    ForeachVerifying {
+        variable: String,
+        sequence: String,
+        body: Vec<Statement>,
+    },
+    Prompt {
+        text: String,
+        if_first: bool,
+    },
+    Listen(Listen),
+    Verify {
+        verifier: Verifier,
+        root: Argument,
+    },
+    Inform {
+        text: String,
+    },
+}
+
+/// Limits on how long a verification waits for the student
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Listen {
+    /// Key ups before the attempt is over, otherwise it's over once it's
+    /// complete or has clearly gone wrong
+    pub max_notes: Option<usize>,
+    pub max_listen_seconds: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verifier {
+    Scale {
+        scale: &'static Scale,
+        octaves: u8,
+        direction: Direction,
+    },
+}
+
+impl Verifier {
+    /// Looks up the verifier for a statement keyword such as
+    /// `verify_harmonic_minor_scale`. A const fn so that practice_program!
+    /// can reject unknown verifiers at compile time.
+    pub const fn by_keyword(keyword: &str) -> Option<Verifier> {
+        let Some(scale_name) = strip_affixes(keyword, "verify_", "_scale") else {
+            return None;
+        };
+        match Scale::lookup(scale_name) {
+            Some(scale) => Some(Verifier::Scale {
+                scale,
+                octaves: 1,
+                direction: Direction::UpAndDown,
+            }),
+            None => None,
+        }
+    }
+}
+
+/// Whether `name` is one of `names`. A const fn so that practice_program!
+/// can reject undeclared sequences and variables at compile time.
+pub const fn declares(names: &[&str], name: &str) -> bool {
+    let mut i = 0;
+    while i < names.len() {
+        let (a, b) = (names[i].as_bytes(), name.as_bytes());
+        if a.len() == b.len() {
+            let mut j = 0;
+            while j < a.len() && a[j] == b[j] {
+                j += 1;
+            }
+            if j == a.len() {
+                return true;
+            }
+        }
+        i += 1;
+    }
+    false
+}
+
+/// `s` without `prefix` and `suffix`, or None if it doesn't have both
+const fn strip_affixes<'a>(s: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    let (bytes, prefix, suffix) = (s.as_bytes(), prefix.as_bytes(), suffix.as_bytes());
+    if bytes.len() < prefix.len() + suffix.len() {
+        return None;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if bytes[i] != prefix[i] {
+            return None;
+        }
+        i += 1;
+    }
+    let mut i = 0;
+    while i < suffix.len() {
+        if bytes[bytes.len() - suffix.len() + i] != suffix[i] {
+            return None;
+        }
+        i += 1;
+    }
+    let (_, rest) = bytes.split_at(prefix.len());
+    let (middle, _) = rest.split_at(rest.len() - suffix.len());
+    match std::str::from_utf8(middle) {
+        Ok(middle) => Some(middle),
+        Err(_) => None,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    PitchClass(PitchClass),
+    Variable(String),
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script, ScriptError> {
+        let mut script: Script = fs::read_to_string(path)?.parse()?;
+        if script.name.is_empty() {
+            if let Some(stem) = path.file_stem() {
+                script.name = stem.to_string_lossy().into_owned();
+            }
+        }
+        Ok(script)
+    }
+}
+
+impl FromStr for Script {
+    type Err = ScriptError;
+
+    /// Parses a script, leaving the name empty if it has no
+    /// `practice_program "name" { ... }` wrapper
+    fn from_str(s: &str) -> Result<Script, ScriptError> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        };
+        let mut name = String::new();
+        let statements = if parser.peek_word("practice_program") {
+            parser.next();
+            name = parser.expect_string()?;
+            parser.expect_symbol('{')?;
+            let statements = parser.statements(&[], &HashMap::new())?;
+            parser.expect_symbol('}')?;
+            statements
+        } else {
+            parser.statements(&[], &HashMap::new())?
+        };
+        if let Some(token) = parser.peek() {
+            return Err(parser.error_at(token.line, "unexpected text after the end of the program"));
+        }
+        Ok(Script { name, statements })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Number(u64),
+    Symbol(char),
+}
+
+#[derive(Clone, Debug)]
+struct Lexeme {
+    token: Token,
+    line: usize,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Lexeme>, ScriptError> {
+    let mut lexemes = Vec::new();
+    let mut line = 1;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '#' => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '{' | '}' | '[' | ']' | '(' | ')' | ',' | '=' => Token::Symbol(c),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => break,
+                        },
+                        Some('\n') | None => {
+                            return Err(ScriptError::Syntax {
+                                line,
+                                message: "unterminated string".to_string(),
+                            })
+                        }
+                        Some(c) => text.push(c),
+                    }
+                }
+                Token::Str(text)
+            }
+            c if c.is_ascii_digit() => {
+                let mut digits = c.to_string();
+                while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                    digits.push(digit);
+                }
+                Token::Number(digits.parse().map_err(|_| ScriptError::Syntax {
+                    line,
+                    message: format!("number {} is too big", digits),
+                })?)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+            c => {
+                return Err(ScriptError::Syntax {
+                    line,
+                    message: format!("unexpected character '{}'", c),
+                })
+            }
+        };
+        lexemes.push(Lexeme { token, line });
+    }
+    Ok(lexemes)
+}
+
+struct Parser {
+    tokens: Vec<Lexeme>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Lexeme> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_word(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Lexeme { token: Token::Word(w), .. }) if w == word)
+    }
+
+    fn peek_symbol(&self, symbol: char) -> bool {
+        matches!(self.peek(), Some(Lexeme { token: Token::Symbol(c), .. }) if *c == symbol)
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        let lexeme = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        lexeme
+    }
+
+    fn line(&self) -> usize {
+        match self.peek() {
+            Some(lexeme) => lexeme.line,
+            None => self.tokens.last().map_or(1, |lexeme| lexeme.line),
+        }
+    }
+
+    fn error(&self, message: &str) -> ScriptError {
+        self.error_at(self.line(), message)
+    }
+
+    fn error_at(&self, line: usize, message: &str) -> ScriptError {
+        ScriptError::Syntax {
+            line,
+            message: message.to_string(),
+        }
+    }
+
+    fn expected(&self, what: &str) -> ScriptError {
+        match self.peek() {
+            Some(lexeme) => self.error(&format!("expected {}, found {:?}", what, lexeme.token)),
+            None => self.error(&format!("expected {}, found the end of the file", what)),
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), ScriptError> {
+        if !self.peek_symbol(symbol) {
+            return Err(self.expected(&format!("'{}'", symbol)));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn expect_word(&mut self) -> Result<String, ScriptError> {
+        match self.peek().map(|lexeme| lexeme.token.clone()) {
+            Some(Token::Word(word)) => {
+                self.next();
+                Ok(word)
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    fn expect_string(&mut self) -> Result<String, ScriptError> {
+        match self.peek().map(|lexeme| lexeme.token.clone()) {
+            Some(Token::Str(text)) => {
+                self.next();
+                Ok(text)
+            }
+            _ => Err(self.expected("a quoted string")),
+        }
+    }
+
+    fn expect_number(&mut self) -> Result<u64, ScriptError> {
+        match self.peek().map(|lexeme| lexeme.token.clone()) {
+            Some(Token::Number(number)) => {
+                self.next();
+                Ok(number)
+            }
+            _ => Err(self.expected("a number")),
+        }
+    }
+
+    /// `name=` if it's next, for optional keyword arguments
+    fn keyword_argument(&mut self) -> Option<String> {
+        let is_keyword = matches!(
+            (
+                self.tokens.get(self.position),
+                self.tokens.get(self.position + 1)
+            ),
+            (
+                Some(Lexeme {
+                    token: Token::Word(_),
+                    ..
+                }),
+                Some(Lexeme {
+                    token: Token::Symbol('='),
+                    ..
+                })
+            )
+        );
+        if !is_keyword {
+            return None;
+        }
+        let name = self.expect_word().ok();
+        self.next();
+        name
+    }
+
+    /// Statements up to a closing brace or the end of the file. `variables`
+    /// are the foreach_verifying variables in scope and `sequences` the
+    /// sequences declared in enclosing blocks; ones declared in this block
+    /// are only visible until its end.
+    fn statements(
+        &mut self,
+        variables: &[String],
+        sequences: &HashMap<String, Vec<String>>,
+    ) -> Result<Vec<Statement>, ScriptError> {
+        let mut sequences = sequences.clone();
+        let mut statements = Vec::new();
+        while self.peek().is_some() && !self.peek_symbol('}') {
+            let statement = self.statement(variables, &sequences)?;
+            if let Statement::Sequence { name, items, .. } = &statement {
+                sequences.insert(name.clone(), items.clone());
+            }
+            statements.push(statement);
+        }
+        Ok(statements)
+    }
+
+    fn statement(
+        &mut self,
+        variables: &[String],
+        sequences: &HashMap<String, Vec<String>>,
+    ) -> Result<Statement, ScriptError> {
+        let line = self.line();
+        let keyword = self.expect_word()?;
+        match keyword.as_str() {
+            "sequence" => {
+                let name = self.expect_word()?;
+                if self.peek_symbol(',') {
+                    self.next();
+                }
+                let randomize = self.peek_word("randomize");
+                if randomize {
+                    self.next();
+                    self.expect_symbol('(')?;
+                }
+                let items = self.list()?;
+                if randomize {
+                    self.expect_symbol(')')?;
+                }
+                Ok(Statement::Sequence {
+                    name,
+                    items,
+                    randomize,
+                })
+            }
+            "foreach_verifying" => {
+                let variable = self.expect_word()?;
+                if !self.peek_word("in") {
+                    return Err(self.expected("'in'"));
+                }
+                self.next();
+                let sequence = self.expect_word()?;
+                let Some(items) = sequences.get(&sequence) else {
+                    return Err(self.error_at(line, &format!("no sequence named {}", sequence)));
+                };
+                self.expect_symbol('{')?;
+                let mut in_scope = variables.to_vec();
+                in_scope.push(variable.clone());
+                let body = self.statements(&in_scope, sequences)?;
+                self.expect_symbol('}')?;
+                if verifies_variable(&body, &variable) {
+                    if let Some(item) = items
+                        .iter()
+                        .find(|item| item.parse::<PitchClass>().is_err())
+                    {
+                        return Err(self.error_at(
+                            line,
+                            &format!(
+                                "{} is verified as a note, but \"{}\" isn't one",
+                                variable, item
+                            ),
+                        ));
+                    }
+                }
+                Ok(Statement::ForeachVerifying {
+                    variable,
+                    sequence,
+                    body,
+                })
+            }
+            "prompt" => {
+                let if_first = match self.keyword_argument() {
+                    Some(argument) if argument == "if_first" => true,
+                    Some(argument) => {
+                        return Err(
+                            self.error_at(line, &format!("unknown prompt option {}", argument))
+                        )
+                    }
+                    None => false,
+                };
+                let text = self.expect_string()?;
+                Ok(Statement::Prompt { text, if_first })
+            }
+            "listen" => {
+                let mut listen = Listen::default();
+                while let Some(argument) = self.keyword_argument() {
+                    let value = self.expect_number()?;
+                    match argument.as_str() {
+                        "max_notes" => listen.max_notes = Some(value as usize),
+                        "max_listen_seconds" => listen.max_listen_seconds = Some(value),
+                        _ => {
+                            return Err(
+                                self.error_at(line, &format!("unknown listen option {}", argument))
+                            )
+                        }
+                    }
+                    if self.peek_symbol(',') {
+                        self.next();
+                    }
+                }
+                Ok(Statement::Listen(listen))
+            }
+            "inform" => Ok(Statement::Inform {
+                text: self.expect_string()?,
+            }),
+            verify if verify.starts_with("verify_") => {
+                let Some(mut verifier) = Verifier::by_keyword(verify) else {
+                    return Err(self.error_at(line, &format!("unknown verifier {}", verify)));
+                };
+                let root = match self.peek().map(|lexeme| lexeme.token.clone()) {
+                    Some(Token::Str(text)) => {
+                        self.next();
+                        Argument::PitchClass(text.parse().map_err(|_| {
+                            self.error_at(line, &format!("\"{}\" isn't a note", text))
+                        })?)
+                    }
+                    Some(Token::Word(variable)) if variables.contains(&variable) => {
+                        self.next();
+                        Argument::Variable(variable)
+                    }
+                    Some(Token::Word(variable)) => {
+                        return Err(self.error_at(line, &format!("no variable named {}", variable)))
+                    }
+                    _ => return Err(self.expected("a note or variable")),
+                };
+                self.verifier_options(&mut verifier, line)?;
+                Ok(Statement::Verify { verifier, root })
+            }
+            _ => Err(self.error_at(line, &format!("unknown statement {}", keyword))),
+        }
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, ScriptError> {
+        self.expect_symbol('[')?;
+        let mut items = Vec::new();
+        while !self.peek_symbol(']') {
+            items.push(self.expect_string()?);
+            if self.peek_symbol(',') {
+                self.next();
+            } else {
+                break;
+            }
+        }
+        self.expect_symbol(']')?;
+        Ok(items)
+    }
+
+    fn verifier_options(
+        &mut self,
+        verifier: &mut Verifier,
+        line: usize,
+    ) -> Result<(), ScriptError> {
+        let Verifier::Scale {
+            octaves, direction, ..
+        } = verifier;
+        while let Some(argument) = self.keyword_argument() {
+            match argument.as_str() {
+                "octaves" => {
+                    *octaves = u8::try_from(self.expect_number()?)
+                        .ok()
+                        .filter(|octaves| (1..=MAX_OCTAVES).contains(octaves))
+                        .ok_or_else(|| self.error_at(line, "octaves must be between 1 and 4"))?
+                }
+                "direction" => {
+                    *direction = self
+                        .expect_word()?
+                        .parse()
+                        .map_err(|err: String| self.error_at(line, &err))?
+                }
+                _ => {
+                    return Err(self.error_at(line, &format!("unknown verify option {}", argument)))
+                }
+            }
+            if self.peek_symbol(',') {
+                self.next();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn verifies_variable(statements: &[Statement], variable: &str) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::Verify {
+            root: Argument::Variable(name),
+            ..
+        } => name == variable,
+        Statement::ForeachVerifying { body, .. } => verifies_variable(body, variable),
+        _ => false,
+    })
+}
+
+pub struct ScriptedPracticeProgram {
+    state: PracticeProgramState,
+    ctrl_sender: SyncSender<ControlMessage>,
+    key_receiver: Receiver<KeyMessage>,
+    speaker: Speaker,
+    script: Script,
+}
+
+/// What a block of statements runs with
+#[derive(Clone, Default)]
+struct Scope {
+    sequences: HashMap<String, Vec<String>>,
+    variables: HashMap<String, String>,
+    listen: Listen,
+    first_attempt: bool,
+}
+
+impl Scope {
+    /// Fills in variables, with note names pronounced in `locale`
+    fn interpolate(&self, text: &str, locale: Locale) -> String {
+        let mut interpolated = text.to_string();
+        for (name, value) in &self.variables {
+            interpolated =
+                interpolated.replace(&format!("${{{}}}", name), &locale.pronounce_name(value));
+        }
+        interpolated
+    }
+}
+
+impl ScriptedPracticeProgram {
+    pub fn new(
+        ctrl_sender: SyncSender<ControlMessage>,
+        key_receiver: Receiver<KeyMessage>,
+        speaker: Speaker,
+        script: Script,
+    ) -> ScriptedPracticeProgram {
+        ScriptedPracticeProgram {
+            state: PracticeProgramState::INITIALIZING,
+            ctrl_sender,
+            key_receiver,
+            speaker,
+            script,
+        }
+    }
+
+    /// Runs statements in order, returning false as soon as a verification
+    /// fails, or an error if the script names a sequence it never declared
+    fn execute(&mut self, statements: &[Statement], scope: &mut Scope) -> Result<bool, String> {
+        for statement in statements {
+            trace!("executing {:?}", statement);
+            match statement {
+                Statement::Sequence {
+                    name,
+                    items,
+                    randomize,
+                } => {
+                    let mut items = items.clone();
+                    if *randomize {
+                        items.shuffle(&mut rand::thread_rng());
+                    }
+                    scope.sequences.insert(name.clone(), items);
+                }
+                Statement::ForeachVerifying {
+                    variable,
+                    sequence,
+                    body,
+                } => {
+                    let Some(items) = scope.sequences.get(sequence).cloned() else {
+                        return Err(format!("no sequence named {}", sequence));
+                    };
+                    for item in items {
+                        let mut inner = scope.clone();
+                        inner.variables.insert(variable.clone(), item);
+                        inner.first_attempt = true;
+                        while !self.execute(body, &mut inner.clone())? {
+                            inner.first_attempt = false;
+                        }
+                    }
+                }
+                Statement::Prompt { text, if_first } => {
+                    if !if_first || scope.first_attempt {
+                        self.state = PracticeProgramState::PROMPTING;
+                        self.speaker
+                            .prompt(scope.interpolate(text, self.speaker.locale()));
+                    }
+                }
+                Statement::Listen(listen) => scope.listen = *listen,
+                Statement::Verify { verifier, root } => {
+                    let root = match root {
+                        Argument::PitchClass(root) => *root,
+                        Argument::Variable(name) => {
+                            match scope.variables.get(name).map(|value| value.parse()) {
+                                Some(Ok(root)) => root,
+                                _ => {
+                                    log::error!("{} isn't a note, skipping verification", name);
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    if !self.verify(verifier, root, &scope.listen) {
+                        return Ok(false);
+                    }
+                }
+                Statement::Inform { text } => {
+                    self.state = PracticeProgramState::PROMPTING;
+                    self.speaker
+                        .prompt(scope.interpolate(text, self.speaker.locale()));
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Listens for an attempt, explaining what went wrong if it fails
+    fn verify(&mut self, verifier: &Verifier, root: PitchClass, listen: &Listen) -> bool {
+        let Verifier::Scale {
+            scale,
+            octaves,
+            direction,
+        } = *verifier;
+        let request = ScaleRequest {
+            octaves,
+            direction,
+            ..ScaleRequest::new(scale, root)
+        };
+
+        self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+        self.state = PracticeProgramState::LISTENING;
+        let deadline = listen
+            .max_listen_seconds
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds));
+        let mut chronological_key_ups = Vec::new();
+        loop {
+            let received = match deadline {
+                Some(deadline) => self
+                    .key_receiver
+                    .recv_timeout(deadline.saturating_duration_since(Instant::now())),
+                None => self
+                    .key_receiver
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let latest = match received {
+                Ok(latest) => latest,
+                Err(RecvTimeoutError::Timeout) => {
+                    info!("ran out of time for {} {} scale", root, scale.name);
+                    self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                    self.speaker
+                        .feedback(self.speaker.locale().message(&Message::TimesUp));
+                    return false;
+                }
+                Err(RecvTimeoutError::Disconnected) => panic!("key messages stopped arriving"),
+            };
+            // whatever is still being played over feedback belongs to the last attempt
+            if self.speaker.speaking() == Some(Priority::Feedback) {
+                continue;
+            }
+            if latest.message_type == MidiMessageTypes::NoteOn {
+                self.speaker.cancel(Priority::Prompt);
+                continue;
+            }
+            if latest.message_type != MidiMessageTypes::NoteOff {
+                continue;
+            }
+            chronological_key_ups.push(latest.key);
+
+            let verification = request.verify(&chronological_key_ups);
+            trace!("verification = {:?}", verification);
+            if verification.is_complete() {
+                info!("user played {} {} scale", root, scale.name);
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                return true;
+            }
+            let out_of_notes = listen
+                .max_notes
+                .is_some_and(|max_notes| chronological_key_ups.len() >= max_notes);
+            if out_of_notes || request.attempt_finished(&chronological_key_ups) {
+                let locale = self.speaker.locale();
+                let explanation = request
+                    .explain_mistake(&chronological_key_ups, |mistake, key| {
+                        locale.explain_mistake(mistake, key)
+                    })
+                    .unwrap_or_else(|| locale.message(&Message::NotQuiteRight));
+                info!("user made a mistake: {}", explanation);
+                self.ctrl_sender.send(ControlMessage::NewRun).unwrap();
+                if let Some(message) = partly_right(&request, &verification) {
+                    self.speaker.feedback(locale.message(&message));
+                }
+                self.speaker.feedback(explanation);
+                return false;
+            }
+        }
+    }
+}
+
+impl PracticeProgram for ScriptedPracticeProgram {
+    fn get_state(&self) -> PracticeProgramState {
+        return self.state;
+    }
+
+    fn run(mut self) {
+        info!("starting script {}", self.script.name);
+        std::thread::spawn(move || {
+            let statements = self.script.statements.clone();
+            let mut scope = Scope {
+                first_attempt: true,
+                ..Scope::default()
+            };
+            if let Err(err) = self.execute(&statements, &mut scope) {
+                log::error!("stopping script {}: {}", self.script.name, err);
+            }
+            self.state = PracticeProgramState::FINISHED;
+            info!("finished script {}", self.script.name);
+            // keep draining so the key dispatcher never blocks on a full channel
+            while self.key_receiver.recv().is_ok() {}
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::scale::{HARMONIC_MINOR, MAJOR};
+
+    fn parse(s: &str) -> Script {
+        s.parse().unwrap()
+    }
+
+    /// The line and message of the syntax error `s` fails with
+    fn error(s: &str) -> (usize, String) {
+        match s.parse::<Script>() {
+            Err(ScriptError::Syntax { line, message }) => (line, message),
+            other => panic!("expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_the_minor_scales_drill() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("drills/minor.txt");
+        let script = Script::load(&path).unwrap();
+        assert_eq!(script.name, "harmonic-minor-scales-random-order");
+        let [Statement::Sequence {
+            name,
+            items,
+            randomize,
+        }, Statement::ForeachVerifying {
+            variable,
+            sequence,
+            body,
+        }, Statement::Inform { text }] = &script.statements[..]
+        else {
+            panic!("unexpected statements {:?}", script.statements);
+        };
+        assert_eq!(name, "keys");
+        assert_eq!(items.len(), 12);
+        assert!(randomize);
+        assert_eq!((variable.as_str(), sequence.as_str()), ("key", "keys"));
+        assert_eq!(text, "you are done");
+        assert_eq!(
+            body,
+            &[
+                Statement::Prompt {
+                    text: "play a harmonic minor scale in the key of ${key}".to_string(),
+                    if_first: true,
+                },
+                Statement::Listen(Listen {
+                    max_notes: Some(20),
+                    max_listen_seconds: Some(60),
+                }),
+                Statement::Verify {
+                    verifier: Verifier::Scale {
+                        scale: &HARMONIC_MINOR,
+                        octaves: 1,
+                        direction: Direction::UpAndDown,
+                    },
+                    root: Argument::Variable("key".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn the_wrapper_and_comments_are_optional() {
+        let script = parse(
+            "# a comment\n\
+             verify_major_scale \"Eb\" octaves=2, direction=ascending // another\n\
+             prompt \"again\"",
+        );
+        assert_eq!(script.name, "");
+        assert_eq!(
+            script.statements,
+            [
+                Statement::Verify {
+                    verifier: Verifier::Scale {
+                        scale: &MAJOR,
+                        octaves: 2,
+                        direction: Direction::Ascending,
+                    },
+                    root: Argument::PitchClass("Eb".parse().unwrap()),
+                },
+                Statement::Prompt {
+                    text: "again".to_string(),
+                    if_first: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        assert_eq!(
+            error("inform \"hi\"\nforeach_verifying key in keys {\n}"),
+            (2, "no sequence named keys".to_string())
+        );
+        assert_eq!(
+            error("verify_major_scale \"C\"\n\nverify_major_scale \"C\" hands=2"),
+            (3, "unknown verify option hands".to_string())
+        );
+        assert_eq!(
+            error("verify_major_scale \"C\" octaves=5").1,
+            "octaves must be between 1 and 4"
+        );
+        assert_eq!(
+            error("verify_bebop_scale \"C\"").1,
+            "unknown verifier verify_bebop_scale"
+        );
+        assert_eq!(error("verify_major_scale \"H\"").1, "\"H\" isn't a note");
+        assert_eq!(
+            error("listen max_keys=3").1,
+            "unknown listen option max_keys"
+        );
+        assert_eq!(
+            error("prompt if_last=\"no\"").1,
+            "unknown prompt option if_last"
+        );
+        assert_eq!(error("play \"C\"").1, "unknown statement play");
+        assert_eq!(
+            error("inform \"hi\n\""),
+            (1, "unterminated string".to_string())
+        );
+        assert_eq!(
+            error("inform \"hi\" }").1,
+            "unexpected text after the end of the program"
+        );
+    }
+
+    #[test]
+    fn variables_are_scoped_to_their_loop() {
+        let nested = parse(
+            "sequence roots, [\"C\", \"G\"]\n\
+             sequence octaves, [\"1\", \"2\"]\n\
+             foreach_verifying root in roots {\n\
+                 foreach_verifying octave in octaves {\n\
+                     verify_major_scale root\n\
+                 }\n\
+             }",
+        );
+        assert_eq!(nested.statements.len(), 3);
+
+        assert_eq!(
+            error(
+                "sequence roots, [\"C\"]\n\
+                 foreach_verifying root in roots {\n\
+                 }\n\
+                 verify_major_scale root"
+            ),
+            (4, "no variable named root".to_string())
+        );
+        // a sequence has to be declared before it's used
+        assert_eq!(
+            error("foreach_verifying root in roots {\n}\nsequence roots, [\"C\"]").1,
+            "no sequence named roots"
+        );
+        assert_eq!(
+            error(
+                "sequence octaves, [\"1\", \"2\"]\n\
+                 foreach_verifying octave in octaves {\n\
+                     verify_major_scale octave\n\
+                 }"
+            ),
+            (
+                2,
+                "octave is verified as a note, but \"1\" isn't one".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn sequences_are_scoped_to_their_block() {
+        let nested = parse(
+            "sequence roots, [\"C\", \"G\"]\n\
+             foreach_verifying root in roots {\n\
+                 sequence octaves, [\"1\", \"2\"]\n\
+                 foreach_verifying octave in octaves {\n\
+                     verify_major_scale root\n\
+                 }\n\
+             }",
+        );
+        assert_eq!(nested.statements.len(), 2);
+
+        assert_eq!(
+            error(
+                "sequence roots, [\"C\"]\n\
+                 foreach_verifying root in roots {\n\
+                     sequence again, [\"D\"]\n\
+                 }\n\
+                 foreach_verifying root in again {\n\
+                 }"
+            ),
+            (5, "no sequence named again".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolates_variables_in_the_locale() {
+        let scope = Scope {
+            variables: HashMap::from([("key".to_string(), "Bb".to_string())]),
+            ..Scope::default()
+        };
+        let text = "the key of ${key}, not ${other}";
+        assert_eq!(
+            scope.interpolate(text, Locale::English),
+            "the key of B Flat, not ${other}"
+        );
+        assert_eq!(
+            scope.interpolate(text, Locale::German),
+            "the key of B, not ${other}"
+        );
+    }
+}
@@ -0,0 +1,286 @@
+//! Records a practice session into a WAV file instead of playing it live:
+//! speech is rendered by a local engine and notes sent to MIDI out by a
+//! built-in synth, each placed at the time it happened.
+
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+use crate::{
+    midi::{KeyMessage, MidiMessageTypes},
+    speech::{backend::auto_engine, SpeechBackend, SpeechError, Utterance},
+};
+
+pub mod synth;
+pub mod wav;
+
+pub use synth::SynthNote;
+pub use wav::Wav;
+
+pub const SAMPLE_RATE: u32 = 22050;
+
+#[derive(Default)]
+struct SessionState {
+    speech: Vec<(Duration, Wav)>,
+    notes: Vec<SynthNote>,
+    // notes that have started but not ended, with their start and velocity
+    sounding: HashMap<u8, (Duration, u8)>,
+}
+
+/// Everything said and played to the player so far. Clones share the same
+/// session.
+#[derive(Clone)]
+pub struct SessionRecorder {
+    started: Instant,
+    state: Arc<Mutex<SessionState>>,
+}
+
+impl SessionRecorder {
+    pub fn new() -> SessionRecorder {
+        SessionRecorder {
+            started: Instant::now(),
+            state: Arc::new(Mutex::new(SessionState::default())),
+        }
+    }
+
+    /// Time since the session started
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn add_speech(&self, at: Duration, speech: Wav) {
+        self.state.lock().unwrap().speech.push((at, speech));
+    }
+
+    /// Records a note on or off that would have gone to MIDI out
+    pub fn record_key(&self, msg: KeyMessage) {
+        let now = self.elapsed();
+        let mut state = self.state.lock().unwrap();
+        let started = match msg.message_type {
+            MidiMessageTypes::NoteOn if msg.velocity > 0 => {
+                state.sounding.insert(msg.key, (now, msg.velocity))
+            }
+            _ => state.sounding.remove(&msg.key),
+        };
+        // a repeated note on ends the note before it
+        if let Some((start, velocity)) = started {
+            state.notes.push(SynthNote {
+                key: msg.key,
+                velocity,
+                start,
+                end: now,
+            });
+        }
+    }
+
+    /// Mixes everything recorded so far, cutting off notes that are still sounding
+    pub fn render(&self) -> Wav {
+        let now = self.elapsed();
+        let state = self.state.lock().unwrap();
+        let mut out = Wav::new(SAMPLE_RATE, Vec::new());
+        let still_sounding = state
+            .sounding
+            .iter()
+            .map(|(key, (start, velocity))| SynthNote {
+                key: *key,
+                velocity: *velocity,
+                start: *start,
+                end: now,
+            });
+        for note in state.notes.iter().copied().chain(still_sounding) {
+            note.render_into(&mut out);
+        }
+        for (at, speech) in &state.speech {
+            out.mix(&speech.resampled(SAMPLE_RATE).samples, *at);
+        }
+        out
+    }
+
+    pub fn write_wav(&self, path: &Path) -> io::Result<()> {
+        self.render().write(path)
+    }
+}
+
+impl Default for SessionRecorder {
+    fn default() -> SessionRecorder {
+        SessionRecorder::new()
+    }
+}
+
+/// A speech engine that can write what it says to a WAV file
+enum Renderer {
+    Say { voice: String },
+    EspeakNg { voice: Option<String> },
+    Festival,
+    Piper { model: String },
+    Silent,
+}
+
+impl Renderer {
+    fn by_name(name: &str, voice: Option<String>) -> Result<Renderer, SpeechError> {
+        Ok(match name {
+            "auto" => match auto_engine(true) {
+                Some(program) => Renderer::by_name(program, voice)?,
+                None => {
+                    warn!("no speech engine found, the session will have no speech");
+                    Renderer::Silent
+                }
+            },
+            "say" => Renderer::Say {
+                voice: voice.unwrap_or_else(|| "Moira".to_string()),
+            },
+            "espeak-ng" | "espeak" => Renderer::EspeakNg { voice },
+            "festival" => Renderer::Festival,
+            "piper" => Renderer::Piper {
+                model: voice.ok_or(SpeechError::MissingVoice("piper"))?,
+            },
+            "none" | "null" | "log" => Renderer::Silent,
+            _ => return Err(SpeechError::UnknownBackend(name.to_string())),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Renderer::Say { .. } => "say",
+            Renderer::EspeakNg { .. } => "espeak-ng",
+            Renderer::Festival => "festival",
+            Renderer::Piper { .. } => "piper",
+            Renderer::Silent => "none",
+        }
+    }
+
+    /// Writes `text` as speech to `path`, or returns false if this renderer
+    /// makes no sound
+    fn render(&self, text: &str, path: &Path) -> Result<bool, SpeechError> {
+        let mut input = None;
+        let mut command = match self {
+            Renderer::Say { voice } => {
+                let mut command = Command::new("say");
+                command
+                    .arg(format!("--voice={}", voice))
+                    .arg("-o")
+                    .arg(path)
+                    .arg(format!("--data-format=LEI16@{}", SAMPLE_RATE))
+                    .arg(text);
+                command
+            }
+            Renderer::EspeakNg { voice } => {
+                let mut command = Command::new("espeak-ng");
+                if let Some(voice) = voice {
+                    command.arg("-v").arg(voice);
+                }
+                command.arg("-w").arg(path).arg("--").arg(text);
+                command
+            }
+            Renderer::Festival => {
+                input = Some(text);
+                let mut command = Command::new("text2wave");
+                command.arg("-o").arg(path);
+                command
+            }
+            Renderer::Piper { model } => {
+                input = Some(text);
+                let mut command = Command::new("piper");
+                command
+                    .arg("--model")
+                    .arg(model)
+                    .arg("--output_file")
+                    .arg(path);
+                command
+            }
+            Renderer::Silent => return Ok(false),
+        };
+
+        let backend = self.name();
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|source| SpeechError::Spawn { backend, source })?;
+        // dropping stdin closes it, which tells the engine the text is over
+        let mut stdin = child.stdin.take().unwrap();
+        if let Some(text) = input {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|source| SpeechError::Spawn { backend, source })?;
+        }
+        drop(stdin);
+        let status = child
+            .wait()
+            .map_err(|source| SpeechError::Spawn { backend, source })?;
+        if !status.success() {
+            return Err(SpeechError::Failed { backend, status });
+        }
+        Ok(true)
+    }
+}
+
+/// Renders speech into a session instead of saying it. Each utterance lasts
+/// as long as the rendered speech so prompts don't pile up on each other;
+/// cancelled speech is still rendered in full.
+pub struct SessionSpeech {
+    renderer: Renderer,
+    recorder: SessionRecorder,
+    rendered: AtomicU64,
+}
+
+impl SessionSpeech {
+    /// `engine` and `voice` are as for --speech-backend and --voice, but only
+    /// say, espeak-ng, festival, piper and none can render to a file
+    pub fn new(
+        engine: &str,
+        voice: Option<String>,
+        recorder: SessionRecorder,
+    ) -> Result<SessionSpeech, SpeechError> {
+        let renderer = Renderer::by_name(engine, voice)?;
+        info!("rendering speech with {}", renderer.name());
+        Ok(SessionSpeech {
+            renderer,
+            recorder,
+            rendered: AtomicU64::new(0),
+        })
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        let count = self.rendered.fetch_add(1, Ordering::Relaxed);
+        env::temp_dir().join(format!("midi-hack-{}-{}.wav", process::id(), count))
+    }
+}
+
+impl SpeechBackend for SessionSpeech {
+    fn name(&self) -> &'static str {
+        self.renderer.name()
+    }
+
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let at = self.recorder.elapsed();
+        let path = self.temp_path();
+        if !self.renderer.render(text, &path)? {
+            info!("(not rendered) {}", text);
+            return Ok(Utterance::finished(self.name()));
+        }
+        let read = Wav::read(&path);
+        let _ = std::fs::remove_file(&path);
+        let mut speech = read.map_err(|source| SpeechError::Render {
+            backend: self.name(),
+            source,
+        })?;
+        let gain = f32::from(volume.unwrap_or(100)) / 100.0;
+        speech.samples.iter_mut().for_each(|sample| *sample *= gain);
+        let duration = speech.duration();
+        self.recorder.add_speech(at, speech);
+        Ok(Utterance::lasting(self.name(), duration))
+    }
+}
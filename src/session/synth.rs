@@ -0,0 +1,61 @@
+//! A small additive synth standing in for the MIDI device when notes sent to
+//! MIDI out are rendered into a session
+
+use std::{f32::consts::TAU, time::Duration};
+
+use super::wav::Wav;
+
+// relative loudness of the fundamental and the next few harmonics
+const HARMONICS: &[f32] = &[1.0, 0.5, 0.25, 0.125];
+const ATTACK: Duration = Duration::from_millis(5);
+const RELEASE: Duration = Duration::from_millis(150);
+// how quickly a held note fades, per second, like a struck string
+const DECAY: f32 = 2.0;
+// so a few loud notes together don't clip
+const MAX_AMPLITUDE: f32 = 0.2;
+
+/// A note from note on to note off, timed from the start of the session
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SynthNote {
+    pub key: u8,
+    pub velocity: u8,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl SynthNote {
+    pub fn frequency(&self) -> f32 {
+        440.0 * 2f32.powf((f32::from(self.key) - 69.0) / 12.0)
+    }
+
+    /// Mixes the note into `out` at its start time
+    pub fn render_into(&self, out: &mut Wav) {
+        let rate = out.sample_rate as f32;
+        let held = self.end.saturating_sub(self.start).as_secs_f32();
+        let length = ((held + RELEASE.as_secs_f32()) * rate) as usize;
+        let frequency = self.frequency();
+        let amplitude = MAX_AMPLITUDE * f32::from(self.velocity) / 127.0;
+        let total: f32 = HARMONICS.iter().sum();
+
+        let samples: Vec<f32> = (0..length)
+            .map(|i| {
+                let t = i as f32 / rate;
+                let envelope = if t < ATTACK.as_secs_f32() {
+                    t / ATTACK.as_secs_f32()
+                } else if t < held {
+                    (-DECAY * t).exp()
+                } else {
+                    (-DECAY * held).exp() * (1.0 - (t - held) / RELEASE.as_secs_f32())
+                };
+                let tone: f32 = HARMONICS
+                    .iter()
+                    .enumerate()
+                    .filter(|(n, _)| frequency * (*n as f32 + 1.0) < rate / 2.0)
+                    .map(|(n, loudness)| loudness * (TAU * frequency * (n as f32 + 1.0) * t).sin())
+                    .sum();
+                amplitude * envelope * tone / total
+            })
+            .collect();
+        out.mix(&samples, self.start);
+    }
+}
@@ -0,0 +1,246 @@
+//! Just enough of the WAV format to read what speech engines write and to
+//! write a session: 16-bit PCM, mixed down to mono.
+
+use std::{fs, io, path::Path, time::Duration};
+
+const PCM: u16 = 1;
+const EXTENSIBLE: u16 = 0xFFFE;
+
+/// Mono audio with samples from -1.0 to 1.0
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Wav {
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+impl Wav {
+    pub fn new(sample_rate: u32, samples: Vec<f32>) -> Wav {
+        Wav {
+            sample_rate,
+            samples,
+        }
+    }
+
+    pub fn read(path: &Path) -> io::Result<Wav> {
+        Wav::parse(&fs::read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> io::Result<Wav> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(invalid("not a WAV file"));
+        }
+        let mut format = None;
+        let mut at = 12;
+        while at + 8 <= bytes.len() {
+            let id = &bytes[at..at + 4];
+            // engines writing to a pipe can't know the size up front, so
+            // sizes past the end of the file mean "the rest of it"
+            let size = (u32_at(bytes, at + 4) as usize).min(bytes.len() - at - 8);
+            let body = &bytes[at + 8..at + 8 + size];
+            match id {
+                b"fmt " if size >= 16 => {
+                    let tag = u16_at(body, 0);
+                    let channels = u16_at(body, 2);
+                    let sample_rate = u32_at(body, 4);
+                    let bits = u16_at(body, 14);
+                    if !(tag == PCM || tag == EXTENSIBLE) || bits != 16 || channels == 0 {
+                        return Err(invalid("only 16-bit PCM WAV files are supported"));
+                    }
+                    if sample_rate == 0 {
+                        return Err(invalid("WAV file has a sample rate of 0"));
+                    }
+                    format = Some((channels as usize, sample_rate));
+                }
+                b"data" => {
+                    let (channels, sample_rate) =
+                        format.ok_or_else(|| invalid("WAV data before its format"))?;
+                    let samples = body
+                        .chunks_exact(2 * channels)
+                        .map(|frame| {
+                            let sum: f32 = frame
+                                .chunks_exact(2)
+                                .map(|sample| f32::from(i16::from_le_bytes([sample[0], sample[1]])))
+                                .sum();
+                            sum / channels as f32 / 32768.0
+                        })
+                        .collect();
+                    return Ok(Wav::new(sample_rate, samples));
+                }
+                _ => (),
+            }
+            // chunks are padded to an even length
+            at += 8 + size + size % 2;
+        }
+        Err(invalid("WAV file has no data"))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let data_size = self.samples.len() as u32 * 2;
+        let mut bytes = Vec::with_capacity(44 + data_size as usize);
+        bytes.extend_from_slice(b"RIFF");
+        bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&PCM.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.sample_rate * 2).to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        for sample in &self.samples {
+            let sample = (sample.clamp(-1.0, 1.0) * 32767.0) as i16;
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.samples.len() as f64 / f64::from(self.sample_rate))
+    }
+
+    /// Converts to another sample rate by linear interpolation
+    pub fn resampled(&self, sample_rate: u32) -> Wav {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return Wav::new(sample_rate, self.samples.clone());
+        }
+        let step = f64::from(self.sample_rate) / f64::from(sample_rate);
+        let length = (self.samples.len() as f64 / step) as usize;
+        let last = self.samples.len() - 1;
+        let samples = (0..length)
+            .map(|i| {
+                let position = i as f64 * step;
+                let before = (position as usize).min(last);
+                let after = (before + 1).min(last);
+                let fraction = (position - before as f64) as f32;
+                self.samples[before] * (1.0 - fraction) + self.samples[after] * fraction
+            })
+            .collect();
+        Wav::new(sample_rate, samples)
+    }
+
+    /// Adds `other` into this one starting `at` into it, growing it as needed.
+    /// Both must have the same sample rate.
+    pub fn mix(&mut self, other: &[f32], at: Duration) {
+        let start = (at.as_secs_f64() * f64::from(self.sample_rate)) as usize;
+        if self.samples.len() < start + other.len() {
+            self.samples.resize(start + other.len(), 0.0);
+        }
+        for (sample, added) in self.samples[start..].iter_mut().zip(other) {
+            *sample += added;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A WAV file with one fmt chunk and one data chunk of `frames`
+    fn wav_bytes(tag: u16, channels: u16, bits: u16, frames: &[i16], data_size: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend_from_slice(b"fmt ");
+        bytes.extend_from_slice(&16u32.to_le_bytes());
+        bytes.extend_from_slice(&tag.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&16000u32.to_le_bytes());
+        bytes.extend_from_slice(&(16000 * 2 * u32::from(channels)).to_le_bytes());
+        bytes.extend_from_slice(&(2 * channels).to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+        // a chunk of odd length to skip, with its padding byte
+        bytes.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        for sample in frames {
+            bytes.extend_from_slice(&sample.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let wav = Wav::new(22050, vec![0.0, 0.5, -0.5, 1.0, -1.0]);
+        let path = std::env::temp_dir().join(format!("wav-test-{}.wav", std::process::id()));
+        wav.write(&path).unwrap();
+        let read = Wav::read(&path);
+        fs::remove_file(&path).unwrap();
+        let read = read.unwrap();
+        assert_eq!(read.sample_rate, 22050);
+        assert_eq!(read.samples.len(), wav.samples.len());
+        for (read, written) in read.samples.iter().zip(&wav.samples) {
+            assert!((read - written).abs() < 1e-4, "{} vs {}", read, written);
+        }
+    }
+
+    #[test]
+    fn mixes_channels_down_to_mono() {
+        let frames = [16384, -16384, 16384, 16384];
+        let wav = Wav::parse(&wav_bytes(PCM, 2, 16, &frames, 8)).unwrap();
+        assert_eq!(wav.sample_rate, 16000);
+        assert_eq!(wav.samples, [0.0, 0.5]);
+        let extensible = Wav::parse(&wav_bytes(EXTENSIBLE, 2, 16, &frames, 8)).unwrap();
+        assert_eq!(extensible, wav);
+    }
+
+    #[test]
+    fn a_data_size_past_the_end_means_the_rest_of_the_file() {
+        let wav = Wav::parse(&wav_bytes(PCM, 1, 16, &[8192, 16384], u32::MAX)).unwrap();
+        assert_eq!(wav.samples, [0.25, 0.5]);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_read() {
+        assert!(Wav::parse(b"RIFX\0\0\0\0WAVE").is_err());
+        assert!(Wav::parse(&wav_bytes(PCM, 1, 8, &[0], 2)).is_err());
+        assert!(Wav::parse(&wav_bytes(3, 1, 16, &[0], 2)).is_err());
+        assert!(Wav::parse(&wav_bytes(PCM, 0, 16, &[0], 2)).is_err());
+        let mut no_sample_rate = wav_bytes(PCM, 1, 16, &[0], 2);
+        no_sample_rate[24..28].copy_from_slice(&0u32.to_le_bytes());
+        assert!(Wav::parse(&no_sample_rate).is_err());
+        let mut no_data = wav_bytes(PCM, 1, 16, &[], 0);
+        no_data.truncate(no_data.len() - 8);
+        assert!(Wav::parse(&no_data).is_err());
+    }
+
+    #[test]
+    fn resamples_by_linear_interpolation() {
+        let ramp = Wav::new(8000, (0..8).map(|i| i as f32 / 8.0).collect());
+        let down = ramp.resampled(4000);
+        assert_eq!(down.sample_rate, 4000);
+        assert_eq!(down.samples, [0.0, 0.25, 0.5, 0.75]);
+        assert_eq!(down.duration(), ramp.duration());
+
+        let up = ramp.resampled(16000);
+        assert_eq!(up.samples.len(), 16);
+        assert_eq!(&up.samples[..4], [0.0, 0.0625, 0.125, 0.1875]);
+        // past the last sample it holds the last value
+        assert_eq!(up.samples[15], 0.875);
+
+        assert_eq!(ramp.resampled(8000), ramp);
+        assert!(Wav::new(8000, Vec::new())
+            .resampled(4000)
+            .samples
+            .is_empty());
+    }
+
+    #[test]
+    fn mix_adds_and_grows() {
+        let mut wav = Wav::new(10, vec![0.5; 4]);
+        wav.mix(&[0.25, 0.25, 0.25], Duration::from_millis(200));
+        assert_eq!(wav.samples, [0.5, 0.5, 0.75, 0.75, 0.25]);
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    env,
+    error::Error,
+    fmt,
+    io::{self, Write},
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+
+pub const DEFAULT_VOLUME: u8 = 50;
+
+/// An engine "auto" looks for on the PATH, by its backend name
+struct Engine {
+    name: &'static str,
+    /// Whether it can write speech to a file, for recording sessions
+    renders: bool,
+}
+
+// tried in order when the backend is "auto"
+const AUTO_ENGINES: &[Engine] = &[
+    Engine {
+        name: "say",
+        renders: true,
+    },
+    Engine {
+        name: "espeak-ng",
+        renders: true,
+    },
+    Engine {
+        name: "spd-say",
+        renders: false,
+    },
+    Engine {
+        name: "festival",
+        renders: true,
+    },
+];
+
+#[derive(Debug)]
+pub enum SpeechError {
+    UnknownBackend(String),
+    MissingVoice(&'static str),
+    Spawn {
+        backend: &'static str,
+        source: io::Error,
+    },
+    Failed {
+        backend: &'static str,
+        status: ExitStatus,
+    },
+    /// The engine ran but what it wrote couldn't be read back
+    Render {
+        backend: &'static str,
+        source: io::Error,
+    },
+}
+
+impl fmt::Display for SpeechError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpeechError::UnknownBackend(name) => write!(
+                f,
+                "unknown speech backend \"{}\" (expected auto, say, espeak-ng, spd-say, festival, piper or none)",
+                name
+            ),
+            SpeechError::MissingVoice(backend) => {
+                write!(f, "the {} speech backend needs --voice", backend)
+            }
+            SpeechError::Spawn { backend, source } => {
+                write!(f, "couldn't start {}: {}", backend, source)
+            }
+            SpeechError::Failed { backend, status } => write!(f, "{} failed: {}", backend, status),
+            SpeechError::Render { backend, source } => {
+                write!(f, "couldn't read speech rendered by {}: {}", backend, source)
+            }
+        }
+    }
+}
+
+impl Error for SpeechError {}
+
+/// Something being spoken, which might still be in progress
+pub struct Utterance {
+    backend: &'static str,
+    // a pipeline for some backends, e.g. piper into aplay
+    processes: Vec<Child>,
+    // for speech that isn't a running process but should take time anyway
+    until: Option<Instant>,
+}
+
+impl Utterance {
+    pub fn new(backend: &'static str, processes: Vec<Child>) -> Utterance {
+        Utterance {
+            backend,
+            processes,
+            until: None,
+        }
+    }
+
+    /// An utterance that's already over, e.g. for backends that don't make sound
+    pub fn finished(backend: &'static str) -> Utterance {
+        Utterance::new(backend, Vec::new())
+    }
+
+    /// An utterance without a process that's over after `duration`, e.g.
+    /// for speech rendered to a file
+    pub fn lasting(backend: &'static str, duration: Duration) -> Utterance {
+        Utterance {
+            backend,
+            processes: Vec::new(),
+            until: Some(Instant::now() + duration),
+        }
+    }
+
+    pub fn is_finished(&mut self) -> bool {
+        if let Some(until) = self.until {
+            if Instant::now() < until {
+                return false;
+            }
+        }
+        self.processes
+            .iter_mut()
+            .all(|process| !matches!(process.try_wait(), Ok(None)))
+    }
+
+    /// Blocks until the utterance is over
+    pub fn wait(mut self) -> Result<(), SpeechError> {
+        if let Some(until) = self.until {
+            std::thread::sleep(until.saturating_duration_since(Instant::now()));
+        }
+        for process in self.processes.iter_mut() {
+            let status = process.wait().map_err(|source| SpeechError::Spawn {
+                backend: self.backend,
+                source,
+            })?;
+            if !status.success() {
+                return Err(SpeechError::Failed {
+                    backend: self.backend,
+                    status,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops speaking right away
+    pub fn cancel(mut self) {
+        for process in self.processes.iter_mut() {
+            // it may have already finished, which is fine
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// A text-to-speech engine
+pub trait SpeechBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Starts speaking `text` at `volume` (0-100, engine default if None),
+    /// returning without waiting for it to finish
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError>;
+}
+
+/// Looks up a backend by the name used for the --speech-backend flag.
+/// `voice` is the voice name for say, espeak-ng and spd-say, and the model
+/// file for piper.
+pub fn backend_by_name(
+    name: &str,
+    voice: Option<String>,
+) -> Result<Box<dyn SpeechBackend>, SpeechError> {
+    let backend: Box<dyn SpeechBackend> = match name {
+        "auto" => {
+            return Ok(match auto_engine(false) {
+                Some(program) => backend_by_name(program, voice)?,
+                None => {
+                    warn!("no speech engine found, speech will only be logged");
+                    Box::new(NullSpeech)
+                }
+            })
+        }
+        "say" => Box::new(MacSay {
+            voice: voice.unwrap_or_else(|| "Moira".to_string()),
+        }),
+        "espeak-ng" | "espeak" => Box::new(EspeakNg { voice }),
+        "spd-say" | "speech-dispatcher" => Box::new(SpdSay { voice }),
+        "festival" => Box::new(Festival),
+        "piper" => Box::new(Piper {
+            model: voice.ok_or(SpeechError::MissingVoice("piper"))?,
+        }),
+        "none" | "null" | "log" => Box::new(NullSpeech),
+        _ => return Err(SpeechError::UnknownBackend(name.to_string())),
+    };
+    info!("using {} for speech", backend.name());
+    Ok(backend)
+}
+
+/// The first engine on the PATH that "auto" would pick, only counting ones
+/// that can write speech to a file if `renders`
+pub(crate) fn auto_engine(renders: bool) -> Option<&'static str> {
+    AUTO_ENGINES
+        .iter()
+        .find(|engine| (engine.renders || !renders) && on_path(engine.name))
+        .map(|engine| engine.name)
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+fn spawn(backend: &'static str, command: &mut Command) -> Result<Child, SpeechError> {
+    command
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|source| SpeechError::Spawn { backend, source })
+}
+
+/// Spawns `command` and writes `text` to its standard input
+fn spawn_with_input(
+    backend: &'static str,
+    command: &mut Command,
+    text: &str,
+) -> Result<Child, SpeechError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|source| SpeechError::Spawn { backend, source })?;
+    // dropping stdin closes it, which tells the engine the text is over
+    let mut stdin = child.stdin.take().unwrap();
+    stdin
+        .write_all(text.as_bytes())
+        .map_err(|source| SpeechError::Spawn { backend, source })?;
+    Ok(child)
+}
+
+/// The macOS `say` command
+pub struct MacSay {
+    pub voice: String,
+}
+
+impl SpeechBackend for MacSay {
+    fn name(&self) -> &'static str {
+        "say"
+    }
+
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let mut text = text.to_string();
+        if let Some(volume) = volume {
+            text = format!("[[volm {}]] {}", f32::from(volume) / 100.0, text);
+        }
+        let child = spawn(
+            self.name(),
+            Command::new("say")
+                .arg(format!("--voice={}", self.voice))
+                .arg(text),
+        )?;
+        Ok(Utterance::new(self.name(), vec![child]))
+    }
+}
+
+pub struct EspeakNg {
+    pub voice: Option<String>,
+}
+
+impl SpeechBackend for EspeakNg {
+    fn name(&self) -> &'static str {
+        "espeak-ng"
+    }
+
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let mut command = Command::new("espeak-ng");
+        if let Some(voice) = &self.voice {
+            command.arg("-v").arg(voice);
+        }
+        if let Some(volume) = volume {
+            // amplitude goes from 0 to 200, 100 is the default
+            command.arg("-a").arg((u16::from(volume) * 2).to_string());
+        }
+        let child = spawn(self.name(), command.arg("--").arg(text))?;
+        Ok(Utterance::new(self.name(), vec![child]))
+    }
+}
+
+/// speech-dispatcher's command line client
+pub struct SpdSay {
+    pub voice: Option<String>,
+}
+
+impl SpeechBackend for SpdSay {
+    fn name(&self) -> &'static str {
+        "spd-say"
+    }
+
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let mut command = Command::new("spd-say");
+        // otherwise it returns as soon as the text is queued
+        command.arg("--wait");
+        if let Some(voice) = &self.voice {
+            command.arg("--synthesis-voice").arg(voice);
+        }
+        if let Some(volume) = volume {
+            // volume goes from -100 to 100
+            command
+                .arg("--volume")
+                .arg((i16::from(volume) * 2 - 100).to_string());
+        }
+        let child = spawn(self.name(), command.arg("--").arg(text))?;
+        Ok(Utterance::new(self.name(), vec![child]))
+    }
+}
+
+/// Festival reading from standard input; it has no volume control
+pub struct Festival;
+
+impl SpeechBackend for Festival {
+    fn name(&self) -> &'static str {
+        "festival"
+    }
+
+    fn speak(&self, text: &str, _volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let child = spawn_with_input(self.name(), Command::new("festival").arg("--tts"), text)?;
+        Ok(Utterance::new(self.name(), vec![child]))
+    }
+}
+
+/// Piper neural TTS, played through ALSA's aplay. It has no volume control.
+pub struct Piper {
+    /// Path to the .onnx voice model
+    pub model: String,
+}
+
+// sample rate of the medium and high quality piper voices
+const PIPER_SAMPLE_RATE: u32 = 22050;
+
+impl SpeechBackend for Piper {
+    fn name(&self) -> &'static str {
+        "piper"
+    }
+
+    fn speak(&self, text: &str, _volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        let mut piper = spawn_with_input(
+            self.name(),
+            Command::new("piper")
+                .arg("--model")
+                .arg(Path::new(&self.model))
+                .arg("--output-raw")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null()),
+            text,
+        )?;
+        let audio = piper.stdout.take().unwrap();
+        let aplay = spawn(
+            self.name(),
+            Command::new("aplay")
+                .args(["-q", "-t", "raw", "-f", "S16_LE", "-c", "1"])
+                .arg("-r")
+                .arg(PIPER_SAMPLE_RATE.to_string())
+                .stdin(audio),
+        )?;
+        Ok(Utterance::new(self.name(), vec![piper, aplay]))
+    }
+}
+
+/// Only logs what would have been said, for machines without a speech engine
+pub struct NullSpeech;
+
+impl SpeechBackend for NullSpeech {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn speak(&self, text: &str, _volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        info!("(not spoken) {}", text);
+        Ok(Utterance::finished(self.name()))
+    }
+}
+
+/// Something RecordingSpeech was asked to say
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedUtterance {
+    /// Time since the backend was created
+    pub at: Duration,
+    pub text: String,
+    pub volume: Option<u8>,
+}
+
+/// Remembers everything it's asked to say instead of saying it, for tests.
+/// Clones share the same recording.
+#[derive(Clone)]
+pub struct RecordingSpeech {
+    created: Instant,
+    utterances: Arc<Mutex<Vec<RecordedUtterance>>>,
+}
+
+impl RecordingSpeech {
+    pub fn new() -> RecordingSpeech {
+        RecordingSpeech {
+            created: Instant::now(),
+            utterances: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn utterances(&self) -> Vec<RecordedUtterance> {
+        self.utterances.lock().unwrap().clone()
+    }
+
+    /// Just what was said, oldest first
+    pub fn texts(&self) -> Vec<String> {
+        self.utterances
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|utterance| utterance.text.clone())
+            .collect()
+    }
+
+    pub fn clear(&self) {
+        self.utterances.lock().unwrap().clear();
+    }
+}
+
+impl Default for RecordingSpeech {
+    fn default() -> RecordingSpeech {
+        RecordingSpeech::new()
+    }
+}
+
+impl SpeechBackend for RecordingSpeech {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn speak(&self, text: &str, volume: Option<u8>) -> Result<Utterance, SpeechError> {
+        self.utterances.lock().unwrap().push(RecordedUtterance {
+            at: self.created.elapsed(),
+            text: text.to_string(),
+            volume,
+        });
+        Ok(Utterance::finished(self.name()))
+    }
+}
@@ -0,0 +1,253 @@
+//! Everything the practice programs say, in each language they can say it
+//! in. Each locale has a catalog in its own module; English text for chords
+//! and mistakes comes from the music module itself.
+
+use std::{fmt, str::FromStr};
+
+use crate::music::{ChordMatch, Direction, Interval, Key, Mistake, PitchClass, RomanNumeral};
+
+mod de;
+mod en;
+mod es;
+mod fr;
+mod it;
+mod ja;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    /// With H for B and B for B flat
+    German,
+    /// Do-Re-Mi note names, as are Italian and Spanish
+    French,
+    Italian,
+    Spanish,
+    /// Do-Re-Mi note names in katakana
+    Japanese,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 6] = [
+        Locale::English,
+        Locale::German,
+        Locale::French,
+        Locale::Italian,
+        Locale::Spanish,
+        Locale::Japanese,
+    ];
+
+    /// The ISO 639-1 code used to pick the locale, e.g. "de"
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::German => "de",
+            Locale::French => "fr",
+            Locale::Italian => "it",
+            Locale::Spanish => "es",
+            Locale::Japanese => "ja",
+        }
+    }
+
+    fn catalog(&self) -> &'static dyn Catalog {
+        match self {
+            Locale::English => &en::English,
+            Locale::German => &de::German,
+            Locale::French => &fr::French,
+            Locale::Italian => &it::Italian,
+            Locale::Spanish => &es::Spanish,
+            Locale::Japanese => &ja::Japanese,
+        }
+    }
+
+    /// A note name for speech, e.g. "B Flat", "B" in German or "Si bémol"
+    pub fn pronounce(&self, pitch_class: &PitchClass) -> String {
+        self.catalog().note_name(pitch_class)
+    }
+
+    /// Like pronounce for a note name like "Bb"; anything that doesn't parse
+    /// as a pitch class is returned unchanged
+    pub fn pronounce_name(&self, note: &str) -> String {
+        match note.parse::<PitchClass>() {
+            Ok(pitch_class) => self.pronounce(&pitch_class),
+            Err(_) => note.to_string(),
+        }
+    }
+
+    /// e.g. "perfect fifth" or "descending minor tenth"
+    pub fn interval_name(&self, interval: &Interval) -> String {
+        self.catalog().interval_name(interval)
+    }
+
+    /// e.g. "five dominant seventh of two" for V7/ii
+    pub fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        self.catalog().roman_numeral(numeral)
+    }
+
+    pub fn message(&self, message: &Message) -> String {
+        self.catalog().message(message)
+    }
+
+    /// e.g. "C major seventh, first inversion"
+    pub fn describe_chord(&self, chord: &ChordMatch) -> String {
+        self.catalog().chord(chord)
+    }
+
+    /// A sentence explaining `mistake`, spelling notes in `key`
+    pub fn explain_mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        self.catalog().mistake(mistake, key)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// An ISO 639-1 code like "de", optionally with a region like "de_AT",
+    /// or the language's English name
+    fn from_str(s: &str) -> Result<Locale, String> {
+        let language = s
+            .trim()
+            .to_lowercase()
+            .split(['-', '_', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        match language.as_str() {
+            "en" | "english" => Ok(Locale::English),
+            "de" | "german" => Ok(Locale::German),
+            "fr" | "french" => Ok(Locale::French),
+            "it" | "italian" => Ok(Locale::Italian),
+            "es" | "spanish" => Ok(Locale::Spanish),
+            "ja" | "japanese" => Ok(Locale::Japanese),
+            _ => Err(format!(
+                "unknown locale \"{}\" (expected en, de, fr, it, es or ja)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Something a practice program says, other than chords and mistakes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Message {
+    /// Asks for a major scale; anything other than one octave up and down
+    /// gets spelled out
+    PlayMajorScale {
+        root: PitchClass,
+        octaves: u8,
+        start_octave: Option<i8>,
+        direction: Direction,
+        contrary_motion: bool,
+    },
+    ProgramFinished,
+    /// A major scale was played, but not the one asked for
+    WrongKey,
+    /// A mistake that couldn't be pinned down
+    NotQuiteRight,
+    /// A run that went wrong after `completed` of its `sections` (octaves
+    /// going one way, counting each hand in contrary motion) were played
+    PartlyRight {
+        completed: usize,
+        sections: usize,
+    },
+    TimesUp,
+    StartingEarTraining,
+    NewChord,
+    PerfectMatch,
+    /// The right interval starting on another note
+    CorrectInterval(Interval),
+    HeresTheChord,
+    TryAgain,
+    /// A chord played back right
+    CorrectChord(ChordMatch),
+    /// The right notes, but not voiced the way it was asked for
+    WrongVoicing,
+    NewMelody,
+    HeresTheMelody,
+    /// The first note of a melody that was played wrong, counting from 1
+    WrongMelodyNote {
+        position: usize,
+        expected: PitchClass,
+        played: PitchClass,
+    },
+    /// The right note of a melody in the wrong octave, `octaves` up if positive
+    WrongMelodyOctave {
+        position: usize,
+        note: PitchClass,
+        octaves: i16,
+    },
+    NewProgression,
+    HeresTheProgression,
+    /// The first chord of a progression that was played wrong, counting
+    /// from 1. `played` is None if it wasn't a chord in the key.
+    WrongProgressionChord {
+        position: usize,
+        expected: RomanNumeral,
+        played: Option<RomanNumeral>,
+    },
+}
+
+/// The text for one locale
+trait Catalog: Sync {
+    fn note_name(&self, pitch_class: &PitchClass) -> String;
+
+    fn interval_name(&self, interval: &Interval) -> String;
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String;
+
+    fn message(&self, message: &Message) -> String;
+
+    fn chord(&self, chord: &ChordMatch) -> String;
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String;
+}
+
+/// Names for the accidentals -2 to 2 in a locale, with "" for naturals
+type Accidentals = [&'static str; 5];
+
+/// Do-Re-Mi names in `syllables` (starting from Do) with `accidentals`
+/// appended, e.g. "Si bémol"
+fn solfege(pitch_class: &PitchClass, syllables: &[&str; 7], accidentals: &Accidentals) -> String {
+    let syllable = syllables[pitch_class.letter.index() as usize];
+    let accidental = accidentals[(pitch_class.accidental.clamp(-2, 2) + 2) as usize];
+    format!("{}{}", syllable, accidental)
+}
+
+/// Interval names for languages that put the quality after the number and
+/// agree it in gender, e.g. "tierce mineure" or "unisono giusto"
+struct IntervalNames {
+    /// Unison to double octave, with whether each is feminine
+    numbers: [(&'static str, bool); 15],
+    /// Masculine and feminine forms, in IntervalQuality order
+    qualities: [(&'static str, &'static str); 5],
+    descending: (&'static str, &'static str),
+}
+
+impl IntervalNames {
+    fn name(&self, interval: &Interval) -> String {
+        let (number, feminine) = self.numbers[usize::from(interval.number - 1)];
+        let agree = |(masculine, feminine_form): (&'static str, &'static str)| {
+            if feminine {
+                feminine_form
+            } else {
+                masculine
+            }
+        };
+        let mut name = number.to_string();
+        if !interval.is_unison_or_octave() {
+            name.push(' ');
+            name.push_str(agree(self.qualities[interval.quality as usize]));
+        }
+        if interval.descending {
+            name.push(' ');
+            name.push_str(agree(self.descending));
+        }
+        name
+    }
+}
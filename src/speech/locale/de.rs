@@ -0,0 +1,323 @@
+use crate::music::{
+    ChordMatch, ChordQuality, Direction, Interval, IntervalQuality, Key, Letter, Mistake,
+    PitchClass, RomanNumeral,
+};
+
+use super::{Catalog, Message};
+
+const NUMBER_NAMES: [&str; 15] = [
+    "Prime",
+    "Sekunde",
+    "Terz",
+    "Quarte",
+    "Quinte",
+    "Sexte",
+    "Septime",
+    "Oktave",
+    "None",
+    "Dezime",
+    "Undezime",
+    "Duodezime",
+    "Tredezime",
+    "Quartdezime",
+    "Doppeloktave",
+];
+
+pub struct German;
+
+impl German {
+    /// An interval with its article, as in "du bist einen Ganzton nach oben gegangen"
+    fn step(&self, semitones: u16) -> String {
+        match semitones {
+            1 => "einen Halbton".to_string(),
+            2 => "einen Ganzton".to_string(),
+            6 => "einen Tritonus".to_string(),
+            n => match Interval::from_semitones(n as i16) {
+                Some(interval) => format!("eine {}", self.interval_name(&interval)),
+                None => format!("{} Halbtöne", n),
+            },
+        }
+    }
+
+    fn movement(&self, semitones: i16) -> String {
+        let direction = if semitones < 0 {
+            "nach unten"
+        } else {
+            "nach oben"
+        };
+        format!("{} {}", self.step(semitones.unsigned_abs()), direction)
+    }
+
+    fn quality(&self, quality: ChordQuality) -> &'static str {
+        match quality {
+            ChordQuality::Major => "Dur",
+            ChordQuality::Minor => "Moll",
+            ChordQuality::Diminished => "vermindert",
+            ChordQuality::Augmented => "übermäßig",
+            ChordQuality::Sus2 => "sus zwei",
+            ChordQuality::Sus4 => "sus vier",
+            ChordQuality::Major6 => "Dur mit Sexte",
+            ChordQuality::Minor6 => "Moll mit Sexte",
+            ChordQuality::Dominant7 => "Dominantseptakkord",
+            ChordQuality::Major7 => "Dur mit großer Septime",
+            ChordQuality::Minor7 => "Moll mit kleiner Septime",
+            ChordQuality::MinorMajor7 => "Moll mit großer Septime",
+            ChordQuality::HalfDiminished7 => "halbvermindert",
+            ChordQuality::Diminished7 => "verminderter Septakkord",
+            ChordQuality::Augmented7 => "übermäßig mit kleiner Septime",
+            ChordQuality::AugmentedMajor7 => "übermäßig mit großer Septime",
+            ChordQuality::Dominant7Sus4 => "Septakkord sus vier",
+            ChordQuality::Add9 => "Dur add neun",
+            ChordQuality::MinorAdd9 => "Moll add neun",
+            ChordQuality::Add11 => "Dur add elf",
+            ChordQuality::Add13 => "Dur add dreizehn",
+            ChordQuality::Dominant9 => "Dominantnonenakkord",
+            ChordQuality::Major9 => "Dur mit großer Septime und None",
+            ChordQuality::Minor9 => "Moll mit Septime und None",
+            ChordQuality::Dominant7Flat9 => "Septakkord mit kleiner None",
+            ChordQuality::Dominant7Sharp9 => "Septakkord mit übermäßiger None",
+            ChordQuality::Dominant7Sharp11 => "Septakkord mit übermäßiger Undezime",
+            ChordQuality::Dominant7Flat5 => "Septakkord mit verminderter Quinte",
+            ChordQuality::Dominant7Flat13 => "Septakkord mit kleiner Tredezime",
+        }
+    }
+}
+
+impl Catalog for German {
+    /// H for B, with -is for sharps and -es for flats, e.g. "Fis", "Es" or "B"
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        let name = match (pitch_class.letter, pitch_class.accidental) {
+            (Letter::B, -1) => return "B".to_string(),
+            (Letter::B, -2) => return "Heses".to_string(),
+            (Letter::E, -1) => return "Es".to_string(),
+            (Letter::E, -2) => return "Eses".to_string(),
+            (Letter::A, -1) => return "As".to_string(),
+            (Letter::A, -2) => return "Asas".to_string(),
+            (Letter::B, _) => "H".to_string(),
+            (letter, _) => letter.to_string(),
+        };
+        let suffix = match pitch_class.accidental {
+            -2 => "eses",
+            -1 => "es",
+            1 => "is",
+            2 => "isis",
+            _ => "",
+        };
+        format!("{}{}", name, suffix)
+    }
+
+    /// e.g. "kleine Terz", "übermäßige Quarte" or "fallende Oktave"
+    fn interval_name(&self, interval: &Interval) -> String {
+        let mut name = String::new();
+        if interval.descending {
+            name.push_str("fallende ");
+        }
+        if !interval.is_unison_or_octave() {
+            name.push_str(match interval.quality {
+                IntervalQuality::Diminished => "verminderte ",
+                IntervalQuality::Minor => "kleine ",
+                IntervalQuality::Perfect => "reine ",
+                IntervalQuality::Major => "große ",
+                IntervalQuality::Augmented => "übermäßige ",
+            });
+        }
+        name.push_str(NUMBER_NAMES[usize::from(interval.number - 1)]);
+        name
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let degrees = ["eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben"];
+        let chord = format!(
+            "{} {}",
+            degrees[usize::from(numeral.degree - 1)],
+            self.quality(numeral.quality)
+        );
+        match numeral.of {
+            Some(of) => format!("{} von {}", chord, degrees[usize::from(of - 1)]),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("spiele {} Dur", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!(", {} Oktaven", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!(", beginnend in Oktave {}", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => ", nur aufwärts",
+                    Direction::Descending => ", von oben nur abwärts",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => ", von oben beginnend",
+                });
+                if contrary_motion {
+                    text.push_str(", in Gegenbewegung");
+                }
+                text
+            }
+            Message::ProgramFinished => "du hast das Programm geschafft. gut gemacht!".to_string(),
+            Message::WrongKey => {
+                "Das war eine Durtonleiter, aber in der falschen Tonart.".to_string()
+            }
+            Message::NotQuiteRight => "das war nicht ganz richtig".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!(
+                "du hast {} von {} Oktaven richtig gespielt",
+                completed, sections
+            ),
+            Message::TimesUp => "die Zeit ist um".to_string(),
+            Message::StartingEarTraining => "Gehörbildung beginnt".to_string(),
+            Message::NewChord => "neuer Akkord".to_string(),
+            Message::PerfectMatch => "genau richtig".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("richtiges Intervall, {}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "hier ist der Akkord".to_string(),
+            Message::TryAgain => "nicht ganz, versuch es noch einmal".to_string(),
+            Message::CorrectChord(chord) => format!("richtig, {}", self.chord(&chord)),
+            Message::WrongVoicing => "richtiger Akkord, aber nicht dieselbe Lage".to_string(),
+            Message::NewMelody => "neue Melodie".to_string(),
+            Message::HeresTheMelody => "hier ist die Melodie".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "Ton {} sollte {} sein, nicht {}",
+                position,
+                self.note_name(&expected),
+                self.note_name(&played)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 { "hoch" } else { "tief" };
+                match octaves.abs() {
+                    1 => format!(
+                        "Ton {}, {}, war eine Oktave zu {}",
+                        position,
+                        self.note_name(&note),
+                        height
+                    ),
+                    n => format!(
+                        "Ton {}, {}, war {} Oktaven zu {}",
+                        position,
+                        self.note_name(&note),
+                        n,
+                        height
+                    ),
+                }
+            }
+            Message::NewProgression => "neue Akkordfolge".to_string(),
+            Message::HeresTheProgression => "hier ist die Akkordfolge".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "Akkord {} sollte {} sein, nicht {}",
+                    position,
+                    self.roman_numeral(&expected),
+                    self.roman_numeral(&played)
+                ),
+                None => format!(
+                    "Akkord {} sollte {} sein",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        let text = format!(
+            "{} {}",
+            self.note_name(&chord.root),
+            self.quality(chord.quality)
+        );
+        match chord.inversion {
+            Some(0) => text,
+            Some(1) => format!("{}, erste Umkehrung", text),
+            Some(2) => format!("{}, zweite Umkehrung", text),
+            Some(3) => format!("{}, dritte Umkehrung", text),
+            Some(_) => format!("{}, vierte Umkehrung", text),
+            None => format!("{} über {}", text, self.note_name(&chord.bass)),
+        }
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        let note = |k: u8| self.note_name(&key.spell(k % 12));
+        match *mistake {
+            Mistake::StartedOn { played, expected } => format!(
+                "du hast auf {} statt auf {} angefangen",
+                note(played),
+                note(expected)
+            ),
+            Mistake::Repeated { note: from, wanted } => format!(
+                "du hast {} wiederholt, statt {} zu gehen",
+                note(from),
+                self.movement(wanted)
+            ),
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "du bist von {} {} gegangen statt {}",
+                note(from),
+                self.movement(went),
+                self.step(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "du bist von {} {} gegangen statt {}",
+                note(from),
+                self.movement(went),
+                self.movement(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 { "hoch" } else { "tief" };
+                match octaves.abs() {
+                    1 => format!("du hast {} eine Oktave zu {} gespielt", note(k), height),
+                    n => format!("du hast {} {} Oktaven zu {} gespielt", note(k), n, height),
+                }
+            }
+            Mistake::LeftOutFirst { note: k } => {
+                format!("du hast den ersten Ton ausgelassen, {}", note(k))
+            }
+            Mistake::LeftOut { note: k, after } => {
+                format!("du hast {} nach {} ausgelassen", note(k), note(after))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!(
+                    "du hast vor dem Anfang ein zusätzliches {} gespielt",
+                    note(k)
+                )
+            }
+            Mistake::Extra { note: k, after } => format!(
+                "du hast nach {} ein zusätzliches {} gespielt",
+                note(after),
+                note(k)
+            ),
+            Mistake::Swapped { first, second } => {
+                format!("du hast {} vor {} gespielt", note(second), note(first))
+            }
+            Mistake::NothingPlayed => "du hast nichts gespielt".to_string(),
+            Mistake::Stopped { after, short } => format!(
+                "du hast nach {} aufgehört, {} Töne zu früh",
+                note(after),
+                short
+            ),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use crate::music::{ChordMatch, Direction, Interval, Key, Mistake, PitchClass, RomanNumeral};
+
+use super::{Catalog, Message};
+
+pub struct English;
+
+impl Catalog for English {
+    /// Spells out accidentals so TTS doesn't read "Bb" as "bee bee"
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        let accidental = match pitch_class.accidental {
+            -2 => " Double Flat",
+            -1 => " Flat",
+            1 => " Sharp",
+            2 => " Double Sharp",
+            _ => "",
+        };
+        format!("{}{}", pitch_class.letter, accidental)
+    }
+
+    fn interval_name(&self, interval: &Interval) -> String {
+        interval.to_string()
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let degrees = ["one", "two", "three", "four", "five", "six", "seven"];
+        let chord = format!(
+            "{} {}",
+            degrees[usize::from(numeral.degree - 1)],
+            numeral.quality.name()
+        );
+        match numeral.of {
+            Some(of) => format!("{} of {}", chord, degrees[usize::from(of - 1)]),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("play {} mayjur", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!(", {} octaves", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!(", starting in octave {}", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => ", going up only",
+                    Direction::Descending => ", from the top going down only",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => ", starting from the top",
+                });
+                if contrary_motion {
+                    text.push_str(", in contrary motion");
+                }
+                text
+            }
+            Message::ProgramFinished => "you've finished the program. good job!".to_string(),
+            Message::WrongKey => "You've played a major scale but in the wrong key.".to_string(),
+            Message::NotQuiteRight => "that wasn't quite right".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!("you got {} of {} octaves right", completed, sections),
+            Message::TimesUp => "time's up".to_string(),
+            Message::StartingEarTraining => "starting ear training".to_string(),
+            Message::NewChord => "new chord".to_string(),
+            Message::PerfectMatch => "perfect match".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("correct interval, {}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "here's the chord".to_string(),
+            Message::TryAgain => "not quite, try again".to_string(),
+            Message::CorrectChord(chord) => format!("correct, {}", self.chord(&chord)),
+            Message::WrongVoicing => "right chord, but not the same voicing".to_string(),
+            Message::NewMelody => "new melody".to_string(),
+            Message::HeresTheMelody => "here's the melody".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "note {} should be {}, not {}",
+                position,
+                self.note_name(&expected),
+                self.note_name(&played)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 { "high" } else { "low" };
+                match octaves.abs() {
+                    1 => format!(
+                        "note {} was {} an octave too {}",
+                        position,
+                        self.note_name(&note),
+                        height
+                    ),
+                    n => format!(
+                        "note {} was {} {} octaves too {}",
+                        position,
+                        self.note_name(&note),
+                        n,
+                        height
+                    ),
+                }
+            }
+            Message::NewProgression => "new progression".to_string(),
+            Message::HeresTheProgression => "here's the progression".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "chord {} should be {}, not {}",
+                    position,
+                    self.roman_numeral(&expected),
+                    self.roman_numeral(&played)
+                ),
+                None => format!(
+                    "chord {} should be {}",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        chord.describe_with(|pitch_class| self.note_name(pitch_class))
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        mistake.describe_with(key, |pitch_class| self.note_name(pitch_class))
+    }
+}
@@ -0,0 +1,304 @@
+use crate::music::{
+    ChordMatch, ChordQuality, Direction, Interval, Key, Mistake, PitchClass, RomanNumeral,
+};
+
+use super::{solfege, Accidentals, Catalog, IntervalNames, Message};
+
+const SYLLABLES: [&str; 7] = ["Do", "Re", "Mi", "Fa", "Sol", "La", "Si"];
+
+const ACCIDENTALS: Accidentals = [
+    " doble bemol",
+    " bemol",
+    "",
+    " sostenido",
+    " doble sostenido",
+];
+
+const INTERVALS: IntervalNames = IntervalNames {
+    numbers: [
+        ("unísono", false),
+        ("segunda", true),
+        ("tercera", true),
+        ("cuarta", true),
+        ("quinta", true),
+        ("sexta", true),
+        ("séptima", true),
+        ("octava", true),
+        ("novena", true),
+        ("décima", true),
+        ("undécima", true),
+        ("duodécima", true),
+        ("decimotercera", true),
+        ("decimocuarta", true),
+        ("doble octava", true),
+    ],
+    qualities: [
+        ("disminuido", "disminuida"),
+        ("menor", "menor"),
+        ("justo", "justa"),
+        ("mayor", "mayor"),
+        ("aumentado", "aumentada"),
+    ],
+    descending: ("descendente", "descendente"),
+};
+
+pub struct Spanish;
+
+impl Spanish {
+    /// How far a move went, as in "subiste un tono"
+    fn step(&self, semitones: u16) -> String {
+        match semitones {
+            1 => "un semitono".to_string(),
+            2 => "un tono".to_string(),
+            3 => "una tercera menor".to_string(),
+            4 => "una tercera mayor".to_string(),
+            5 => "una cuarta".to_string(),
+            6 => "un tritono".to_string(),
+            7 => "una quinta".to_string(),
+            8 => "una sexta menor".to_string(),
+            9 => "una sexta mayor".to_string(),
+            10 => "una séptima menor".to_string(),
+            11 => "una séptima mayor".to_string(),
+            12 => "una octava".to_string(),
+            n => format!("{} semitonos", n),
+        }
+    }
+
+    fn went(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "bajaste" } else { "subiste" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn to_go(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "bajar" } else { "subir" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn quality(&self, quality: ChordQuality) -> &'static str {
+        match quality {
+            ChordQuality::Major => "mayor",
+            ChordQuality::Minor => "menor",
+            ChordQuality::Diminished => "disminuido",
+            ChordQuality::Augmented => "aumentado",
+            ChordQuality::Sus2 => "sus dos",
+            ChordQuality::Sus4 => "sus cuatro",
+            ChordQuality::Major6 => "mayor con sexta",
+            ChordQuality::Minor6 => "menor con sexta",
+            ChordQuality::Dominant7 => "séptima de dominante",
+            ChordQuality::Major7 => "mayor con séptima mayor",
+            ChordQuality::Minor7 => "menor con séptima",
+            ChordQuality::MinorMajor7 => "menor con séptima mayor",
+            ChordQuality::HalfDiminished7 => "semidisminuido",
+            ChordQuality::Diminished7 => "séptima disminuida",
+            ChordQuality::Augmented7 => "aumentado con séptima",
+            ChordQuality::AugmentedMajor7 => "aumentado con séptima mayor",
+            ChordQuality::Dominant7Sus4 => "séptima sus cuatro",
+            ChordQuality::Add9 => "mayor add nueve",
+            ChordQuality::MinorAdd9 => "menor add nueve",
+            ChordQuality::Add11 => "mayor add once",
+            ChordQuality::Add13 => "mayor add trece",
+            ChordQuality::Dominant9 => "novena de dominante",
+            ChordQuality::Major9 => "mayor con novena",
+            ChordQuality::Minor9 => "menor con novena",
+            ChordQuality::Dominant7Flat9 => "séptima con novena bemol",
+            ChordQuality::Dominant7Sharp9 => "séptima con novena sostenida",
+            ChordQuality::Dominant7Sharp11 => "séptima con oncena sostenida",
+            ChordQuality::Dominant7Flat5 => "séptima con quinta bemol",
+            ChordQuality::Dominant7Flat13 => "séptima con trecena bemol",
+        }
+    }
+}
+
+impl Catalog for Spanish {
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        solfege(pitch_class, &SYLLABLES, &ACCIDENTALS)
+    }
+
+    fn interval_name(&self, interval: &Interval) -> String {
+        INTERVALS.name(interval)
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let degrees = ["uno", "dos", "tres", "cuatro", "cinco", "seis", "siete"];
+        let chord = format!(
+            "{} {}",
+            degrees[usize::from(numeral.degree - 1)],
+            self.quality(numeral.quality)
+        );
+        match numeral.of {
+            Some(of) => format!("{} de {}", chord, degrees[usize::from(of - 1)]),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("toca la escala de {} mayor", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!(", {} octavas", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!(", empezando en la octava {}", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => ", solo subiendo",
+                    Direction::Descending => ", desde arriba solo bajando",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => ", empezando desde arriba",
+                });
+                if contrary_motion {
+                    text.push_str(", en movimiento contrario");
+                }
+                text
+            }
+            Message::ProgramFinished => "has terminado el programa. ¡buen trabajo!".to_string(),
+            Message::WrongKey => {
+                "Has tocado una escala mayor, pero en la tonalidad equivocada.".to_string()
+            }
+            Message::NotQuiteRight => "no estuvo del todo bien".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!("octavas correctas: {} de {}", completed, sections),
+            Message::TimesUp => "se acabó el tiempo".to_string(),
+            Message::StartingEarTraining => "empieza el entrenamiento auditivo".to_string(),
+            Message::NewChord => "nuevo acorde".to_string(),
+            Message::PerfectMatch => "perfecto".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("intervalo correcto, {}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "aquí está el acorde".to_string(),
+            Message::TryAgain => "no del todo, inténtalo de nuevo".to_string(),
+            Message::CorrectChord(chord) => format!("correcto, {}", self.chord(&chord)),
+            Message::WrongVoicing => "acorde correcto, pero no la misma disposición".to_string(),
+            Message::NewMelody => "nueva melodía".to_string(),
+            Message::HeresTheMelody => "aquí está la melodía".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "la nota {} debía ser {}, no {}",
+                position,
+                self.note_name(&expected),
+                self.note_name(&played)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 { "alta" } else { "baja" };
+                match octaves.abs() {
+                    1 => format!(
+                        "la nota {}, {}, estaba una octava demasiado {}",
+                        position,
+                        self.note_name(&note),
+                        height
+                    ),
+                    n => format!(
+                        "la nota {}, {}, estaba {} octavas demasiado {}",
+                        position,
+                        self.note_name(&note),
+                        n,
+                        height
+                    ),
+                }
+            }
+            Message::NewProgression => "nueva progresión".to_string(),
+            Message::HeresTheProgression => "aquí está la progresión".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "el acorde {} debía ser {}, no {}",
+                    position,
+                    self.roman_numeral(&expected),
+                    self.roman_numeral(&played)
+                ),
+                None => format!(
+                    "el acorde {} debía ser {}",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        let text = format!(
+            "{} {}",
+            self.note_name(&chord.root),
+            self.quality(chord.quality)
+        );
+        match chord.inversion {
+            Some(0) => text,
+            Some(1) => format!("{}, primera inversión", text),
+            Some(2) => format!("{}, segunda inversión", text),
+            Some(3) => format!("{}, tercera inversión", text),
+            Some(_) => format!("{}, cuarta inversión", text),
+            None => format!("{} con bajo en {}", text, self.note_name(&chord.bass)),
+        }
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        let note = |k: u8| self.note_name(&key.spell(k % 12));
+        match *mistake {
+            Mistake::StartedOn { played, expected } => {
+                format!("empezaste en {} en vez de {}", note(played), note(expected))
+            }
+            Mistake::Repeated { note: from, wanted } => {
+                format!("repetiste {} en vez de {}", note(from), self.to_go(wanted))
+            }
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "desde {} {} en vez de {}",
+                note(from),
+                self.went(went),
+                self.step(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "desde {} {} en vez de {}",
+                note(from),
+                self.went(went),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 { "alto" } else { "bajo" };
+                match octaves.abs() {
+                    1 => format!("tocaste {} una octava demasiado {}", note(k), height),
+                    n => format!("tocaste {} {} octavas demasiado {}", note(k), n, height),
+                }
+            }
+            Mistake::LeftOutFirst { note: k } => {
+                format!("te saltaste la primera nota, {}", note(k))
+            }
+            Mistake::LeftOut { note: k, after } => {
+                format!("te saltaste {} después de {}", note(k), note(after))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!("tocaste un {} de más antes de empezar", note(k))
+            }
+            Mistake::Extra { note: k, after } => {
+                format!("tocaste un {} de más después de {}", note(k), note(after))
+            }
+            Mistake::Swapped { first, second } => {
+                format!("tocaste {} antes de {}", note(second), note(first))
+            }
+            Mistake::NothingPlayed => "no tocaste nada".to_string(),
+            Mistake::Stopped { after, short } => format!(
+                "paraste después de {}, faltaban {} notas",
+                note(after),
+                short
+            ),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::music::{
+    ChordMatch, ChordQuality, Direction, Interval, Key, Mistake, PitchClass, RomanNumeral,
+};
+
+use super::{solfege, Accidentals, Catalog, IntervalNames, Message};
+
+const SYLLABLES: [&str; 7] = ["Do", "Ré", "Mi", "Fa", "Sol", "La", "Si"];
+
+const ACCIDENTALS: Accidentals = [" double bémol", " bémol", "", " dièse", " double dièse"];
+
+const INTERVALS: IntervalNames = IntervalNames {
+    numbers: [
+        ("unisson", false),
+        ("seconde", true),
+        ("tierce", true),
+        ("quarte", true),
+        ("quinte", true),
+        ("sixte", true),
+        ("septième", true),
+        ("octave", true),
+        ("neuvième", true),
+        ("dixième", true),
+        ("onzième", true),
+        ("douzième", true),
+        ("treizième", true),
+        ("quatorzième", true),
+        ("double octave", true),
+    ],
+    qualities: [
+        ("diminué", "diminuée"),
+        ("mineur", "mineure"),
+        ("juste", "juste"),
+        ("majeur", "majeure"),
+        ("augmenté", "augmentée"),
+    ],
+    descending: ("descendant", "descendante"),
+};
+
+pub struct French;
+
+impl French {
+    /// How far a move went, as in "tu es monté d'un ton"
+    fn step(&self, semitones: u16) -> String {
+        match semitones {
+            1 => "d'un demi-ton".to_string(),
+            2 => "d'un ton".to_string(),
+            3 => "d'une tierce mineure".to_string(),
+            4 => "d'une tierce majeure".to_string(),
+            5 => "d'une quarte".to_string(),
+            6 => "d'un triton".to_string(),
+            7 => "d'une quinte".to_string(),
+            8 => "d'une sixte mineure".to_string(),
+            9 => "d'une sixte majeure".to_string(),
+            10 => "d'une septième mineure".to_string(),
+            11 => "d'une septième majeure".to_string(),
+            12 => "d'une octave".to_string(),
+            n => format!("de {} demi-tons", n),
+        }
+    }
+
+    fn went(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "descendu" } else { "monté" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn to_go(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "descendre" } else { "monter" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn quality(&self, quality: ChordQuality) -> &'static str {
+        match quality {
+            ChordQuality::Major => "majeur",
+            ChordQuality::Minor => "mineur",
+            ChordQuality::Diminished => "diminué",
+            ChordQuality::Augmented => "augmenté",
+            ChordQuality::Sus2 => "sus deux",
+            ChordQuality::Sus4 => "sus quatre",
+            ChordQuality::Major6 => "majeur sixte",
+            ChordQuality::Minor6 => "mineur sixte",
+            ChordQuality::Dominant7 => "septième de dominante",
+            ChordQuality::Major7 => "septième majeure",
+            ChordQuality::Minor7 => "mineur septième",
+            ChordQuality::MinorMajor7 => "mineur septième majeure",
+            ChordQuality::HalfDiminished7 => "demi-diminué",
+            ChordQuality::Diminished7 => "septième diminuée",
+            ChordQuality::Augmented7 => "augmenté septième",
+            ChordQuality::AugmentedMajor7 => "augmenté septième majeure",
+            ChordQuality::Dominant7Sus4 => "septième sus quatre",
+            ChordQuality::Add9 => "majeur add neuf",
+            ChordQuality::MinorAdd9 => "mineur add neuf",
+            ChordQuality::Add11 => "majeur add onze",
+            ChordQuality::Add13 => "majeur add treize",
+            ChordQuality::Dominant9 => "neuvième de dominante",
+            ChordQuality::Major9 => "neuvième majeure",
+            ChordQuality::Minor9 => "mineur neuvième",
+            ChordQuality::Dominant7Flat9 => "septième neuvième bémol",
+            ChordQuality::Dominant7Sharp9 => "septième neuvième dièse",
+            ChordQuality::Dominant7Sharp11 => "septième onzième dièse",
+            ChordQuality::Dominant7Flat5 => "septième quinte bémol",
+            ChordQuality::Dominant7Flat13 => "septième treizième bémol",
+        }
+    }
+}
+
+impl Catalog for French {
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        solfege(pitch_class, &SYLLABLES, &ACCIDENTALS)
+    }
+
+    fn interval_name(&self, interval: &Interval) -> String {
+        INTERVALS.name(interval)
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let degrees = ["un", "deux", "trois", "quatre", "cinq", "six", "sept"];
+        let chord = format!(
+            "{} {}",
+            degrees[usize::from(numeral.degree - 1)],
+            self.quality(numeral.quality)
+        );
+        match numeral.of {
+            Some(of) => format!("{} de {}", chord, degrees[usize::from(of - 1)]),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("joue la gamme de {} majeur", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!(", sur {} octaves", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!(", en commençant à l'octave {}", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => ", en montant seulement",
+                    Direction::Descending => ", depuis le haut en descendant seulement",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => ", en commençant par le haut",
+                });
+                if contrary_motion {
+                    text.push_str(", en mouvement contraire");
+                }
+                text
+            }
+            Message::ProgramFinished => "tu as terminé le programme. bravo !".to_string(),
+            Message::WrongKey => {
+                "Tu as joué une gamme majeure, mais dans la mauvaise tonalité.".to_string()
+            }
+            Message::NotQuiteRight => "ce n'était pas tout à fait juste".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!("octaves réussies : {} sur {}", completed, sections),
+            Message::TimesUp => "le temps est écoulé".to_string(),
+            Message::StartingEarTraining => "début de l'entraînement de l'oreille".to_string(),
+            Message::NewChord => "nouvel accord".to_string(),
+            Message::PerfectMatch => "parfait".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("bon intervalle, {}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "voici l'accord".to_string(),
+            Message::TryAgain => "pas tout à fait, réessaie".to_string(),
+            Message::CorrectChord(chord) => format!("exact, {}", self.chord(&chord)),
+            Message::WrongVoicing => "bon accord, mais pas la même disposition".to_string(),
+            Message::NewMelody => "nouvelle mélodie".to_string(),
+            Message::HeresTheMelody => "voici la mélodie".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "la note {} devait être {}, pas {}",
+                position,
+                self.note_name(&expected),
+                self.note_name(&played)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 { "haut" } else { "bas" };
+                match octaves.abs() {
+                    1 => format!(
+                        "la note {}, {}, était une octave trop {}",
+                        position,
+                        self.note_name(&note),
+                        height
+                    ),
+                    n => format!(
+                        "la note {}, {}, était {} octaves trop {}",
+                        position,
+                        self.note_name(&note),
+                        n,
+                        height
+                    ),
+                }
+            }
+            Message::NewProgression => "nouvelle progression".to_string(),
+            Message::HeresTheProgression => "voici la progression".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "l'accord {} devait être {}, pas {}",
+                    position,
+                    self.roman_numeral(&expected),
+                    self.roman_numeral(&played)
+                ),
+                None => format!(
+                    "l'accord {} devait être {}",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        let text = format!(
+            "{} {}",
+            self.note_name(&chord.root),
+            self.quality(chord.quality)
+        );
+        match chord.inversion {
+            Some(0) => text,
+            Some(1) => format!("{}, premier renversement", text),
+            Some(2) => format!("{}, deuxième renversement", text),
+            Some(3) => format!("{}, troisième renversement", text),
+            Some(_) => format!("{}, quatrième renversement", text),
+            None => format!("{} sur {}", text, self.note_name(&chord.bass)),
+        }
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        let note = |k: u8| self.note_name(&key.spell(k % 12));
+        match *mistake {
+            Mistake::StartedOn { played, expected } => format!(
+                "tu as commencé sur {} au lieu de {}",
+                note(played),
+                note(expected)
+            ),
+            Mistake::Repeated { note: from, wanted } => format!(
+                "tu as répété {} au lieu de {}",
+                note(from),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "depuis {} tu es {} au lieu {}",
+                note(from),
+                self.went(went),
+                self.step(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "depuis {} tu es {} au lieu de {}",
+                note(from),
+                self.went(went),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 { "haut" } else { "bas" };
+                match octaves.abs() {
+                    1 => format!("tu as joué {} une octave trop {}", note(k), height),
+                    n => format!("tu as joué {} {} octaves trop {}", note(k), n, height),
+                }
+            }
+            Mistake::LeftOutFirst { note: k } => {
+                format!("tu as oublié la première note, {}", note(k))
+            }
+            Mistake::LeftOut { note: k, after } => {
+                format!("tu as oublié {} après {}", note(k), note(after))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!("tu as joué un {} de trop avant de commencer", note(k))
+            }
+            Mistake::Extra { note: k, after } => {
+                format!("tu as joué un {} de trop après {}", note(k), note(after))
+            }
+            Mistake::Swapped { first, second } => {
+                format!("tu as joué {} avant {}", note(second), note(first))
+            }
+            Mistake::NothingPlayed => "tu n'as rien joué".to_string(),
+            Mistake::Stopped { after, short } => format!(
+                "tu t'es arrêté après {}, il manquait {} notes",
+                note(after),
+                short
+            ),
+        }
+    }
+}
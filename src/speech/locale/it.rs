@@ -0,0 +1,308 @@
+use crate::music::{
+    ChordMatch, ChordQuality, Direction, Interval, Key, Mistake, PitchClass, RomanNumeral,
+};
+
+use super::{solfege, Accidentals, Catalog, IntervalNames, Message};
+
+const SYLLABLES: [&str; 7] = ["Do", "Re", "Mi", "Fa", "Sol", "La", "Si"];
+
+const ACCIDENTALS: Accidentals = [
+    " doppio bemolle",
+    " bemolle",
+    "",
+    " diesis",
+    " doppio diesis",
+];
+
+const INTERVALS: IntervalNames = IntervalNames {
+    numbers: [
+        ("unisono", false),
+        ("seconda", true),
+        ("terza", true),
+        ("quarta", true),
+        ("quinta", true),
+        ("sesta", true),
+        ("settima", true),
+        ("ottava", true),
+        ("nona", true),
+        ("decima", true),
+        ("undicesima", true),
+        ("dodicesima", true),
+        ("tredicesima", true),
+        ("quattordicesima", true),
+        ("doppia ottava", true),
+    ],
+    qualities: [
+        ("diminuito", "diminuita"),
+        ("minore", "minore"),
+        ("giusto", "giusta"),
+        ("maggiore", "maggiore"),
+        ("eccedente", "eccedente"),
+    ],
+    descending: ("discendente", "discendente"),
+};
+
+pub struct Italian;
+
+impl Italian {
+    /// How far a move went, as in "sei salito di un tono"
+    fn step(&self, semitones: u16) -> String {
+        match semitones {
+            1 => "di un semitono".to_string(),
+            2 => "di un tono".to_string(),
+            3 => "di una terza minore".to_string(),
+            4 => "di una terza maggiore".to_string(),
+            5 => "di una quarta".to_string(),
+            6 => "di un tritono".to_string(),
+            7 => "di una quinta".to_string(),
+            8 => "di una sesta minore".to_string(),
+            9 => "di una sesta maggiore".to_string(),
+            10 => "di una settima minore".to_string(),
+            11 => "di una settima maggiore".to_string(),
+            12 => "di un'ottava".to_string(),
+            n => format!("di {} semitoni", n),
+        }
+    }
+
+    fn went(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "sceso" } else { "salito" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn to_go(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 { "scendere" } else { "salire" };
+        format!("{} {}", verb, self.step(semitones.unsigned_abs()))
+    }
+
+    fn quality(&self, quality: ChordQuality) -> &'static str {
+        match quality {
+            ChordQuality::Major => "maggiore",
+            ChordQuality::Minor => "minore",
+            ChordQuality::Diminished => "diminuito",
+            ChordQuality::Augmented => "aumentato",
+            ChordQuality::Sus2 => "sus due",
+            ChordQuality::Sus4 => "sus quattro",
+            ChordQuality::Major6 => "maggiore con sesta",
+            ChordQuality::Minor6 => "minore con sesta",
+            ChordQuality::Dominant7 => "settima di dominante",
+            ChordQuality::Major7 => "settima maggiore",
+            ChordQuality::Minor7 => "minore settima",
+            ChordQuality::MinorMajor7 => "minore con settima maggiore",
+            ChordQuality::HalfDiminished7 => "semidiminuito",
+            ChordQuality::Diminished7 => "settima diminuita",
+            ChordQuality::Augmented7 => "aumentato con settima",
+            ChordQuality::AugmentedMajor7 => "aumentato con settima maggiore",
+            ChordQuality::Dominant7Sus4 => "settima sus quattro",
+            ChordQuality::Add9 => "maggiore add nove",
+            ChordQuality::MinorAdd9 => "minore add nove",
+            ChordQuality::Add11 => "maggiore add undici",
+            ChordQuality::Add13 => "maggiore add tredici",
+            ChordQuality::Dominant9 => "nona di dominante",
+            ChordQuality::Major9 => "nona maggiore",
+            ChordQuality::Minor9 => "minore nona",
+            ChordQuality::Dominant7Flat9 => "settima nona bemolle",
+            ChordQuality::Dominant7Sharp9 => "settima nona diesis",
+            ChordQuality::Dominant7Sharp11 => "settima undicesima diesis",
+            ChordQuality::Dominant7Flat5 => "settima quinta bemolle",
+            ChordQuality::Dominant7Flat13 => "settima tredicesima bemolle",
+        }
+    }
+}
+
+impl Catalog for Italian {
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        solfege(pitch_class, &SYLLABLES, &ACCIDENTALS)
+    }
+
+    fn interval_name(&self, interval: &Interval) -> String {
+        INTERVALS.name(interval)
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let degrees = ["uno", "due", "tre", "quattro", "cinque", "sei", "sette"];
+        let chord = format!(
+            "{} {}",
+            degrees[usize::from(numeral.degree - 1)],
+            self.quality(numeral.quality)
+        );
+        match numeral.of {
+            Some(of) => format!("{} di {}", chord, degrees[usize::from(of - 1)]),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("suona la scala di {} maggiore", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!(", su {} ottave", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!(", partendo dall'ottava {}", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => ", solo salendo",
+                    Direction::Descending => ", dall'alto solo scendendo",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => ", partendo dall'alto",
+                });
+                if contrary_motion {
+                    text.push_str(", per moto contrario");
+                }
+                text
+            }
+            Message::ProgramFinished => "hai finito il programma. bravo!".to_string(),
+            Message::WrongKey => {
+                "Hai suonato una scala maggiore, ma nella tonalità sbagliata.".to_string()
+            }
+            Message::NotQuiteRight => "non era proprio giusto".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!("ottave giuste: {} su {}", completed, sections),
+            Message::TimesUp => "tempo scaduto".to_string(),
+            Message::StartingEarTraining => "inizia l'allenamento dell'orecchio".to_string(),
+            Message::NewChord => "nuovo accordo".to_string(),
+            Message::PerfectMatch => "perfetto".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("intervallo giusto, {}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "ecco l'accordo".to_string(),
+            Message::TryAgain => "non proprio, riprova".to_string(),
+            Message::CorrectChord(chord) => format!("giusto, {}", self.chord(&chord)),
+            Message::WrongVoicing => "accordo giusto, ma non la stessa disposizione".to_string(),
+            Message::NewMelody => "nuova melodia".to_string(),
+            Message::HeresTheMelody => "ecco la melodia".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "la nota {} doveva essere {}, non {}",
+                position,
+                self.note_name(&expected),
+                self.note_name(&played)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 { "alta" } else { "bassa" };
+                match octaves.abs() {
+                    1 => format!(
+                        "la nota {}, {}, era un'ottava troppo {}",
+                        position,
+                        self.note_name(&note),
+                        height
+                    ),
+                    n => format!(
+                        "la nota {}, {}, era {} ottave troppo {}",
+                        position,
+                        self.note_name(&note),
+                        n,
+                        height
+                    ),
+                }
+            }
+            Message::NewProgression => "nuova progressione".to_string(),
+            Message::HeresTheProgression => "ecco la progressione".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "l'accordo {} doveva essere {}, non {}",
+                    position,
+                    self.roman_numeral(&expected),
+                    self.roman_numeral(&played)
+                ),
+                None => format!(
+                    "l'accordo {} doveva essere {}",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        let text = format!(
+            "{} {}",
+            self.note_name(&chord.root),
+            self.quality(chord.quality)
+        );
+        match chord.inversion {
+            Some(0) => text,
+            Some(1) => format!("{}, primo rivolto", text),
+            Some(2) => format!("{}, secondo rivolto", text),
+            Some(3) => format!("{}, terzo rivolto", text),
+            Some(_) => format!("{}, quarto rivolto", text),
+            None => format!("{} con basso {}", text, self.note_name(&chord.bass)),
+        }
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        let note = |k: u8| self.note_name(&key.spell(k % 12));
+        match *mistake {
+            Mistake::StartedOn { played, expected } => format!(
+                "hai cominciato da {} invece che da {}",
+                note(played),
+                note(expected)
+            ),
+            Mistake::Repeated { note: from, wanted } => format!(
+                "hai ripetuto {} invece di {}",
+                note(from),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "da {} sei {} invece che {}",
+                note(from),
+                self.went(went),
+                self.step(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "da {} sei {} invece di {}",
+                note(from),
+                self.went(went),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 { "alto" } else { "basso" };
+                match octaves.abs() {
+                    1 => format!("hai suonato {} un'ottava troppo {}", note(k), height),
+                    n => format!("hai suonato {} {} ottave troppo {}", note(k), n, height),
+                }
+            }
+            Mistake::LeftOutFirst { note: k } => {
+                format!("hai saltato la prima nota, {}", note(k))
+            }
+            Mistake::LeftOut { note: k, after } => {
+                format!("hai saltato {} dopo {}", note(k), note(after))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!("hai suonato un {} in più prima di cominciare", note(k))
+            }
+            Mistake::Extra { note: k, after } => {
+                format!("hai suonato un {} in più dopo {}", note(k), note(after))
+            }
+            Mistake::Swapped { first, second } => {
+                format!("hai suonato {} prima di {}", note(second), note(first))
+            }
+            Mistake::NothingPlayed => "non hai suonato niente".to_string(),
+            Mistake::Stopped { after, short } => format!(
+                "ti sei fermato dopo {}, mancavano {} note",
+                note(after),
+                short
+            ),
+        }
+    }
+}
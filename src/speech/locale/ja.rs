@@ -0,0 +1,284 @@
+use crate::music::{
+    ChordMatch, ChordQuality, Direction, Interval, IntervalQuality, Key, Mistake, PitchClass,
+    RomanNumeral,
+};
+
+use super::{solfege, Accidentals, Catalog, Message};
+
+const SYLLABLES: [&str; 7] = ["ド", "レ", "ミ", "ファ", "ソ", "ラ", "シ"];
+
+const ACCIDENTALS: Accidentals = [
+    "ダブルフラット",
+    "フラット",
+    "",
+    "シャープ",
+    "ダブルシャープ",
+];
+
+const NUMBER_NAMES: [&str; 15] = [
+    "一", "二", "三", "四", "五", "六", "七", "八", "九", "十", "十一", "十二", "十三", "十四",
+    "十五",
+];
+
+pub struct Japanese;
+
+impl Japanese {
+    fn step(&self, semitones: u16) -> String {
+        match semitones {
+            1 => "半音".to_string(),
+            2 => "全音".to_string(),
+            n => match Interval::from_semitones(n as i16) {
+                Some(interval) => self.interval_name(&interval),
+                None => format!("半音{}個", n),
+            },
+        }
+    }
+
+    fn went(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 {
+            "下がりました"
+        } else {
+            "上がりました"
+        };
+        format!("{}{}", self.step(semitones.unsigned_abs()), verb)
+    }
+
+    fn to_go(&self, semitones: i16) -> String {
+        let verb = if semitones < 0 {
+            "下がる"
+        } else {
+            "上がる"
+        };
+        format!("{}{}", self.step(semitones.unsigned_abs()), verb)
+    }
+
+    fn quality(&self, quality: ChordQuality) -> &'static str {
+        match quality {
+            ChordQuality::Major => "メジャー",
+            ChordQuality::Minor => "マイナー",
+            ChordQuality::Diminished => "ディミニッシュ",
+            ChordQuality::Augmented => "オーギュメント",
+            ChordQuality::Sus2 => "サスツー",
+            ChordQuality::Sus4 => "サスフォー",
+            ChordQuality::Major6 => "シックス",
+            ChordQuality::Minor6 => "マイナーシックス",
+            ChordQuality::Dominant7 => "セブンス",
+            ChordQuality::Major7 => "メジャーセブンス",
+            ChordQuality::Minor7 => "マイナーセブンス",
+            ChordQuality::MinorMajor7 => "マイナーメジャーセブンス",
+            ChordQuality::HalfDiminished7 => "マイナーセブンスフラットファイブ",
+            ChordQuality::Diminished7 => "ディミニッシュセブンス",
+            ChordQuality::Augmented7 => "オーギュメントセブンス",
+            ChordQuality::AugmentedMajor7 => "オーギュメントメジャーセブンス",
+            ChordQuality::Dominant7Sus4 => "セブンスサスフォー",
+            ChordQuality::Add9 => "アドナインス",
+            ChordQuality::MinorAdd9 => "マイナーアドナインス",
+            ChordQuality::Add11 => "アドイレブンス",
+            ChordQuality::Add13 => "アドサーティーンス",
+            ChordQuality::Dominant9 => "ナインス",
+            ChordQuality::Major9 => "メジャーナインス",
+            ChordQuality::Minor9 => "マイナーナインス",
+            ChordQuality::Dominant7Flat9 => "セブンスフラットナインス",
+            ChordQuality::Dominant7Sharp9 => "セブンスシャープナインス",
+            ChordQuality::Dominant7Sharp11 => "セブンスシャープイレブンス",
+            ChordQuality::Dominant7Flat5 => "セブンスフラットファイブ",
+            ChordQuality::Dominant7Flat13 => "セブンスフラットサーティーンス",
+        }
+    }
+}
+
+impl Catalog for Japanese {
+    fn note_name(&self, pitch_class: &PitchClass) -> String {
+        solfege(pitch_class, &SYLLABLES, &ACCIDENTALS)
+    }
+
+    /// e.g. "短三度", "増四度" or "下行完全五度"
+    fn interval_name(&self, interval: &Interval) -> String {
+        let direction = if interval.descending { "下行" } else { "" };
+        let quality = match interval.quality {
+            IntervalQuality::Diminished => "減",
+            IntervalQuality::Minor => "短",
+            IntervalQuality::Perfect => "完全",
+            IntervalQuality::Major => "長",
+            IntervalQuality::Augmented => "増",
+        };
+        format!(
+            "{}{}{}度",
+            direction,
+            quality,
+            NUMBER_NAMES[usize::from(interval.number - 1)]
+        )
+    }
+
+    fn roman_numeral(&self, numeral: &RomanNumeral) -> String {
+        let chord = format!("{}度{}", numeral.degree, self.quality(numeral.quality));
+        match numeral.of {
+            Some(of) => format!("{}度の{}", of, chord),
+            None => chord,
+        }
+    }
+
+    fn message(&self, message: &Message) -> String {
+        match *message {
+            Message::PlayMajorScale {
+                root,
+                octaves,
+                start_octave,
+                direction,
+                contrary_motion,
+            } => {
+                let mut text = format!("{}メジャースケールを弾いてください", self.note_name(&root));
+                if octaves > 1 {
+                    text.push_str(&format!("、{}オクターブ", octaves));
+                }
+                if let Some(octave) = start_octave {
+                    text.push_str(&format!("、オクターブ{}から", octave));
+                }
+                text.push_str(match direction {
+                    Direction::Ascending => "、上行のみ",
+                    Direction::Descending => "、上から下行のみ",
+                    Direction::UpAndDown => "",
+                    Direction::DownAndUp => "、上から",
+                });
+                if contrary_motion {
+                    text.push_str("、反進行で");
+                }
+                text
+            }
+            Message::ProgramFinished => "プログラム終了です。よくできました！".to_string(),
+            Message::WrongKey => "メジャースケールですが、調が違います。".to_string(),
+            Message::NotQuiteRight => "少し違いました".to_string(),
+            Message::PartlyRight {
+                completed,
+                sections,
+            } => format!("{}オクターブ中{}オクターブ正解です", sections, completed),
+            Message::TimesUp => "時間切れです".to_string(),
+            Message::StartingEarTraining => "聴音トレーニングを始めます".to_string(),
+            Message::NewChord => "新しい和音".to_string(),
+            Message::PerfectMatch => "完璧です".to_string(),
+            Message::CorrectInterval(interval) => {
+                format!("音程は正解、{}", self.interval_name(&interval))
+            }
+            Message::HeresTheChord => "もう一度弾きます".to_string(),
+            Message::TryAgain => "惜しい、もう一度".to_string(),
+            Message::CorrectChord(chord) => format!("正解、{}", self.chord(&chord)),
+            Message::WrongVoicing => "和音は正解ですが、配置が違います".to_string(),
+            Message::NewMelody => "新しいメロディー".to_string(),
+            Message::HeresTheMelody => "メロディーをもう一度弾きます".to_string(),
+            Message::WrongMelodyNote {
+                position,
+                expected,
+                played,
+            } => format!(
+                "{}番目の音は{}ではなく{}です",
+                position,
+                self.note_name(&played),
+                self.note_name(&expected)
+            ),
+            Message::WrongMelodyOctave {
+                position,
+                note,
+                octaves,
+            } => {
+                let height = if octaves > 0 {
+                    "高すぎます"
+                } else {
+                    "低すぎます"
+                };
+                format!(
+                    "{}番目の音{}が{}オクターブ{}",
+                    position,
+                    self.note_name(&note),
+                    octaves.abs(),
+                    height
+                )
+            }
+            Message::NewProgression => "新しいコード進行".to_string(),
+            Message::HeresTheProgression => "コード進行をもう一度弾きます".to_string(),
+            Message::WrongProgressionChord {
+                position,
+                expected,
+                played,
+            } => match played {
+                Some(played) => format!(
+                    "{}番目の和音は{}ではなく{}です",
+                    position,
+                    self.roman_numeral(&played),
+                    self.roman_numeral(&expected)
+                ),
+                None => format!(
+                    "{}番目の和音は{}です",
+                    position,
+                    self.roman_numeral(&expected)
+                ),
+            },
+        }
+    }
+
+    fn chord(&self, chord: &ChordMatch) -> String {
+        let text = format!(
+            "{}{}",
+            self.note_name(&chord.root),
+            self.quality(chord.quality)
+        );
+        match chord.inversion {
+            Some(0) => text,
+            Some(1) => format!("{}、第一転回形", text),
+            Some(2) => format!("{}、第二転回形", text),
+            Some(3) => format!("{}、第三転回形", text),
+            Some(_) => format!("{}、第四転回形", text),
+            None => format!("{}オン{}", text, self.note_name(&chord.bass)),
+        }
+    }
+
+    fn mistake(&self, mistake: &Mistake, key: &Key) -> String {
+        let note = |k: u8| self.note_name(&key.spell(k % 12));
+        match *mistake {
+            Mistake::StartedOn { played, expected } => {
+                format!("{}ではなく{}から始めました", note(expected), note(played))
+            }
+            Mistake::Repeated { note: from, wanted } => format!(
+                "{}を繰り返しました。{}ところです",
+                note(from),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongStep { from, went, wanted } => format!(
+                "{}から{}が、正しくは{}です",
+                note(from),
+                self.went(went),
+                self.step(wanted.unsigned_abs())
+            ),
+            Mistake::WrongDirection { from, went, wanted } => format!(
+                "{}から{}が、正しくは{}ところです",
+                note(from),
+                self.went(went),
+                self.to_go(wanted)
+            ),
+            Mistake::WrongOctave { note: k, octaves } => {
+                let height = if octaves > 0 {
+                    "高すぎました"
+                } else {
+                    "低すぎました"
+                };
+                format!("{}が{}オクターブ{}", note(k), octaves.abs(), height)
+            }
+            Mistake::LeftOutFirst { note: k } => format!("最初の音、{}が抜けました", note(k)),
+            Mistake::LeftOut { note: k, after } => {
+                format!("{}の後の{}が抜けました", note(after), note(k))
+            }
+            Mistake::ExtraBefore { note: k } => {
+                format!("始める前に余分な{}を弾きました", note(k))
+            }
+            Mistake::Extra { note: k, after } => {
+                format!("{}の後に余分な{}を弾きました", note(after), note(k))
+            }
+            Mistake::Swapped { first, second } => {
+                format!("{}より先に{}を弾きました", note(first), note(second))
+            }
+            Mistake::NothingPlayed => "何も弾きませんでした".to_string(),
+            Mistake::Stopped { after, short } => {
+                format!("{}で止まりました。あと{}音です", note(after), short)
+            }
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use log::{info, warn};
+
+use super::{
+    backend::{SpeechBackend, DEFAULT_VOLUME},
+    locale::Locale,
+};
+
+// how often an utterance is checked for having finished or been cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Feedback on what was just played goes ahead of queued prompts
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Prompt,
+    Feedback,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Queued {
+    priority: Priority,
+    // order of arrival, so equal priorities are said first come first served
+    sequence: u64,
+    text: String,
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Queued) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Queued) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Default)]
+struct SpeakerState {
+    queue: BinaryHeap<Queued>,
+    current: Option<Priority>,
+    cancel_current: bool,
+    next_sequence: u64,
+}
+
+/// Says things on its own thread so callers can keep listening to keys.
+/// Clones share the same queue.
+#[derive(Clone)]
+pub struct Speaker {
+    shared: Arc<(Mutex<SpeakerState>, Condvar)>,
+    locale: Locale,
+}
+
+impl Speaker {
+    pub fn new(backend: Arc<dyn SpeechBackend>) -> Speaker {
+        Speaker::with_locale(backend, Locale::default())
+    }
+
+    pub fn with_locale(backend: Arc<dyn SpeechBackend>, locale: Locale) -> Speaker {
+        let speaker = Speaker {
+            shared: Arc::new((Mutex::new(SpeakerState::default()), Condvar::new())),
+            locale,
+        };
+        let service = speaker.clone();
+        std::thread::spawn(move || loop {
+            service.speak_next(backend.as_ref());
+        });
+        speaker
+    }
+
+    /// The language everything said through this speaker should be in
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SpeakerState> {
+        self.shared.0.lock().unwrap()
+    }
+
+    fn speak_next(&self, backend: &dyn SpeechBackend) {
+        let (_, changed) = &*self.shared;
+        let next = {
+            let mut state = changed
+                .wait_while(self.lock(), |state| state.queue.is_empty())
+                .unwrap();
+            let next = state.queue.pop().unwrap();
+            state.current = Some(next.priority);
+            state.cancel_current = false;
+            next
+        };
+
+        info!(
+            "attempting say ({}, {:?}): {}",
+            backend.name(),
+            next.priority,
+            next.text
+        );
+        match backend.speak(&next.text, Some(DEFAULT_VOLUME)) {
+            Ok(mut utterance) => loop {
+                if utterance.is_finished() {
+                    break;
+                }
+                let (state, _) = changed.wait_timeout(self.lock(), POLL_INTERVAL).unwrap();
+                if state.cancel_current {
+                    drop(state);
+                    info!("cancelled: {}", next.text);
+                    utterance.cancel();
+                    break;
+                }
+            },
+            Err(err) => warn!("couldn't say \"{}\": {}", next.text, err),
+        }
+
+        self.lock().current = None;
+        changed.notify_all();
+    }
+
+    /// Queues `text` without waiting for it to be said
+    pub fn say(&self, text: String, priority: Priority) {
+        let mut state = self.lock();
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.queue.push(Queued {
+            priority,
+            sequence,
+            text,
+        });
+        self.shared.1.notify_all();
+    }
+
+    /// Instructions for what to play next
+    pub fn prompt(&self, text: String) {
+        self.say(text, Priority::Prompt)
+    }
+
+    /// Reactions to what was just played
+    pub fn feedback(&self, text: String) {
+        self.say(text, Priority::Feedback)
+    }
+
+    /// Drops everything queued at or below `priority` and stops the current
+    /// utterance if it's one of them, e.g. to stop a prompt once the player
+    /// has started playing
+    pub fn cancel(&self, priority: Priority) {
+        let mut state = self.lock();
+        state.queue.retain(|queued| queued.priority > priority);
+        if state.current.is_some_and(|current| current <= priority) {
+            state.cancel_current = true;
+        }
+        self.shared.1.notify_all();
+    }
+
+    /// The highest priority of anything being said or waiting to be said
+    pub fn speaking(&self) -> Option<Priority> {
+        let state = self.lock();
+        state
+            .current
+            .into_iter()
+            .chain(state.queue.peek().map(|queued| queued.priority))
+            .max()
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.speaking().is_some()
+    }
+
+    /// Blocks until everything queued has been said
+    pub fn wait_until_idle(&self) {
+        let (_, changed) = &*self.shared;
+        let _idle = changed
+            .wait_while(self.lock(), |state| {
+                state.current.is_some() || !state.queue.is_empty()
+            })
+            .unwrap();
+    }
+}
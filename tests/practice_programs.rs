@@ -0,0 +1,686 @@
+//! Drives practice programs with scripted key messages and checks what they
+//! say, using RecordingSpeech instead of a real speech engine.
+
+use std::{
+    sync::{
+        mpsc::{channel, sync_channel, Receiver, SyncSender},
+        Arc,
+    },
+    thread,
+};
+
+use midi_hack::{
+    key_handler::{ControlMessage, KeyDb},
+    midi::{KeyMessage, MidiMessageTypes, Pedal, PedalMessage},
+    music::{ChordQuality, Direction, Interval, Key},
+    practice_program::{
+        ChordEarTrainingPracticeProgram, ChordProgressionPracticeProgram,
+        CircleOfFourthsPracticeProgram, EarTrainingPracticeProgram, EarTrainingStats, Earcons,
+        FreePlayPracticeProgram, Grade, MelodicDictationPracticeProgram, MelodySettings,
+        NotePlayer, ProgressionAnswer, Scheduler, Skill, Tally, VoicingStrictness,
+    },
+    speech::{Locale, RecordingSpeech, Speaker},
+};
+
+// big enough that nothing blocks on a full channel during a test
+const CHANNEL_SIZE: usize = 1024;
+
+/// Stands in for the key handler: stores key messages and starts a new run
+/// when the program asks for one
+struct Keyboard {
+    key_db: Arc<KeyDb>,
+    ctrl_receiver: Receiver<ControlMessage>,
+    speaker: Speaker,
+    timestamp: u64,
+}
+
+impl Keyboard {
+    fn new(
+        key_db: Arc<KeyDb>,
+        ctrl_receiver: Receiver<ControlMessage>,
+        speaker: Speaker,
+    ) -> Keyboard {
+        Keyboard {
+            key_db,
+            ctrl_receiver,
+            speaker,
+            timestamp: 0,
+        }
+    }
+
+    /// Presses and releases each key in turn, handing every message to
+    /// `on_keypress` and letting speech finish before the next one
+    fn play(&mut self, keys: &[u8], mut on_keypress: impl FnMut(KeyMessage)) {
+        for key in keys {
+            for message_type in [MidiMessageTypes::NoteOn, MidiMessageTypes::NoteOff] {
+                self.send(*key, message_type, &mut on_keypress);
+            }
+        }
+    }
+
+    /// Presses all of `keys`, then releases them
+    fn play_together(&mut self, keys: &[u8], mut on_keypress: impl FnMut(KeyMessage)) {
+        for message_type in [MidiMessageTypes::NoteOn, MidiMessageTypes::NoteOff] {
+            for key in keys {
+                self.send(*key, message_type, &mut on_keypress);
+            }
+        }
+    }
+
+    fn send(
+        &mut self,
+        key: u8,
+        message_type: MidiMessageTypes,
+        on_keypress: &mut impl FnMut(KeyMessage),
+    ) {
+        self.timestamp += 100_000;
+        let msg = KeyMessage {
+            timestamp: self.timestamp,
+            message_type,
+            key,
+            channel: 0,
+            velocity: 64,
+        };
+        self.key_db.push_msg(msg);
+        on_keypress(msg);
+        for ctrl in self.ctrl_receiver.try_iter() {
+            if let ControlMessage::NewRun = ctrl {
+                self.key_db.clear();
+            }
+        }
+        self.speaker.wait_until_idle();
+    }
+}
+
+/// Everything a program under test is built from, wired to a Keyboard, a
+/// RecordingSpeech and the receiving end of MIDI out for the test to check
+struct Harness {
+    ctrl_sender: SyncSender<ControlMessage>,
+    key_receiver: Receiver<KeyMessage>,
+    key_db: Arc<KeyDb>,
+    speaker: Speaker,
+    player: NotePlayer,
+    keyboard: Keyboard,
+    recording: RecordingSpeech,
+    midi_out_receiver: Receiver<KeyMessage>,
+}
+
+impl Harness {
+    fn new() -> Harness {
+        let recording = RecordingSpeech::new();
+        let speaker = Speaker::new(Arc::new(recording.clone()));
+        let key_db = Arc::new(KeyDb::new());
+        let (ctrl_sender, ctrl_receiver) = sync_channel(CHANNEL_SIZE);
+        let (midi_out_sender, midi_out_receiver) = sync_channel(CHANNEL_SIZE);
+        // tests hand key messages to on_keypress directly
+        let (_key_sender, key_receiver) = sync_channel(CHANNEL_SIZE);
+        Harness {
+            ctrl_sender,
+            key_receiver,
+            key_db: key_db.clone(),
+            speaker: speaker.clone(),
+            player: NotePlayer::untimed(midi_out_sender),
+            keyboard: Keyboard::new(key_db, ctrl_receiver, speaker),
+            recording,
+            midi_out_receiver,
+        }
+    }
+}
+
+#[test]
+fn free_play_announces_a_chord_held_by_the_sustain_pedal() {
+    let harness = Harness::new();
+    let mut program = FreePlayPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+    );
+    let (mut keyboard, recording) = (harness.keyboard, harness.recording);
+
+    keyboard.key_db.push_pedal(PedalMessage {
+        timestamp: 1,
+        pedal: Pedal::Sustain,
+        value: 127,
+        channel: 0,
+    });
+    keyboard.play(&[48, 64, 67], |msg| program.on_keypress(msg));
+    assert_eq!(recording.texts(), ["C major"]);
+}
+
+fn circle_of_fourths(
+    scheduler: Option<Scheduler>,
+) -> (CircleOfFourthsPracticeProgram, Keyboard, RecordingSpeech) {
+    let harness = Harness::new();
+    let program = CircleOfFourthsPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+        1,
+        Direction::UpAndDown,
+        None,
+        false,
+        scheduler,
+    );
+    (program, harness.keyboard, harness.recording)
+}
+
+#[test]
+fn circle_of_fourths_moves_on_after_a_correct_scale() {
+    let (mut program, mut keyboard, recording) = circle_of_fourths(None);
+    program.start();
+    keyboard.speaker.wait_until_idle();
+    assert_eq!(recording.texts(), ["play C mayjur"]);
+
+    keyboard.play(
+        &[60, 62, 64, 65, 67, 69, 71, 72, 71, 69, 67, 65, 64, 62, 60],
+        |msg| program.on_keypress(msg),
+    );
+    assert_eq!(recording.texts(), ["play C mayjur", "play F mayjur"]);
+}
+
+#[test]
+fn circle_of_fourths_asks_for_what_the_scheduler_has_due() {
+    let mut scheduler = Scheduler::new();
+    scheduler.review("major-scale:C", Grade::Easy);
+    let (mut program, mut keyboard, recording) = circle_of_fourths(Some(scheduler));
+    program.start();
+    keyboard.speaker.wait_until_idle();
+    assert_eq!(recording.texts(), ["play F mayjur"]);
+
+    keyboard.play(
+        &[65, 67, 69, 70, 72, 74, 76, 77, 76, 74, 72, 70, 69, 67, 65],
+        |msg| program.on_keypress(msg),
+    );
+    assert_eq!(recording.texts(), ["play F mayjur", "play B Flat mayjur"]);
+}
+
+#[test]
+fn circle_of_fourths_explains_a_wrong_note() {
+    let (mut program, mut keyboard, recording) = circle_of_fourths(None);
+    program.start();
+    keyboard.speaker.wait_until_idle();
+
+    // Bb instead of B
+    keyboard.play(
+        &[60, 62, 64, 65, 67, 69, 70, 72, 70, 69, 67, 65, 64, 62, 60],
+        |msg| program.on_keypress(msg),
+    );
+    assert_eq!(
+        recording.texts(),
+        [
+            "play C mayjur",
+            "you went up a half step on A instead of a whole step",
+            "play C mayjur",
+        ]
+    );
+}
+
+#[test]
+fn circle_of_fourths_says_how_much_of_a_run_was_right() {
+    let (mut program, mut keyboard, recording) = circle_of_fourths(None);
+    program.start();
+    keyboard.speaker.wait_until_idle();
+
+    // Ab instead of G on the way down
+    keyboard.play(
+        &[60, 62, 64, 65, 67, 69, 71, 72, 71, 69, 68, 65, 64, 62, 60],
+        |msg| program.on_keypress(msg),
+    );
+    assert_eq!(
+        recording.texts(),
+        [
+            "play C mayjur",
+            "you got 1 of 2 octaves right",
+            "you went down a half step on A instead of a whole step",
+            "play C mayjur",
+        ]
+    );
+}
+
+#[test]
+fn circle_of_fourths_points_out_the_wrong_key() {
+    let (mut program, mut keyboard, recording) = circle_of_fourths(None);
+    program.start();
+    keyboard.speaker.wait_until_idle();
+
+    keyboard.play(
+        &[67, 69, 71, 72, 74, 76, 78, 79, 78, 76, 74, 72, 71, 69, 67],
+        |msg| program.on_keypress(msg),
+    );
+    assert_eq!(
+        recording.texts(),
+        [
+            "play C mayjur",
+            "You've played a major scale but in the wrong key.",
+            "play C mayjur",
+        ]
+    );
+}
+
+fn ear_training(
+    earcons: Option<Earcons>,
+    stats: EarTrainingStats,
+) -> (
+    EarTrainingPracticeProgram,
+    Keyboard,
+    RecordingSpeech,
+    Receiver<KeyMessage>,
+) {
+    let harness = Harness::new();
+    let program = EarTrainingPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.player,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+        earcons,
+        false,
+        true,
+        stats,
+        None,
+    );
+    (
+        program,
+        harness.keyboard,
+        harness.recording,
+        harness.midi_out_receiver,
+    )
+}
+
+fn key_down(timestamp: u64, key: u8) -> KeyMessage {
+    KeyMessage {
+        timestamp,
+        message_type: MidiMessageTypes::NoteOn,
+        key,
+        channel: 0,
+        velocity: 64,
+    }
+}
+
+/// Keys the program has started playing since last checked
+fn notes_played(midi_out_receiver: &Receiver<KeyMessage>) -> Vec<u8> {
+    midi_out_receiver
+        .try_iter()
+        .filter(|msg| msg.message_type == MidiMessageTypes::NoteOn)
+        .map(|msg| msg.key)
+        .collect()
+}
+
+#[test]
+fn ear_training_recognizes_a_perfect_match() {
+    let (mut program, mut keyboard, recording, midi_out_receiver) =
+        ear_training(None, EarTrainingStats::new());
+
+    program.start();
+    let played = notes_played(&midi_out_receiver);
+    assert_eq!(played.len(), 2);
+    assert_eq!(recording.texts(), ["starting ear training", "new chord"]);
+
+    keyboard.play(&played, |msg| program.on_keypress(msg));
+    assert_eq!(
+        recording.texts(),
+        [
+            "starting ear training",
+            "new chord",
+            "perfect match",
+            "new chord"
+        ]
+    );
+}
+
+#[test]
+fn ear_training_takes_either_order_or_both_keys_together() {
+    let (mut program, mut keyboard, recording, midi_out_receiver) =
+        ear_training(None, EarTrainingStats::new());
+
+    program.start();
+    let mut played = notes_played(&midi_out_receiver);
+    played.reverse();
+    keyboard.play(&played, |msg| program.on_keypress(msg));
+
+    let played = notes_played(&midi_out_receiver);
+    keyboard.play_together(&played, |msg| program.on_keypress(msg));
+    assert_eq!(
+        recording.texts()[2..],
+        ["perfect match", "new chord", "perfect match", "new chord"]
+    );
+}
+
+#[test]
+fn ear_training_cues_answers_with_earcons() {
+    let earcons = Earcons::new(
+        "A5".parse().unwrap(),
+        "C5 E5".parse().unwrap(),
+        "C2+Db2".parse().unwrap(),
+    );
+    let (mut program, mut keyboard, recording, midi_out_receiver) =
+        ear_training(Some(earcons), EarTrainingStats::new());
+
+    program.start();
+    let played = notes_played(&midi_out_receiver);
+    assert_eq!(played[0], 81);
+    let pair = &played[1..];
+
+    // too far apart to be any interval that's asked for
+    keyboard.play(&[30, 100], |msg| program.on_keypress(msg));
+    assert_eq!(notes_played(&midi_out_receiver), [36, 37]);
+
+    keyboard.play(pair, |msg| program.on_keypress(msg));
+    let played = notes_played(&midi_out_receiver);
+    assert_eq!(played[..3], [72, 76, 81]);
+    assert_eq!(played.len(), 5);
+
+    assert_eq!(recording.texts(), ["starting ear training"]);
+}
+
+#[test]
+fn ear_training_ignores_keys_played_over_an_earcon() {
+    let harness = Harness::new();
+    let key_db = harness.key_db.clone();
+    // unbuffered, so the earcon can't be over before the keys played over
+    // it are in the key db
+    let (midi_out_sender, midi_out_receiver) = sync_channel::<KeyMessage>(0);
+    let (played_sender, played) = channel();
+    let (over_earcon_sender, over_earcon) = channel();
+    thread::spawn(move || {
+        for msg in midi_out_receiver {
+            if msg.message_type != MidiMessageTypes::NoteOn {
+                continue;
+            }
+            played_sender.send(msg.key).unwrap();
+            // the earcon for a right answer
+            if msg.key == 108 {
+                for (i, key) in [30, 100].into_iter().enumerate() {
+                    let down = key_down(10_000_000 + i as u64, key);
+                    key_db.push_msg(down);
+                    over_earcon_sender.send(down).unwrap();
+                }
+            }
+        }
+    });
+    let earcons = Earcons::new(
+        "A5".parse().unwrap(),
+        "C8".parse().unwrap(),
+        "C7+Db7".parse().unwrap(),
+    );
+    let mut program = EarTrainingPracticeProgram::new(
+        harness.ctrl_sender,
+        NotePlayer::untimed(midi_out_sender),
+        harness.key_receiver,
+        harness.key_db.clone(),
+        harness.speaker,
+        Some(earcons),
+        false,
+        true,
+        EarTrainingStats::new(),
+        None,
+    );
+
+    program.start();
+    let pair: Vec<u8> = played.iter().skip(1).take(2).collect();
+    for (i, key) in pair.into_iter().enumerate() {
+        let down = key_down(1_000 + i as u64, key);
+        harness.key_db.push_msg(down);
+        program.on_keypress(down);
+    }
+    // handled only now, as they would be after the earcon
+    for down in over_earcon.try_iter() {
+        program.on_keypress(down);
+    }
+    drop(program);
+    let played: Vec<u8> = played.iter().collect();
+    assert!(played.contains(&108));
+    assert!(!played.contains(&96), "{:?}", played);
+}
+
+#[test]
+fn ear_training_keeps_score_of_first_answers() {
+    let path = std::env::temp_dir().join(format!("midi_hack_stats_{}.tsv", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let (mut program, mut keyboard, _recording, midi_out_receiver) =
+        ear_training(None, EarTrainingStats::load(&path).unwrap());
+
+    program.start();
+    let played = notes_played(&midi_out_receiver);
+    keyboard.play(&[30, 100], |msg| program.on_keypress(msg));
+    // only the first answer to a question counts
+    keyboard.play(&played, |msg| program.on_keypress(msg));
+
+    let interval = Interval::from_semitones(i16::from(played[1]) - i16::from(played[0])).unwrap();
+    let stats = EarTrainingStats::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(
+        stats.tally(Skill::Interval(interval)),
+        Tally { right: 0, wrong: 1 }
+    );
+}
+
+fn chord_ear_training(
+    strictness: VoicingStrictness,
+) -> (
+    ChordEarTrainingPracticeProgram,
+    Keyboard,
+    RecordingSpeech,
+    Receiver<KeyMessage>,
+) {
+    let harness = Harness::new();
+    let program = ChordEarTrainingPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.player,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+        None,
+        vec![ChordQuality::Major],
+        vec![1],
+        strictness,
+        None,
+    );
+    (
+        program,
+        harness.keyboard,
+        harness.recording,
+        harness.midi_out_receiver,
+    )
+}
+
+#[test]
+fn chord_ear_training_accepts_any_voicing() {
+    let (mut program, mut keyboard, recording, midi_out_receiver) =
+        chord_ear_training(VoicingStrictness::AnyVoicing);
+
+    program.start();
+    let played = notes_played(&midi_out_receiver);
+    assert_eq!(played.len(), 3);
+
+    // root position an octave down instead of first inversion
+    let root = played[2] - 24;
+    keyboard.play_together(&[root, root + 4, root + 7], |msg| program.on_keypress(msg));
+    let texts = recording.texts();
+    assert_eq!(texts.len(), 4);
+    assert!(texts[2].starts_with("correct, "), "{}", texts[2]);
+    assert!(
+        texts[2].ends_with(" major, first inversion"),
+        "{}",
+        texts[2]
+    );
+}
+
+#[test]
+fn chord_ear_training_can_require_the_exact_voicing() {
+    let (mut program, mut keyboard, recording, midi_out_receiver) =
+        chord_ear_training(VoicingStrictness::Exact);
+
+    program.start();
+    let played = notes_played(&midi_out_receiver);
+
+    let root = played[2] - 12;
+    keyboard.play_together(&[root, root + 4, root + 7], |msg| program.on_keypress(msg));
+    assert_eq!(
+        recording.texts()[2..],
+        ["right chord, but not the same voicing"]
+    );
+
+    keyboard.play_together(&played, |msg| program.on_keypress(msg));
+    assert!(recording.texts()[3].starts_with("correct, "));
+}
+
+fn melodic_dictation() -> (
+    MelodicDictationPracticeProgram,
+    Keyboard,
+    RecordingSpeech,
+    Receiver<KeyMessage>,
+) {
+    let harness = Harness::new();
+    let program = MelodicDictationPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.player,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+        None,
+        MelodySettings {
+            key: Some(Key::C_MAJOR),
+            min_notes: 3,
+            max_notes: 3,
+            ..MelodySettings::default()
+        },
+    );
+    (
+        program,
+        harness.keyboard,
+        harness.recording,
+        harness.midi_out_receiver,
+    )
+}
+
+#[test]
+fn melodic_dictation_points_out_the_first_wrong_note() {
+    let (mut program, mut keyboard, recording, midi_out_receiver) = melodic_dictation();
+
+    program.start();
+    let melody = notes_played(&midi_out_receiver);
+    assert_eq!(melody, program.current_melody().keys());
+    assert_eq!(recording.texts(), ["starting ear training", "new melody"]);
+
+    keyboard.play(&[melody[0], melody[1] + 1], |msg| program.on_keypress(msg));
+    let texts = recording.texts();
+    assert!(texts[2].starts_with("note 2 should be "), "{}", texts[2]);
+
+    keyboard.play(&[melody[0], melody[1] - 12], |msg| program.on_keypress(msg));
+    let texts = recording.texts();
+    assert!(texts[3].ends_with(" an octave too low"), "{}", texts[3]);
+
+    keyboard.play(&melody, |msg| program.on_keypress(msg));
+    assert_eq!(recording.texts()[4..], ["perfect match", "new melody"]);
+}
+
+fn chord_progressions(
+    answer: ProgressionAnswer,
+    key: Key,
+    scheduler: Option<Scheduler>,
+) -> (
+    ChordProgressionPracticeProgram,
+    Keyboard,
+    RecordingSpeech,
+    Receiver<KeyMessage>,
+) {
+    let harness = Harness::new();
+    let program = ChordProgressionPracticeProgram::new(
+        harness.ctrl_sender,
+        harness.player,
+        harness.key_receiver,
+        harness.key_db,
+        harness.speaker,
+        None,
+        1,
+        answer,
+        Some(key),
+        scheduler,
+    );
+    (
+        program,
+        harness.keyboard,
+        harness.recording,
+        harness.midi_out_receiver,
+    )
+}
+
+#[test]
+fn chord_progressions_name_the_first_wrong_chord() {
+    let (mut program, mut keyboard, recording, _midi_out_receiver) =
+        chord_progressions(ProgressionAnswer::Chords, Key::C_MAJOR, None);
+
+    program.start();
+    assert_eq!(
+        recording.texts(),
+        ["starting ear training", "new progression"]
+    );
+    // every level 1 progression starts and ends on I
+    let chords = program.current_progression().chords.clone();
+    keyboard.play_together(&[60, 64, 67], |msg| program.on_keypress(msg));
+    keyboard.play_together(&[62, 65, 69], |msg| program.on_keypress(msg));
+    assert_eq!(
+        recording.texts()[2..],
+        [format!(
+            "chord 2 should be {}, not two minor",
+            Locale::English.roman_numeral(&chords[1])
+        )]
+    );
+
+    for chord in &chords {
+        let keys = chord.voicing(&Key::C_MAJOR, 48);
+        keyboard.play_together(&keys, |msg| program.on_keypress(msg));
+    }
+    assert_eq!(recording.texts()[3..], ["perfect match", "new progression"]);
+}
+
+#[test]
+fn chord_progressions_can_ask_for_the_bass_line() {
+    let (mut program, mut keyboard, recording, _midi_out_receiver) =
+        chord_progressions(ProgressionAnswer::BassLine, Key::C_MAJOR, None);
+
+    program.start();
+    keyboard.play(&[48, 50], |msg| program.on_keypress(msg));
+    assert!(recording.texts()[2].starts_with("note 2 should be "));
+
+    let roots: Vec<u8> = program
+        .current_progression()
+        .chords
+        .iter()
+        .map(|chord| 36 + chord.root(&Key::C_MAJOR).semitone())
+        .collect();
+    keyboard.play(&roots, |msg| program.on_keypress(msg));
+    assert_eq!(recording.texts()[3..], ["perfect match", "new progression"]);
+}
+
+#[test]
+fn chord_progressions_can_be_in_a_minor_key() {
+    let a_minor: Key = "A minor".parse().unwrap();
+    let (mut program, mut keyboard, recording, _midi_out_receiver) =
+        chord_progressions(ProgressionAnswer::Chords, a_minor, None);
+
+    program.start();
+    // every level 1 progression starts on i
+    assert_eq!(program.current_progression().chords[0].to_string(), "i");
+    for chord in &program.current_progression().chords.clone() {
+        let keys = chord.voicing(&a_minor, 48);
+        keyboard.play_together(&keys, |msg| program.on_keypress(msg));
+    }
+    assert_eq!(recording.texts()[2..], ["perfect match", "new progression"]);
+}
+
+#[test]
+fn chord_progressions_ask_for_what_the_scheduler_has_due() {
+    let mut scheduler = Scheduler::new();
+    for progression in ["I-IV-V-I", "I-V-I", "I-IV-I"] {
+        scheduler.review(&format!("progression:{}", progression), Grade::Easy);
+    }
+    let (mut program, _keyboard, _recording, _midi_out_receiver) =
+        chord_progressions(ProgressionAnswer::Chords, Key::C_MAJOR, Some(scheduler));
+
+    program.start();
+    assert_eq!(program.current_progression().to_string(), "I-IV-I-V-I");
+}